@@ -2,7 +2,11 @@ use std::{str::FromStr, sync::Arc};
 
 use futures::TryStreamExt;
 
-use crate::{block_on, IrohError, IrohNode};
+use crate::{
+    block_on,
+    key::{decode_hex_key, KEY_ENCODINGS},
+    IrohError, IrohNode,
+};
 
 /// Identifier for an [`Author`]
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -17,7 +21,17 @@ impl std::fmt::Display for AuthorId {
 impl AuthorId {
     /// Get an [`AuthorId`] from a String.
     pub fn from_string(str: String) -> Result<Self, IrohError> {
-        let author = iroh::docs::AuthorId::from_str(&str)?;
+        let author = iroh::docs::AuthorId::from_str(&str).map_err(|err| {
+            anyhow::anyhow!("invalid AuthorId {str:?}: {err}, expected {KEY_ENCODINGS}")
+        })?;
+        Ok(AuthorId(author))
+    }
+
+    /// Get an [`AuthorId`] from a hex string, as printed by some other tools.
+    pub fn from_hex(str: String) -> Result<Self, IrohError> {
+        let author = iroh::docs::AuthorId::from(decode_hex_key(&str, "AuthorId")?);
+        // make sure the bytes are a valid key
+        author.public_key().map_err(anyhow::Error::from)?;
         Ok(AuthorId(author))
     }
 
@@ -25,6 +39,15 @@ impl AuthorId {
     pub fn equal(&self, other: &AuthorId) -> bool {
         *self == *other
     }
+
+    /// Convert to a base32 string limited to the first 10 bytes for a friendly string
+    /// representation of the author.
+    ///
+    /// This is the same abbreviation the iroh CLI uses, and matches
+    /// [`crate::PublicKey::fmt_short`] for the same key.
+    pub fn fmt_short(&self) -> String {
+        self.0.fmt_short()
+    }
 }
 
 /// Author key to insert entries in a document
@@ -60,6 +83,7 @@ impl IrohNode {
     ///
     /// If you need only a single author, use [`Self::default`].
     pub fn author_create(&self) -> Result<Arc<AuthorId>, IrohError> {
+        self.state.ensure_running()?;
         block_on(&self.rt(), async {
             let author = self.sync_client.authors().create().await?;
 
@@ -67,6 +91,22 @@ impl IrohNode {
         })
     }
 
+    /// Create `count` new document authors in one call.
+    ///
+    /// Each author has a freshly generated key and is persisted like one created with
+    /// [`Self::author_create`]. Useful for provisioning and tests that need many authors.
+    pub fn author_create_many(&self, count: u32) -> Result<Vec<Arc<AuthorId>>, IrohError> {
+        self.state.ensure_running()?;
+        block_on(&self.rt(), async {
+            let authors = self.sync_client.authors();
+            let mut ids = Vec::with_capacity(count as usize);
+            for _ in 0..count {
+                ids.push(Arc::new(AuthorId(authors.create().await?)));
+            }
+            Ok(ids)
+        })
+    }
+
     /// Returns the default document author of this node.
     ///
     /// On persistent nodes, the author is created on first start and its public key is saved
@@ -74,6 +114,7 @@ impl IrohNode {
     ///
     /// The default author can be set with [`Self::set_default`].
     pub fn author_default(&self) -> Result<Arc<AuthorId>, IrohError> {
+        self.state.ensure_running()?;
         block_on(&self.rt(), async {
             let author = self.sync_client.authors().default().await?;
             Ok(Arc::new(AuthorId(author)))
@@ -82,6 +123,7 @@ impl IrohNode {
 
     /// List all the AuthorIds that exist on this node.
     pub fn author_list(&self) -> Result<Vec<Arc<AuthorId>>, IrohError> {
+        self.state.ensure_running()?;
         block_on(&self.rt(), async {
             let authors = self
                 .sync_client
@@ -99,6 +141,7 @@ impl IrohNode {
     ///
     /// Warning: This contains sensitive data.
     pub fn author_export(&self, author: Arc<AuthorId>) -> Result<Arc<Author>, IrohError> {
+        self.state.ensure_running()?;
         block_on(&self.rt(), async {
             let author = self.sync_client.authors().export(author.0).await?;
             match author {
@@ -112,6 +155,7 @@ impl IrohNode {
     ///
     /// Warning: This contains sensitive data.
     pub fn author_import(&self, author: Arc<Author>) -> Result<Arc<AuthorId>, IrohError> {
+        self.state.ensure_running()?;
         block_on(&self.rt(), async {
             self.sync_client.authors().import(author.0.clone()).await?;
             Ok(Arc::new(AuthorId(author.0.id())))
@@ -122,6 +166,7 @@ impl IrohNode {
     ///
     /// Warning: This permanently removes this author.
     pub fn author_delete(&self, author: Arc<AuthorId>) -> Result<(), IrohError> {
+        self.state.ensure_running()?;
         block_on(&self.rt(), async {
             self.sync_client.authors().delete(author.0).await?;
             Ok(())
@@ -148,4 +193,52 @@ mod tests {
         let authors = node.author_list().unwrap();
         assert_eq!(authors.len(), 2);
     }
+
+    #[test]
+    fn test_author_create_many() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().display().to_string();
+        let created = {
+            let node = crate::IrohNode::new(path.clone()).unwrap();
+            assert!(node.author_create_many(0).unwrap().is_empty());
+            node.author_create_many(5).unwrap()
+        };
+        let distinct: std::collections::HashSet<_> =
+            created.iter().map(|id| id.to_string()).collect();
+        assert_eq!(distinct.len(), 5);
+
+        // the authors survive a restart of the node
+        let node = crate::IrohNode::new(path).unwrap();
+        let listed: std::collections::HashSet<_> = node
+            .author_list()
+            .unwrap()
+            .iter()
+            .map(|id| id.to_string())
+            .collect();
+        assert!(distinct.is_subset(&listed));
+    }
+
+    #[test]
+    fn test_author_id_from_hex() {
+        let author_str = "mqtlzayyv4pb4xvnqnw5wxb2meivzq5ze6jihpa7fv5lfwdoya4q";
+        let author_hex = "6426bc8318af1e1e5ead836ddb5c3a61115cc3b9279283bc1f2d7ab2d86ec039";
+        let author = crate::AuthorId::from_string(author_str.to_string()).unwrap();
+        let from_hex = crate::AuthorId::from_hex(author_hex.to_uppercase()).unwrap();
+        assert!(author.equal(&from_hex));
+
+        // parse errors name the accepted encodings
+        let err = crate::AuthorId::from_string(author_hex.to_string()).unwrap_err();
+        assert!(err.message().contains("from_hex"));
+        assert!(crate::AuthorId::from_hex(author_str.to_string()).is_err());
+    }
+
+    #[test]
+    fn test_author_id_fmt_short() {
+        let author_str = "mqtlzayyv4pb4xvnqnw5wxb2meivzq5ze6jihpa7fv5lfwdoya4q";
+        let author = crate::AuthorId::from_string(author_str.to_string()).unwrap();
+        assert_eq!("mqtlzayyv4pb4xvn", author.fmt_short());
+        // the same key abbreviates the same way as a PublicKey
+        let key = crate::PublicKey::from_string(author_str.to_string()).unwrap();
+        assert_eq!(key.fmt_short(), author.fmt_short());
+    }
 }
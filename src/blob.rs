@@ -1,16 +1,28 @@
 use std::{
-    path::PathBuf,
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
     str::FromStr,
-    sync::{Arc, RwLock},
-    time::Duration,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    time::{Duration, Instant},
 };
 
-use futures::{StreamExt, TryStreamExt};
-use serde::{Deserialize, Serialize};
+use futures::{stream::FuturesUnordered, StreamExt, TryStreamExt};
+use iroh::client::MemIroh;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
-use crate::ticket::AddrInfoOptions;
-use crate::{block_on, IrohError, NodeAddr};
-use crate::{node::IrohNode, CallbackError};
+use crate::tag::set_tag;
+use crate::ticket::{refresh_node_addr, AddrInfoOptions, BlobTicket};
+use crate::{
+    block_on, IrohError, NodeAddr, PathPreference, ProgressReceiver, ProgressSender, PublicKey,
+};
+use crate::{
+    error::{callback_continue, ContentUnavailable},
+    node::{FailureLog, IrohNode, NodeState},
+    CallbackError,
+};
 
 impl IrohNode {
     /// List all complete blobs.
@@ -18,6 +30,7 @@ impl IrohNode {
     /// Note: this allocates for each `BlobListResponse`, if you have many `BlobListReponse`s this may be a prohibitively large list.
     /// Please file an [issue](https://github.com/n0-computer/iroh-ffi/issues/new) if you run into this issue
     pub fn blobs_list(&self) -> Result<Vec<Arc<Hash>>, IrohError> {
+        self.state.ensure_running()?;
         block_on(&self.rt(), async {
             let response = self.sync_client.blobs().list().await?;
 
@@ -30,30 +43,65 @@ impl IrohNode {
         })
     }
 
+    /// List all complete blobs, calling `cb` once for each hash.
+    ///
+    /// Unlike [`Self::blobs_list`] this does not collect the hashes into a list, so it can be
+    /// used to enumerate a large number of blobs. Returns once all blobs have been listed.
+    /// If the callback returns an error, enumeration stops, see `CallbackError`.
+    pub fn blobs_list_stream(&self, cb: Arc<dyn HashCallback>) -> Result<(), IrohError> {
+        self.state.ensure_running()?;
+        block_on(&self.rt(), async {
+            let mut response = self.sync_client.blobs().list().await?;
+            while let Some(info) = response.next().await {
+                let info = info?;
+                if !callback_continue(cb.on_hash(Arc::new(Hash(info.hash))))? {
+                    break;
+                }
+            }
+            Ok(())
+        })
+    }
+
     /// Get the size information on a single blob.
     ///
     /// Method only exists in FFI
     pub fn blobs_size(&self, hash: &Hash) -> Result<u64, IrohError> {
+        self.state.ensure_running()?;
         block_on(&self.rt(), async {
             let r = self.sync_client.blobs().read(hash.0).await?;
             Ok(r.size())
         })
     }
 
+    /// Get the status of each blob in `hashes`, in the same order.
+    ///
+    /// Reports whether each blob is complete, partially present or not present on this node,
+    /// with the size of the blobs that are present, e.g. to plan how much needs to be
+    /// downloaded.
+    pub fn blobs_status_many(&self, hashes: Vec<Arc<Hash>>) -> Result<Vec<BlobStatus>, IrohError> {
+        self.state.ensure_running()?;
+        block_on(&self.rt(), async {
+            let mut statuses = Vec::with_capacity(hashes.len());
+            for hash in hashes {
+                let status = self.sync_client.blobs().status(hash.0).await?;
+                statuses.push(status.into());
+            }
+            Ok(statuses)
+        })
+    }
+
     /// Read all bytes of single blob.
     ///
     /// This allocates a buffer for the full blob. Use only if you know that the blob you're
     /// reading is small. If not sure, use [`Self::blobs_size`] and check the size with
     /// before calling [`Self::blobs_read_to_bytes`].
     pub fn blobs_read_to_bytes(&self, hash: Arc<Hash>) -> Result<Vec<u8>, IrohError> {
+        self.state.ensure_running()?;
+        self.blob_access.touch(hash.0);
         block_on(&self.rt(), async {
-            let res = self
-                .sync_client
-                .blobs()
-                .read_to_bytes(hash.0)
-                .await
-                .map(|b| b.to_vec())?;
-            Ok(res)
+            let res = self.sync_client.blobs().read_to_bytes(hash.0).await;
+            let res = check_available(&self.sync_client, hash.0, res).await?;
+            Ok(res.to_vec())
         })
     }
 
@@ -72,14 +120,42 @@ impl IrohNode {
             None => None,
             Some(l) => Some(usize::try_from(l).map_err(anyhow::Error::from)?),
         };
+        self.state.ensure_running()?;
+        self.blob_access.touch(hash.0);
         block_on(&self.rt(), async {
             let res = self
                 .sync_client
                 .blobs()
                 .read_at_to_bytes(hash.0, offset, len)
-                .await
-                .map(|b| b.to_vec())?;
-            Ok(res)
+                .await;
+            let res = check_available(&self.sync_client, hash.0, res).await?;
+            Ok(res.to_vec())
+        })
+    }
+
+    /// Open a blob for reading at arbitrary positions, e.g. to play back media.
+    ///
+    /// Fails if none of the blob's data is present on this node. The blob may be partial, see
+    /// [`BlobReader`].
+    pub fn blobs_open_reader(&self, hash: Arc<Hash>) -> Result<Arc<BlobReader>, IrohError> {
+        self.state.ensure_running()?;
+        self.blob_access.touch(hash.0);
+        block_on(&self.rt(), async {
+            let size = match self.sync_client.blobs().status(hash.0).await? {
+                iroh::client::blobs::BlobStatus::NotFound => {
+                    return Err(anyhow::anyhow!("blob {} not found", hash.0).into())
+                }
+                iroh::client::blobs::BlobStatus::Partial { size } => size.value(),
+                iroh::client::blobs::BlobStatus::Complete { size } => size,
+            };
+            Ok(Arc::new(BlobReader {
+                client: self.sync_client.clone(),
+                rt: self.rt(),
+                state: self.state.clone(),
+                hash: hash.0,
+                size,
+                position: Mutex::new(0),
+            }))
         })
     }
 
@@ -89,6 +165,11 @@ impl IrohNode {
     /// the node runs.
     /// If `in_place` is true, Iroh will assume that the data will not change and will share it in
     /// place without copying to the Iroh data directory.
+    ///
+    /// If `strategy` is set, it decides how the files get into the store instead of `in_place`,
+    /// see [`ImportStrategy`].
+    ///
+    /// Returns the hash of each imported file, named by its path on the node's file system.
     pub fn blobs_add_from_path(
         &self,
         path: String,
@@ -96,76 +177,600 @@ impl IrohNode {
         tag: Arc<SetTagOption>,
         wrap: Arc<WrapOption>,
         cb: Arc<dyn AddCallback>,
-    ) -> Result<(), IrohError> {
-        block_on(&self.rt(), async {
+        strategy: Option<ImportStrategy>,
+    ) -> Result<Vec<LinkAndName>, IrohError> {
+        self.state.ensure_running()?;
+        let strategy = ImportStrategy::resolve(strategy, in_place);
+        let source = PathBuf::from(path);
+        let (path, in_place, staging) = self.hard_links.prepare(source.clone(), strategy);
+        // report the names of staged files by their source path
+        let staged = path.display().to_string();
+        let source = source.display().to_string();
+        let unstage = |name: String| {
+            if staging.is_some() {
+                if let Some(rest) = name.strip_prefix(&staged) {
+                    return format!("{source}{rest}");
+                }
+            }
+            name
+        };
+        let mut hashes = Vec::new();
+        let res = block_on(&self.rt(), async {
             let mut stream = self
                 .sync_client
                 .blobs()
                 .add_from_path(
-                    path.into(),
+                    path,
                     in_place,
                     (*tag).clone().into(),
                     (*wrap).clone().into(),
                 )
                 .await?;
+            let mut names = HashMap::new();
+            let mut files = Vec::new();
             while let Some(progress) = stream.next().await {
-                let progress = progress?;
-                cb.progress(Arc::new(progress.into()))?;
+                let mut progress: AddProgress = progress?.into();
+                match progress {
+                    AddProgress::Found(ref mut found) => {
+                        found.name = unstage(std::mem::take(&mut found.name));
+                        names.insert(found.id, found.name.clone());
+                    }
+                    AddProgress::Done(ref done) => {
+                        hashes.push(done.hash.0);
+                        if let Some(name) = names.remove(&done.id) {
+                            files.push(LinkAndName {
+                                name,
+                                link: done.hash.clone(),
+                            });
+                        }
+                    }
+                    _ => {}
+                }
+                if !callback_continue(cb.progress(Arc::new(progress)))? {
+                    break;
+                }
             }
+            Ok(files)
+        });
+        if let Some(staging) = staging {
+            self.hard_links.record(staging, hashes)?;
+        }
+        res
+    }
+
+    /// Import a blob from a filesystem path, pulling the progress events from a stream.
+    ///
+    /// Like [`Self::blobs_add_from_path`], but instead of passing the events to a callback the
+    /// import runs in the background and the events are read with [`AddProgressStream::next`].
+    /// Dropping the stream cancels the import.
+    pub fn blobs_add_from_path_stream(
+        self: Arc<Self>,
+        path: String,
+        in_place: bool,
+        tag: Arc<SetTagOption>,
+        wrap: Arc<WrapOption>,
+        strategy: Option<ImportStrategy>,
+    ) -> Result<Arc<AddProgressStream>, IrohError> {
+        self.state.ensure_running()?;
+        let rt = self.rt();
+        let events = ProgressReceiver::spawn(&rt, move |cb| {
+            self.blobs_add_from_path(path, in_place, tag, wrap, cb, strategy)?;
             Ok(())
-        })
+        });
+        Ok(Arc::new(AddProgressStream(events)))
+    }
+
+    /// Preview what [`Self::blobs_add_from_path`] would import from `path`, without storing
+    /// anything.
+    ///
+    /// Walks `path` and hashes every file, reporting `Found`, `Progress` and `Done` events
+    /// to `cb` like an import would. Symlinks below `path` are skipped, as in an import. The store is only read, to check which of the files
+    /// already exist as complete blobs on this node.
+    pub fn blobs_add_from_path_dry_run(
+        &self,
+        path: String,
+        cb: Arc<dyn AddCallback>,
+    ) -> Result<AddDryRunSummary, IrohError> {
+        self.state.ensure_running()?;
+        let existing = block_on(&self.rt(), async {
+            let hashes = self
+                .sync_client
+                .blobs()
+                .list()
+                .await?
+                .map_ok(|info| info.hash)
+                .try_collect::<std::collections::HashSet<_>>()
+                .await?;
+            anyhow::Ok(hashes)
+        })?;
+
+        let root = Path::new(&path);
+        let mut files = Vec::new();
+        collect_files(root, &mut files).map_err(anyhow::Error::from)?;
+
+        let mut summary = AddDryRunSummary {
+            files: Vec::with_capacity(files.len()),
+            size: 0,
+            existing_files: 0,
+            existing_size: 0,
+        };
+        'files: for (id, file) in files.into_iter().enumerate() {
+            let id = id as u64;
+            let name = import_name(root, &file);
+            let size = std::fs::metadata(&file).map_err(anyhow::Error::from)?.len();
+            if !callback_continue(cb.progress(Arc::new(AddProgress::Found(AddProgressFound {
+                id,
+                name: name.clone(),
+                size,
+            }))))? {
+                break;
+            }
+
+            let mut hasher = blake3::Hasher::new();
+            let mut reader = std::fs::File::open(&file).map_err(anyhow::Error::from)?;
+            let mut buf = vec![0u8; 64 * 1024];
+            let mut offset = 0;
+            loop {
+                let n = std::io::Read::read(&mut reader, &mut buf).map_err(anyhow::Error::from)?;
+                if n == 0 {
+                    break;
+                }
+                hasher.update(&buf[..n]);
+                offset += n as u64;
+                if !callback_continue(cb.progress(Arc::new(AddProgress::Progress(
+                    AddProgressProgress { id, offset },
+                ))))? {
+                    break 'files;
+                }
+            }
+            let hash = iroh::blobs::Hash::from_bytes(*hasher.finalize().as_bytes());
+            let done =
+                callback_continue(cb.progress(Arc::new(AddProgress::Done(AddProgressDone {
+                    id,
+                    hash: Arc::new(hash.into()),
+                }))))?;
+
+            summary.size += size;
+            if existing.contains(&hash) {
+                summary.existing_files += 1;
+                summary.existing_size += size;
+            }
+            summary.files.push(LinkAndName {
+                name,
+                link: Arc::new(hash.into()),
+            });
+            if !done {
+                break;
+            }
+        }
+        Ok(summary)
     }
 
     /// Export the blob contents to a file path
     /// The `path` field is expected to be the absolute path.
+    ///
+    /// The file is written atomically: `path` either keeps its previous content or holds the
+    /// complete blob, even if the export is interrupted.
+    ///
+    /// If a modification time was stored for the blob with [`Self::blobs_set_metadata`], it is
+    /// applied to the written file.
     pub fn blobs_write_to_path(&self, hash: Arc<Hash>, path: String) -> Result<(), IrohError> {
+        self.state.ensure_running()?;
+        self.blob_access.touch(hash.0);
         block_on(&self.rt(), async {
-            let mut reader = self.sync_client.blobs().read(hash.0).await?;
             let path: PathBuf = path.into();
-            if let Some(dir) = path.parent() {
-                tokio::fs::create_dir_all(dir)
-                    .await
-                    .map_err(anyhow::Error::from)?;
-            }
-            let mut file = tokio::fs::File::create(path)
-                .await
-                .map_err(anyhow::Error::from)?;
-            tokio::io::copy(&mut reader, &mut file)
-                .await
-                .map_err(anyhow::Error::from)?;
+            let res = write_atomic(&path, |tmp_path| async move {
+                let mut reader = self.sync_client.blobs().read(hash.0).await?;
+                let mut file = tokio::fs::File::create(tmp_path).await?;
+                tokio::io::copy(&mut reader, &mut file).await?;
+                Ok(())
+            })
+            .await;
+            check_available(&self.sync_client, hash.0, res).await?;
+            self.apply_modified(&hash.0, &path)?;
             Ok(())
         })
     }
 
     /// Write a blob by passing bytes.
     pub fn blobs_add_bytes(&self, bytes: Vec<u8>) -> Result<BlobAddOutcome, IrohError> {
+        self.state.ensure_running()?;
         block_on(&self.rt(), async {
             let res = self.sync_client.blobs().add_bytes(bytes).await?;
             Ok(res.into())
         })
     }
 
+    /// Write a blob by passing bytes, setting a named tag.
+    ///
+    /// Returns the same outcome as [`Self::blobs_add_bytes`], with `tag` set to `name`. Fails
+    /// if `name` starts with `auto-`, which is reserved for automatically generated tags.
+    pub fn blobs_add_bytes_named(
+        &self,
+        bytes: Vec<u8>,
+        name: Vec<u8>,
+    ) -> Result<BlobAddOutcome, IrohError> {
+        self.state.ensure_running()?;
+        check_tag_name(&name)?;
+        block_on(&self.rt(), async {
+            let res = self
+                .sync_client
+                .blobs()
+                .add_bytes_named(bytes, iroh::blobs::Tag(name.into()))
+                .await?;
+            Ok(res.into())
+        })
+    }
+
     /// Download a blob from another node and add it to the local database.
+    ///
+    /// The nodes of `opts` are tried one after the other, see [`BlobDownloadOptions::with_nodes`]:
+    /// if a download from a node fails, it is retried up to `max_attempts_per_node` times before
+    /// moving on to the next node. Retries on the same node wait half a second more each time,
+    /// the next node is tried right away. Data received in a failed attempt is kept for the
+    /// next one.
+    /// Only the failure of the last attempt is reported to `cb` as `DownloadProgress::Abort`,
+    /// and the `DownloadProgress::AllDone` event names the node that served the data.
+    ///
+    /// If the byte budget of `opts` is reached, the download stops with a
+    /// `DownloadProgress::BudgetExhausted` event and returns successfully, keeping the data
+    /// received so far, see [`BlobDownloadConfig::byte_budget`].
     pub fn blobs_download(
         &self,
         hash: Arc<Hash>,
         opts: Arc<BlobDownloadOptions>,
         cb: Arc<dyn DownloadCallback>,
     ) -> Result<(), IrohError> {
+        self.state.ensure_running()?;
         block_on(&self.rt(), async {
-            let mut stream = self
-                .sync_client
-                .blobs()
-                .download_with_opts(hash.0, opts.0.clone())
-                .await?;
-            while let Some(progress) = stream.next().await {
-                let progress = progress?;
-                cb.progress(Arc::new(progress.into()))?;
+            let nodes = opts.ordered_nodes(&self.sync_client).await;
+            let attempts = nodes.len() * opts.max_attempts_per_node as usize;
+            let mut state = DownloadState::new(self.downloads.register(hash.0));
+            self.download_sources
+                .set(hash.0, DownloadSource::new(&opts.opts))?;
+            let mut completed = false;
+            let mut budget_exhausted = false;
+            if !matches!(
+                self.sync_client.blobs().status(hash.0).await?,
+                iroh::client::blobs::BlobStatus::NotFound
+            ) {
+                state.preexisting.insert(hash.0);
             }
-            Ok(())
+            let mut res: Result<(), IrohError> =
+                Err(anyhow::anyhow!("no node to download from").into());
+            let mut attempt = 0;
+            'nodes: for node in nodes {
+                for retry in 0..opts.max_attempts_per_node {
+                    if retry > 0 {
+                        // give a node that just failed a moment before asking it again
+                        tokio::time::sleep(DOWNLOAD_RETRY_DELAY * retry).await;
+                    }
+                    attempt += 1;
+                    let last = attempt == attempts;
+                    match self
+                        .download_attempt(hash.0, &opts, node.clone(), &mut state, &cb, last)
+                        .await
+                    {
+                        DownloadAttempt::Done => {
+                            res = Ok(());
+                            completed = true;
+                            break 'nodes;
+                        }
+                        DownloadAttempt::Failed(err) => {
+                            tracing::debug!(
+                                "download of {} from {} failed: {:?}",
+                                hash.0,
+                                node.node_id,
+                                err
+                            );
+                            res = Err(err);
+                        }
+                        DownloadAttempt::Stopped(err) => {
+                            res = err.map_or(Ok(()), Err);
+                            break 'nodes;
+                        }
+                        DownloadAttempt::BudgetExhausted => {
+                            res = Ok(());
+                            budget_exhausted = true;
+                            break 'nodes;
+                        }
+                    }
+                }
+            }
+            self.downloads.unregister(hash.0, &state.transfer);
+            // the streams of all attempts are dropped, so no download writes to partial blobs
+            let keep_partial = opts.keep_partial_on_abort || budget_exhausted;
+            if !keep_partial {
+                for hash in state.partial.difference(&state.preexisting) {
+                    // another download of the same hash is still writing to it
+                    if self.downloads.get(hash).is_some() {
+                        continue;
+                    }
+                    if let Err(err) = self.sync_client.blobs().delete_blob(*hash).await {
+                        tracing::warn!("failed to remove partial blob {hash}: {err:?}");
+                    }
+                }
+            }
+            if completed || !keep_partial {
+                self.download_sources.remove(&hash.0)?;
+            }
+            res
+        })
+    }
+
+    /// List the downloads started with [`Self::blobs_download`] that did not complete.
+    ///
+    /// This includes downloads that failed or were cancelled, also in an earlier run of the
+    /// node, as long as their partial data was kept, see
+    /// [`BlobDownloadConfig::keep_partial_on_abort`]. Each one remembers the nodes it was downloading from and
+    /// can be continued with [`ResumableDownload::resume`], which only fetches the missing data.
+    pub fn blobs_resumable_downloads(
+        self: Arc<Self>,
+    ) -> Result<Vec<Arc<ResumableDownload>>, IrohError> {
+        self.state.ensure_running()?;
+        block_on(&self.rt(), async {
+            let mut incomplete = HashMap::new();
+            let mut blobs = self.sync_client.blobs().list_incomplete().await?;
+            while let Some(blob) = blobs.next().await {
+                let blob = blob?;
+                incomplete.insert(blob.hash, (blob.size, blob.expected_size));
+            }
+
+            let mut downloads = Vec::new();
+            for (hash, source) in self.download_sources.list() {
+                if self.downloads.get(&hash).is_some() {
+                    continue;
+                }
+                let (size, expected_size) = match incomplete.get(&hash) {
+                    Some(sizes) => *sizes,
+                    None => match self.sync_client.blobs().status(hash).await? {
+                        iroh::client::blobs::BlobStatus::NotFound => (0, 0),
+                        iroh::client::blobs::BlobStatus::Partial { size } => (0, size.value()),
+                        iroh::client::blobs::BlobStatus::Complete { size } => {
+                            // a raw blob completed by another download
+                            if !source.format.is_hash_seq() {
+                                self.download_sources.remove(&hash)?;
+                                continue;
+                            }
+                            (size, size)
+                        }
+                    },
+                };
+                downloads.push(Arc::new(ResumableDownload {
+                    node: self.clone(),
+                    hash,
+                    size,
+                    expected_size,
+                    source,
+                }));
+            }
+            Ok(downloads)
+        })
+    }
+
+    /// Wait until a node is found that provides the complete content of `hash`.
+    ///
+    /// Content is not announced in iroh, so providers are discovered by asking the nodes this
+    /// node knows: the peers listed by [`IrohNode::connections`], which includes nodes added
+    /// with [`IrohNode::add_node_addr`] or found through node discovery, and the nodes recorded
+    /// for a resumable download of `hash`. Each candidate is asked for the verified size of the
+    /// blob, which transfers only its last chunk. The candidates are asked concurrently, so an
+    /// unreachable node does not hold up the others.
+    ///
+    /// The list of candidates is refreshed every second, and new candidates are asked right
+    /// away. A candidate that does not have the content, or cannot be reached, is asked again
+    /// after a backoff that starts at two seconds and doubles up to thirty seconds.
+    ///
+    /// Returns the address of the first provider found, or errors after `timeout_millis`.
+    pub fn blobs_await_provider(
+        &self,
+        hash: Arc<Hash>,
+        timeout_millis: u64,
+    ) -> Result<Arc<NodeAddr>, IrohError> {
+        self.state.ensure_running()?;
+        block_on(&self.rt(), async {
+            let find = async {
+                let endpoint = self.node.endpoint();
+                let target = hash.0;
+                let mut probes = FuturesUnordered::new();
+                let mut probing = HashSet::new();
+                // when a candidate may be asked again, and the backoff that got it there
+                let mut backoff: HashMap<iroh::net::key::PublicKey, (Instant, Duration)> =
+                    HashMap::new();
+                loop {
+                    let now = Instant::now();
+                    for addr in provider_candidates(self, &target).await? {
+                        let due = backoff
+                            .get(&addr.node_id)
+                            .map_or(true, |(at, _)| *at <= now);
+                        if due && probing.insert(addr.node_id) {
+                            probes.push(async move {
+                                let found = has_content(endpoint, addr.clone(), &target).await;
+                                (addr, found)
+                            });
+                        }
+                    }
+                    let refresh = tokio::time::sleep(PROVIDER_POLL_INTERVAL);
+                    tokio::pin!(refresh);
+                    loop {
+                        tokio::select! {
+                            Some((addr, found)) = probes.next() => {
+                                if found {
+                                    return anyhow::Ok(addr);
+                                }
+                                probing.remove(&addr.node_id);
+                                let delay = backoff.get(&addr.node_id).map_or(
+                                    PROVIDER_BACKOFF_MIN,
+                                    |(_, delay)| (*delay * 2).min(PROVIDER_BACKOFF_MAX),
+                                );
+                                backoff.insert(addr.node_id, (Instant::now() + delay, delay));
+                            }
+                            _ = &mut refresh => break,
+                        }
+                    }
+                }
+            };
+            let addr = tokio::time::timeout(Duration::from_millis(timeout_millis), find)
+                .await
+                .map_err(|_| {
+                    anyhow::anyhow!("no provider of {} found within {timeout_millis}ms", hash.0)
+                })??;
+            Ok(Arc::new(addr.into()))
         })
     }
 
+    /// Download a blob from another node, pulling the progress events from a stream.
+    ///
+    /// Like [`Self::blobs_download`], but instead of passing the events to a callback the
+    /// download runs in the background and the events are read with
+    /// [`DownloadProgressStream::next`]. Dropping the stream cancels the download.
+    pub fn blobs_download_stream(
+        self: Arc<Self>,
+        hash: Arc<Hash>,
+        opts: Arc<BlobDownloadOptions>,
+    ) -> Result<Arc<DownloadProgressStream>, IrohError> {
+        self.state.ensure_running()?;
+        let rt = self.rt();
+        let events = ProgressReceiver::spawn(&rt, move |cb| self.blobs_download(hash, opts, cb));
+        Ok(Arc::new(DownloadProgressStream(events)))
+    }
+
+    /// Download `hash` from a single node, as one attempt of [`Self::blobs_download`].
+    ///
+    /// `last` is whether no further attempt follows if this one fails, only then an abort
+    /// is passed on to `cb` and the subscribers of the download.
+    async fn download_attempt(
+        &self,
+        hash: iroh::blobs::Hash,
+        opts: &BlobDownloadOptions,
+        node: iroh::net::endpoint::NodeAddr,
+        state: &mut DownloadState,
+        cb: &Arc<dyn DownloadCallback>,
+        last: bool,
+    ) -> DownloadAttempt {
+        let DownloadState {
+            transfer,
+            partial,
+            preexisting,
+            received,
+        } = state;
+        let node_id = node.node_id;
+        let mut download = opts.opts.clone();
+        download.nodes = vec![node];
+        let mut stream = match self
+            .sync_client
+            .blobs()
+            .download_with_opts(hash, download)
+            .await
+        {
+            Ok(stream) => stream,
+            Err(err) => return DownloadAttempt::Failed(err.into()),
+        };
+        // progress ids are only unique within an attempt
+        let mut ids = HashMap::new();
+        while let Some(progress) = stream.next().await {
+            let progress = match progress {
+                Ok(progress) => progress,
+                Err(err) => return DownloadAttempt::Failed(err.into()),
+            };
+            match &progress {
+                // local data of a blob not seen before in this download predates it
+                iroh::blobs::get::db::DownloadProgress::FoundLocal { hash, .. } => {
+                    if !partial.contains(hash) {
+                        preexisting.insert(*hash);
+                    }
+                }
+                iroh::blobs::get::db::DownloadProgress::Found { id, hash, size, .. } => {
+                    ids.insert(*id, *hash);
+                    partial.insert(*hash);
+                    if let Some(max) = self.max_download_blob_size.filter(|max| size > max) {
+                        let error = format!(
+                            "blob {hash} of {size} bytes exceeds the maximum download blob size of {max} bytes"
+                        );
+                        let abort = DownloadProgress::Abort(DownloadProgressAbort {
+                            error: error.clone(),
+                        });
+                        transfer.forward(&abort);
+                        cb.progress(Arc::new(abort)).ok();
+                        return DownloadAttempt::Stopped(Some(anyhow::anyhow!(error).into()));
+                    }
+                }
+                iroh::blobs::get::db::DownloadProgress::Progress { id, offset } => {
+                    if let Some(hash) = ids.get(id) {
+                        received.insert(*hash, *offset);
+                    }
+                }
+                iroh::blobs::get::db::DownloadProgress::Done { id } => {
+                    if let Some(hash) = ids.remove(id) {
+                        partial.remove(&hash);
+                    }
+                }
+                iroh::blobs::get::db::DownloadProgress::Abort(err) => {
+                    let err = anyhow::anyhow!("{err}").into();
+                    if last {
+                        transfer.on_progress(&progress);
+                        cb.progress(Arc::new(progress.into())).ok();
+                    }
+                    return DownloadAttempt::Failed(err);
+                }
+                _ => {}
+            }
+            transfer.on_progress(&progress);
+            let mut progress: DownloadProgress = progress.into();
+            if let DownloadProgress::AllDone(ref mut done) = progress {
+                done.served_by = Some(Arc::new(node_id.into()));
+            }
+            match callback_continue(cb.progress(Arc::new(progress))) {
+                Ok(true) => {}
+                Ok(false) => return DownloadAttempt::Stopped(None),
+                Err(err) => return DownloadAttempt::Stopped(Some(err)),
+            }
+            if let Some(budget) = opts.byte_budget {
+                let bytes = received.values().sum::<u64>();
+                if bytes >= budget {
+                    let exhausted =
+                        DownloadProgress::BudgetExhausted(DownloadProgressBudgetExhausted {
+                            bytes,
+                            budget,
+                        });
+                    transfer.forward(&exhausted);
+                    cb.progress(Arc::new(exhausted)).ok();
+                    return DownloadAttempt::BudgetExhausted;
+                }
+            }
+        }
+        DownloadAttempt::Done
+    }
+
+    /// Attach to a download of `hash` that is currently running through
+    /// [`Self::blobs_download`] on this node.
+    ///
+    /// The callback first receives a `DownloadProgress::InitialState` event with the current
+    /// state of the transfer, followed by all further progress events of the download. This
+    /// method returns once the initial state was delivered; if the callback returns an error,
+    /// it is detached from the download, without affecting the download itself.
+    ///
+    /// Only downloads started with [`Self::blobs_download`] or [`Self::blobs_download_stream`]
+    /// can be attached to. Content fetched by document sync, or by [`Doc::download_all`], is
+    /// downloaded by iroh directly and is not covered.
+    ///
+    /// Returns an error if no download for `hash` is running.
+    ///
+    /// [`Doc::download_all`]: crate::Doc::download_all
+    pub fn blobs_subscribe_download(
+        &self,
+        hash: Arc<Hash>,
+        cb: Arc<dyn DownloadCallback>,
+    ) -> Result<(), IrohError> {
+        self.state.ensure_running()?;
+        let transfer = self
+            .downloads
+            .get(&hash.0)
+            .ok_or_else(|| anyhow::anyhow!("no download running for {}", hash.0))?;
+        callback_continue(transfer.subscribe(cb))?;
+        Ok(())
+    }
+
     /// Export a blob from the internal blob store to a path on the node's filesystem.
     ///
     /// `destination` should be a writeable, absolute path on the local node's filesystem.
@@ -175,6 +780,14 @@ impl IrohNode {
     ///
     /// The `mode` argument defines if the blob should be copied to the target location or moved out of
     /// the internal store into the target location. See [`ExportMode`] for details.
+    ///
+    /// Single blobs exported with [`BlobExportMode::Copy`] are written atomically: `destination`
+    /// either keeps its previous content or holds the complete blob. Collections and
+    /// [`BlobExportMode::TryReference`] exports are written to their destination directly, since
+    /// the store may keep referencing the exported files.
+    ///
+    /// If a modification time was stored for a single blob with [`Self::blobs_set_metadata`],
+    /// it is applied to the exported file.
     pub fn blobs_export(
         &self,
         hash: Arc<Hash>,
@@ -182,21 +795,38 @@ impl IrohNode {
         format: BlobExportFormat,
         mode: BlobExportMode,
     ) -> Result<(), IrohError> {
+        self.state.ensure_running()?;
+        self.blob_access.touch(hash.0);
         block_on(&self.rt(), async {
             let destination: PathBuf = destination.into();
-            if let Some(dir) = destination.parent() {
-                tokio::fs::create_dir_all(dir)
-                    .await
-                    .map_err(anyhow::Error::from)?;
+            let atomic = matches!(
+                (&format, &mode),
+                (BlobExportFormat::Blob, BlobExportMode::Copy)
+            );
+            let single = matches!(format, BlobExportFormat::Blob);
+            let export = |destination: PathBuf| async move {
+                self.sync_client
+                    .blobs()
+                    .export(hash.0, destination, format.into(), mode.into())
+                    .await?
+                    .finish()
+                    .await?;
+                Ok(())
+            };
+
+            if atomic {
+                write_atomic(&destination, export).await?;
+            } else {
+                if let Some(dir) = destination.parent() {
+                    tokio::fs::create_dir_all(dir)
+                        .await
+                        .map_err(anyhow::Error::from)?;
+                }
+                export(destination.clone()).await?;
+            }
+            if single {
+                self.apply_modified(&hash.0, &destination)?;
             }
-
-            let stream = self
-                .sync_client
-                .blobs()
-                .export(hash.0, destination, format.into(), mode.into())
-                .await?;
-
-            stream.finish().await?;
 
             Ok(())
         })
@@ -209,6 +839,7 @@ impl IrohNode {
         blob_format: BlobFormat,
         ticket_options: AddrInfoOptions,
     ) -> Result<String, IrohError> {
+        self.state.ensure_running()?;
         block_on(&self.rt(), async {
             let ticket = self
                 .sync_client
@@ -219,71 +850,215 @@ impl IrohNode {
         })
     }
 
-    /// List all incomplete (partial) blobs.
+    /// Create a ticket for sharing a blob from this node that is as small as possible.
     ///
-    /// Note: this allocates for each `BlobListIncompleteResponse`, if you have many `BlobListIncompleteResponse`s this may be a prohibitively large list.
-    /// Please file an [issue](https://github.com/n0-computer/iroh-ffi/issues/new) if you run into this issue
-    pub fn blobs_list_incomplete(&self) -> Result<Vec<IncompleteBlobInfo>, IrohError> {
-        block_on(&self.rt(), async {
-            let blobs = self
-                .sync_client
-                .blobs()
-                .list_incomplete()
-                .await?
-                .map_ok(|res| res.into())
-                .try_collect::<Vec<_>>()
-                .await?;
-            Ok(blobs)
-        })
+    /// The ticket only contains the node id and relay url of this node, leaving out the
+    /// direct addresses. Use [`BlobTicket::qr_payload`](crate::BlobTicket::qr_payload) to
+    /// encode it for a QR code.
+    pub fn blobs_share_compact(
+        &self,
+        hash: Arc<Hash>,
+        blob_format: BlobFormat,
+    ) -> Result<String, IrohError> {
+        self.blobs_share(hash, blob_format, AddrInfoOptions::Relay)
     }
 
-    /// List all collections.
+    /// Re-issue a ticket for a blob on this node with the node's current addresses.
     ///
-    /// Note: this allocates for each `BlobListCollectionsResponse`, if you have many `BlobListCollectionsResponse`s this may be a prohibitively large list.
-    /// Please file an [issue](https://github.com/n0-computer/iroh-ffi/issues/new) if you run into this issue
-    pub fn blobs_list_collections(&self) -> Result<Vec<CollectionInfo>, IrohError> {
-        block_on(&self.rt(), async {
-            let blobs = self
-                .sync_client
-                .blobs()
-                .list_collections()?
-                .map_ok(|res| res.into())
-                .try_collect::<Vec<_>>()
-                .await?;
-            Ok(blobs)
-        })
-    }
-
-    /// Read the content of a collection
-    pub fn blobs_get_collection(&self, hash: Arc<Hash>) -> Result<Arc<Collection>, IrohError> {
+    /// Long-lived tickets go stale when the node changes networks. The refreshed ticket is for
+    /// the same hash and format, with the current relay url and direct addresses of this node,
+    /// limited to the kinds of address info `ticket` has: a ticket without direct addresses
+    /// does not get any. Errors if the provider of `ticket` is not this node.
+    pub fn blobs_refresh_ticket(
+        &self,
+        ticket: Arc<BlobTicket>,
+    ) -> Result<Arc<BlobTicket>, IrohError> {
+        self.state.ensure_running()?;
         block_on(&self.rt(), async {
-            let collection = self.sync_client.blobs().get_collection(hash.0).await?;
-
-            Ok(Arc::new(collection.into()))
+            let current = self.sync_client.status().await?.addr;
+            let addr = refresh_node_addr(ticket.0.node_addr(), &current)?;
+            let ticket =
+                iroh::base::ticket::BlobTicket::new(addr, ticket.0.hash(), ticket.0.format())?;
+            Ok(Arc::new(BlobTicket(ticket)))
         })
     }
 
-    /// Create a collection from already existing blobs.
+    /// Import a file or directory and create a ticket for sharing it, in one step.
     ///
-    /// To automatically clear the tags for the passed in blobs you can set
-    /// `tags_to_delete` on those tags, and they will be deleted once the collection is created.
-    pub fn blobs_create_collection(
+    /// Imports `path` like [`Self::blobs_add_from_path`] without wrapping, so a directory
+    /// becomes a collection, protected by `tag`. Before creating the ticket, this waits up to
+    /// 10 seconds for the node to have the addresses `ticket_options` asks for, e.g. a relay
+    /// url for [`AddrInfoOptions::Relay`], so that the ticket is usable right away. Fails if
+    /// the node does not get them in time; the imported data stays on the node in that case.
+    pub fn blobs_add_and_share(
         &self,
-        collection: Arc<Collection>,
+        path: String,
+        in_place: bool,
         tag: Arc<SetTagOption>,
-        tags_to_delete: Vec<String>,
-    ) -> Result<HashAndTag, IrohError> {
+        ticket_options: AddrInfoOptions,
+    ) -> Result<String, IrohError> {
+        self.state.ensure_running()?;
         block_on(&self.rt(), async {
-            let collection = collection.0.read().unwrap().clone();
-            let (hash, tag) = self
+            let mut stream = self
                 .sync_client
                 .blobs()
-                .create_collection(
-                    collection,
+                .add_from_path(
+                    path.into(),
+                    in_place,
                     (*tag).clone().into(),
-                    tags_to_delete
-                        .into_iter()
-                        .map(iroh::blobs::Tag::from)
+                    iroh::client::blobs::WrapOption::NoWrap,
+                )
+                .await?;
+            let mut added = None;
+            while let Some(progress) = stream.next().await {
+                match progress? {
+                    iroh::blobs::provider::AddProgress::AllDone { hash, format, .. } => {
+                        added = Some((hash, format));
+                    }
+                    iroh::blobs::provider::AddProgress::Abort(err) => {
+                        return Err(anyhow::anyhow!("{}", err).into());
+                    }
+                    _ => {}
+                }
+            }
+            let (hash, format) = added
+                .ok_or_else(|| anyhow::anyhow!("add stream ended before the blob was added"))?;
+
+            self.wait_for_ticket_addrs(ticket_options).await?;
+            let ticket = self
+                .sync_client
+                .blobs()
+                .share(hash, format, ticket_options.into())
+                .await?;
+            Ok(ticket.to_string())
+        })
+    }
+
+    /// Import a single file and wrap it in a collection, so the file name travels with it.
+    ///
+    /// The collection holds the file under `name`, or under the file's name in `path` if `name`
+    /// is `None`. Fails if `path` is not a file. Returns the hash of the collection and the
+    /// automatically created tag that protects it.
+    pub fn blobs_add_file_wrapped(
+        &self,
+        path: String,
+        name: Option<String>,
+    ) -> Result<HashAndTag, IrohError> {
+        let path = PathBuf::from(path);
+        let name = match name {
+            Some(name) => name,
+            None => path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .ok_or_else(|| anyhow::anyhow!("{} has no file name", path.display()))?,
+        };
+        self.state.ensure_running()?;
+        block_on(&self.rt(), async {
+            if !tokio::fs::metadata(&path)
+                .await
+                .map_err(anyhow::Error::from)?
+                .is_file()
+            {
+                return Err(anyhow::anyhow!("{} is not a file", path.display()).into());
+            }
+            let mut stream = self
+                .sync_client
+                .blobs()
+                .add_from_path(
+                    path,
+                    false,
+                    iroh::blobs::util::SetTagOption::Auto,
+                    iroh::client::blobs::WrapOption::Wrap { name: Some(name) },
+                )
+                .await?;
+            while let Some(progress) = stream.next().await {
+                match progress? {
+                    iroh::blobs::provider::AddProgress::AllDone { hash, tag, .. } => {
+                        return Ok(HashAndTag {
+                            hash: Arc::new(hash.into()),
+                            tag: tag.0.to_vec(),
+                        });
+                    }
+                    iroh::blobs::provider::AddProgress::Abort(err) => {
+                        return Err(anyhow::anyhow!("{}", err).into());
+                    }
+                    _ => {}
+                }
+            }
+            Err(anyhow::anyhow!("add stream ended before the file was added").into())
+        })
+    }
+
+    /// List all incomplete (partial) blobs.
+    ///
+    /// Note: this allocates for each `BlobListIncompleteResponse`, if you have many `BlobListIncompleteResponse`s this may be a prohibitively large list.
+    /// Please file an [issue](https://github.com/n0-computer/iroh-ffi/issues/new) if you run into this issue
+    pub fn blobs_list_incomplete(&self) -> Result<Vec<IncompleteBlobInfo>, IrohError> {
+        self.state.ensure_running()?;
+        block_on(&self.rt(), async {
+            let blobs = self
+                .sync_client
+                .blobs()
+                .list_incomplete()
+                .await?
+                .map_ok(|res| res.into())
+                .try_collect::<Vec<_>>()
+                .await?;
+            Ok(blobs)
+        })
+    }
+
+    /// List all collections.
+    ///
+    /// Note: this allocates for each `BlobListCollectionsResponse`, if you have many `BlobListCollectionsResponse`s this may be a prohibitively large list.
+    /// Please file an [issue](https://github.com/n0-computer/iroh-ffi/issues/new) if you run into this issue
+    pub fn blobs_list_collections(&self) -> Result<Vec<CollectionInfo>, IrohError> {
+        self.state.ensure_running()?;
+        block_on(&self.rt(), async {
+            let blobs = self
+                .sync_client
+                .blobs()
+                .list_collections()?
+                .map_ok(|res| res.into())
+                .try_collect::<Vec<_>>()
+                .await?;
+            Ok(blobs)
+        })
+    }
+
+    /// Read the content of a collection
+    pub fn blobs_get_collection(&self, hash: Arc<Hash>) -> Result<Arc<Collection>, IrohError> {
+        self.state.ensure_running()?;
+        block_on(&self.rt(), async {
+            let collection = self.sync_client.blobs().get_collection(hash.0).await;
+            let collection = check_available(&self.sync_client, hash.0, collection).await?;
+
+            Ok(Arc::new(collection.into()))
+        })
+    }
+
+    /// Create a collection from already existing blobs.
+    ///
+    /// To automatically clear the tags for the passed in blobs you can set
+    /// `tags_to_delete` on those tags, and they will be deleted once the collection is created.
+    pub fn blobs_create_collection(
+        &self,
+        collection: Arc<Collection>,
+        tag: Arc<SetTagOption>,
+        tags_to_delete: Vec<String>,
+    ) -> Result<HashAndTag, IrohError> {
+        self.state.ensure_running()?;
+        block_on(&self.rt(), async {
+            let collection = collection.0.read().unwrap().clone();
+            let (hash, tag) = self
+                .sync_client
+                .blobs()
+                .create_collection(
+                    collection,
+                    (*tag).clone().into(),
+                    tags_to_delete
+                        .into_iter()
+                        .map(iroh::blobs::Tag::from)
                         .collect(),
                 )
                 .await?;
@@ -297,6 +1072,7 @@ impl IrohNode {
 
     /// Delete a blob.
     pub fn blobs_delete_blob(&self, hash: Arc<Hash>) -> Result<(), IrohError> {
+        self.state.ensure_running()?;
         block_on(&self.rt(), async {
             let mut tags = self.sync_client.tags().list().await?;
 
@@ -314,1335 +1090,4327 @@ impl IrohNode {
                     .blobs()
                     .delete_blob((*hash).clone().0)
                     .await?;
+                self.blob_metadata.remove(&hash.0)?;
             }
 
             Ok(())
         })
     }
-}
 
-/// The Hash and associated tag of a newly created collection
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct HashAndTag {
-    /// The hash of the collection
-    pub hash: Arc<Hash>,
-    /// The tag of the collection
-    pub tag: Vec<u8>,
-}
+    /// Remove blobs that are neither protected by a tag nor referenced by a document.
+    ///
+    /// This removes the partial blobs left behind by interrupted downloads, independent of the
+    /// gc interval. Complete blobs whose tags were deleted are only removed as well if
+    /// `include_complete` is `true`. Blobs are kept if they are tagged, are a child of a tagged
+    /// collection, are the content of an entry in any document on this node, or are currently
+    /// being downloaded through [`Self::blobs_download`], including the children of a hash
+    /// sequence being downloaded. Blobs that are still being added are only protected by a
+    /// temporary tag, so do not run this concurrently with adds.
+    ///
+    /// The children of a tagged collection that is not stored completely are not known, so the
+    /// cleanup fails without removing anything while such a tag exists.
+    ///
+    /// `cb` is called with the hash of each removed blob. If the callback returns an error,
+    /// cleanup stops, see `CallbackError`.
+    pub fn blobs_cleanup_orphans(
+        &self,
+        include_complete: bool,
+        cb: Option<Arc<dyn HashCallback>>,
+    ) -> Result<OrphanCleanupSummary, IrohError> {
+        self.state.ensure_running()?;
+        block_on(&self.rt(), async {
+            let referenced = self.referenced_blobs().await?;
+
+            let mut candidates = Vec::new();
+            if include_complete {
+                let mut complete = self.sync_client.blobs().list().await?;
+                while let Some(blob) = complete.next().await {
+                    let blob = blob?;
+                    candidates.push((blob.hash, blob.size));
+                }
+            }
+            let mut incomplete = self.sync_client.blobs().list_incomplete().await?;
+            while let Some(blob) = incomplete.next().await {
+                let blob = blob?;
+                candidates.push((blob.hash, blob.size));
+            }
 
-/// Outcome of a blob add operation.
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct BlobAddOutcome {
-    /// The hash of the blob
-    pub hash: Arc<Hash>,
-    /// The format the blob
-    pub format: BlobFormat,
-    /// The size of the blob
-    pub size: u64,
-    /// The tag of the blob
-    pub tag: Vec<u8>,
-}
+            let mut summary = OrphanCleanupSummary {
+                removed_blobs: 0,
+                removed_bytes: 0,
+            };
+            for (hash, size) in candidates {
+                if referenced.contains(&hash) {
+                    continue;
+                }
+                self.sync_client.blobs().delete_blob(hash).await?;
+                self.blob_metadata.remove(&hash)?;
+                summary.removed_blobs += 1;
+                summary.removed_bytes += size;
+                if let Some(cb) = &cb {
+                    if !callback_continue(cb.on_hash(Arc::new(hash.into())))? {
+                        break;
+                    }
+                }
+            }
+            Ok(summary)
+        })
+    }
 
-impl From<iroh::client::blobs::AddOutcome> for BlobAddOutcome {
-    fn from(value: iroh::client::blobs::AddOutcome) -> Self {
-        BlobAddOutcome {
-            hash: Arc::new(value.hash.into()),
-            format: value.format.into(),
-            size: value.size,
-            tag: value.tag.0.to_vec(),
+    /// Protect a blob from garbage collection until `expires_at_micros`.
+    ///
+    /// This creates a tag, returned by this method, that keeps the blob until the expiry time,
+    /// given in microseconds since the unix epoch. Afterwards the tag is removed, and the blob
+    /// is collected by the next gc run unless something else references it. Deleting the tag
+    /// with [`Self::tags_delete`] ends the pin early. A blob has at most one pin: pinning it
+    /// again replaces the earlier pin, also if the new expiry is earlier.
+    ///
+    /// Expired pins are removed when the node starts and then once a minute, so a blob may be
+    /// kept up to a minute past its expiry, plus the gc interval. If the node is not running at
+    /// the expiry time, the pin is removed the next time the node starts.
+    ///
+    /// The blob must be complete on this node, partial blobs cannot be pinned. A blob that
+    /// another tag marks as a collection is pinned as a collection, which protects its children
+    /// as well.
+    ///
+    /// Pinning is not cheap. iroh 0.19 cannot point a tag at an existing hash, so the pin tag
+    /// is created by adding the local data again: a raw blob is read and hashed in full, using
+    /// temporary space of its size, and a collection is rebuilt. Each call also scans all tags
+    /// to find the format and the earlier pins of the blob. Avoid pinning large blobs often.
+    pub fn blobs_pin_until(
+        &self,
+        hash: Arc<Hash>,
+        expires_at_micros: u64,
+    ) -> Result<Vec<u8>, IrohError> {
+        if expires_at_micros <= now_micros() {
+            return Err(anyhow::anyhow!("pin expiry {expires_at_micros} is in the past").into());
         }
+        self.state.ensure_running()?;
+        block_on(&self.rt(), async {
+            let status = self.sync_client.blobs().status(hash.0).await?;
+            if !matches!(status, iroh::client::blobs::BlobStatus::Complete { .. }) {
+                return Err(anyhow::anyhow!("cannot pin {}: blob is not complete", hash.0).into());
+            }
+            // one pass over the tags, keeping only what concerns this blob
+            let mut format = BlobFormat::Raw;
+            let mut earlier_pins = Vec::new();
+            let mut tags = self.sync_client.tags().list().await?;
+            while let Some(tag) = tags.try_next().await? {
+                if tag.hash == hash.0 && tag.format.is_hash_seq() {
+                    format = BlobFormat::HashSeq;
+                }
+                if pin_hash(&tag.name.0) == Some(hash.0) {
+                    earlier_pins.push(tag.name);
+                }
+            }
+            let name = iroh::blobs::Tag(bytes::Bytes::from(format!(
+                "{PIN_TAG_PREFIX}{expires_at_micros}/{}",
+                hash.0
+            )));
+            set_tag(&self.sync_client, name.clone(), hash.0, &format).await?;
+            for tag in earlier_pins {
+                if tag != name {
+                    self.sync_client.tags().delete(tag).await?;
+                }
+            }
+            Ok(name.0.to_vec())
+        })
     }
-}
 
-/// An option for commands that allow setting a Tag
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub enum SetTagOption {
-    /// A tag will be automatically generated
-    Auto,
-    /// The tag is explicitly vecnamed
-    Named(Vec<u8>),
-}
+    /// Collect the hashes of all blobs referenced by a tag, a running download or a document
+    /// entry.
+    ///
+    /// Fails if the children of a tagged hash sequence cannot be read, since they could not be
+    /// told apart from orphans.
+    async fn referenced_blobs(&self) -> anyhow::Result<HashSet<iroh::blobs::Hash>> {
+        let mut referenced = HashSet::new();
+
+        let mut tags = self.sync_client.tags().list().await?;
+        while let Some(tag) = tags.next().await {
+            let tag = tag?;
+            referenced.insert(tag.hash);
+            if tag.format.is_hash_seq() {
+                let children = self
+                    .sync_client
+                    .blobs()
+                    .read_to_bytes(tag.hash)
+                    .await
+                    .and_then(iroh::blobs::hashseq::HashSeq::try_from)
+                    .map_err(|err| {
+                        anyhow::anyhow!(
+                            "cannot read the children of tagged hash sequence {}: {err}",
+                            tag.hash
+                        )
+                    })?;
+                referenced.extend(children.iter());
+            }
+        }
 
-impl SetTagOption {
-    /// Indicate you want an automatically generated tag
-    pub fn auto() -> Self {
-        SetTagOption::Auto
-    }
+        // the children of a running hash sequence download are fetched once its root is complete
+        for hash in self.downloads.hashes() {
+            referenced.insert(hash);
+            let is_hash_seq = self
+                .download_sources
+                .get(&hash)
+                .is_some_and(|source| source.format.is_hash_seq());
+            if !is_hash_seq {
+                continue;
+            }
+            let children = self
+                .sync_client
+                .blobs()
+                .read_to_bytes(hash)
+                .await
+                .and_then(iroh::blobs::hashseq::HashSeq::try_from);
+            if let Ok(children) = children {
+                referenced.extend(children.iter());
+            }
+        }
 
-    /// Indicate you want a named tag
-    pub fn named(tag: Vec<u8>) -> Self {
-        SetTagOption::Named(tag)
+        referenced.extend(doc_referenced_blobs(&self.sync_client).await?);
+        Ok(referenced)
     }
-}
 
-impl From<SetTagOption> for iroh::blobs::util::SetTagOption {
-    fn from(value: SetTagOption) -> Self {
-        match value {
-            SetTagOption::Auto => iroh::blobs::util::SetTagOption::Auto,
-            SetTagOption::Named(tag) => {
-                iroh::blobs::util::SetTagOption::Named(iroh::blobs::Tag(bytes::Bytes::from(tag)))
+    /// List the tags and documents that reference `hash`, i.e. keep it from being collected.
+    ///
+    /// A tag references the blob if it points at it, or at a hash sequence that contains it.
+    /// A document references the blob if any of its entries has it as content, including
+    /// entries that were overwritten by a later entry of another author.
+    pub fn blobs_references(&self, hash: Arc<Hash>) -> Result<BlobReferences, IrohError> {
+        self.state.ensure_running()?;
+        block_on(&self.rt(), async {
+            let mut references = BlobReferences {
+                tags: Vec::new(),
+                docs: Vec::new(),
+            };
+
+            let mut tags = self.sync_client.tags().list().await?;
+            while let Some(tag) = tags.next().await {
+                let tag = tag?;
+                let mut referenced = tag.hash == hash.0;
+                if !referenced && tag.format.is_hash_seq() {
+                    let children = self
+                        .sync_client
+                        .blobs()
+                        .read_to_bytes(tag.hash)
+                        .await
+                        .and_then(iroh::blobs::hashseq::HashSeq::try_from)
+                        .map_err(|err| {
+                            anyhow::anyhow!(
+                                "cannot read the children of tagged hash sequence {}: {err}",
+                                tag.hash
+                            )
+                        })?;
+                    referenced = children.iter().any(|child| child == hash.0);
+                }
+                if referenced {
+                    references.tags.push(tag.name.0.to_vec());
+                }
             }
-        }
-    }
-}
 
-/// Whether to wrap the added data in a collection.
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub enum WrapOption {
-    /// Do not wrap the file or directory.
-    NoWrap,
-    /// Wrap the file or directory in a colletion.
-    Wrap {
-        /// Override the filename in the wrapping collection.
-        name: Option<String>,
-    },
-}
+            let mut docs = self.sync_client.docs().list().await?;
+            while let Some(doc) = docs.next().await {
+                let (id, _) = doc?;
+                let Some(doc) = self.sync_client.docs().open(id).await? else {
+                    continue;
+                };
+                let mut entries = doc.get_many(iroh::docs::store::Query::all()).await?;
+                while let Some(entry) = entries.next().await {
+                    if entry?.content_hash() == hash.0 {
+                        references.docs.push(id.to_string());
+                        break;
+                    }
+                }
+            }
 
-impl WrapOption {
-    /// Indicate you do not wrap the file or directory.
-    pub fn no_wrap() -> Self {
-        WrapOption::NoWrap
+            Ok(references)
+        })
     }
 
-    /// Indicate you want to wrap the file or directory in a colletion, with an optional name
-    pub fn wrap(name: Option<String>) -> Self {
-        WrapOption::Wrap { name }
+    /// Attach a content type and arbitrary key-value pairs to a blob.
+    ///
+    /// Replaces any metadata previously set for this hash.
+    ///
+    /// Metadata is local to this node: it is stored next to the node's data, keyed by hash,
+    /// and is *not* transferred when the blob is shared or downloaded by other nodes.
+    ///
+    /// `modified_micros` is an optional modification time, in microseconds since the unix
+    /// epoch, e.g. of the file the blob was imported from. When set, it is applied to the files
+    /// written by [`Self::blobs_write_to_path`] and [`Self::blobs_export`], so that tools
+    /// mirroring a directory see the original timestamps. Writing fails if the time can't be
+    /// represented on this platform. Like the rest of the metadata, it is not part of the
+    /// content: blobs with the same content have the same hash regardless of their timestamps.
+    pub fn blobs_set_metadata(
+        &self,
+        hash: Arc<Hash>,
+        content_type: String,
+        extra: HashMap<String, String>,
+        modified_micros: Option<u64>,
+    ) -> Result<(), IrohError> {
+        self.blob_metadata.set(
+            hash.0,
+            BlobMetadata {
+                content_type,
+                extra,
+                modified_micros,
+            },
+        )?;
+        Ok(())
     }
-}
 
-impl From<WrapOption> for iroh::client::blobs::WrapOption {
-    fn from(value: WrapOption) -> Self {
-        match value {
-            WrapOption::NoWrap => iroh::client::blobs::WrapOption::NoWrap,
-            WrapOption::Wrap { name } => iroh::client::blobs::WrapOption::Wrap { name },
-        }
+    /// Get the metadata set for a blob with [`Self::blobs_set_metadata`].
+    ///
+    /// Returns `None` if no metadata was set for this hash on this node.
+    pub fn blobs_get_metadata(&self, hash: Arc<Hash>) -> Option<BlobMetadata> {
+        self.blob_metadata.get(&hash.0)
     }
-}
-
-/// Hash type used throughout Iroh. A blake3 hash.
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct Hash(pub(crate) iroh::blobs::Hash);
 
-impl From<iroh::blobs::Hash> for Hash {
-    fn from(h: iroh::blobs::Hash) -> Self {
-        Hash(h)
+    /// Set the modification time stored for `hash` on the file at `path`, if there is one.
+    fn apply_modified(&self, hash: &iroh::blobs::Hash, path: &Path) -> anyhow::Result<()> {
+        let Some(micros) = self.blob_metadata.get(hash).and_then(|m| m.modified_micros) else {
+            return Ok(());
+        };
+        let modified = std::time::UNIX_EPOCH
+            .checked_add(Duration::from_micros(micros))
+            .ok_or_else(|| {
+                anyhow::anyhow!("modified time of {micros} microseconds is out of range")
+            })?;
+        std::fs::File::options()
+            .write(true)
+            .open(path)?
+            .set_modified(modified)?;
+        Ok(())
     }
-}
 
-impl Hash {
-    /// Calculate the hash of the provide bytes.
-    pub fn new(buf: Vec<u8>) -> Self {
-        Hash(iroh::blobs::Hash::new(buf))
+    /// Get notified when complete blobs are added to or removed from the store.
+    ///
+    /// The store does not publish changes, so they are found by comparing the list of complete
+    /// blobs every second. This covers every way the store changes: adds, downloads, deletes
+    /// and garbage collection. Each subscription lists the whole store once per second, so
+    /// prefer a single subscription for large stores.
+    ///
+    /// Changes that cancel out between two checks are missed: a blob added and removed again
+    /// within one second is not reported at all, and neither is a blob removed and added
+    /// again. Partial blobs are not reported; a download shows up once it completes. The blobs
+    /// already in the store when subscribing are not reported either, use
+    /// [`Self::blobs_list`] to get them.
+    ///
+    /// Events are delivered one at a time, in order: for each hash, `Added` and `Removed`
+    /// alternate. Within one check, removals are reported before additions, each group
+    /// ordered by hash. The format of an added blob is [`BlobFormat::HashSeq`] if a tag marks
+    /// it as a collection at the time it is found, and [`BlobFormat::Raw`] otherwise.
+    ///
+    /// Watching ends when the returned subscription is cancelled or dropped, when the callback
+    /// returns an error, see `CallbackError`, or when the node is shut down.
+    pub fn blobs_subscribe_store(
+        &self,
+        cb: Arc<dyn StoreEventCallback>,
+    ) -> Result<Arc<StoreSubscription>, IrohError> {
+        self.state.ensure_running()?;
+        let client = self.sync_client.clone();
+        let mut last = block_on(&self.rt(), complete_blobs(&client))?;
+        let subscription = Arc::new(StoreSubscription::default());
+        let cancelled = subscription.cancelled.clone();
+        let state = self.state.clone();
+        self.rt().spawn(async move {
+            let mut failures = FailureLog::default();
+            loop {
+                tokio::time::sleep(STORE_POLL_INTERVAL).await;
+                if !state.is_running() || cancelled.load(Ordering::SeqCst) {
+                    return;
+                }
+                let current = match complete_blobs(&client).await {
+                    Ok(current) => current,
+                    Err(err) => {
+                        failures.warn("listing the store", &err);
+                        continue;
+                    }
+                };
+                let mut removed: Vec<_> = last.difference(&current).copied().collect();
+                let mut added: Vec<_> = current.difference(&last).copied().collect();
+                last = current;
+                if removed.is_empty() && added.is_empty() {
+                    continue;
+                }
+                removed.sort();
+                added.sort();
+                let collections = match collection_tags(&client).await {
+                    Ok(collections) => collections,
+                    Err(err) => {
+                        failures.warn("listing the collection tags", &err);
+                        HashSet::new()
+                    }
+                };
+                let events = removed
+                    .into_iter()
+                    .map(|hash| StoreEvent::Removed(Arc::new(hash.into())))
+                    .chain(added.into_iter().map(|hash| {
+                        StoreEvent::Added(StoreEventAdded {
+                            format: if collections.contains(&hash) {
+                                BlobFormat::HashSeq
+                            } else {
+                                BlobFormat::Raw
+                            },
+                            hash: Arc::new(hash.into()),
+                        })
+                    }));
+                for event in events {
+                    if let Err(err) = cb.event(Arc::new(event)) {
+                        if err != CallbackError::Cancelled {
+                            tracing::warn!("callback failed, ending store subscription: {:?}", err);
+                        }
+                        return;
+                    }
+                }
+            }
+        });
+        Ok(subscription)
     }
 
-    /// Bytes of the hash.
-    pub fn to_bytes(&self) -> Vec<u8> {
-        self.0.as_bytes().to_vec()
+    /// Create a [`BlobBatch`] to add several blobs that are protected from garbage collection
+    /// until they are turned into a collection.
+    pub fn blobs_batch(&self) -> Arc<BlobBatch> {
+        Arc::new(BlobBatch {
+            client: self.sync_client.clone(),
+            rt: self.rt(),
+            state: self.state.clone(),
+            blobs: Mutex::new(Some(Vec::new())),
+        })
     }
+}
 
-    /// Create a `Hash` from its raw bytes representation.
-    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, IrohError> {
-        let bytes: [u8; 32] = bytes.try_into().map_err(|b: Vec<u8>| {
-            anyhow::anyhow!("expected byte array of length 32, got {}", b.len())
-        })?;
-        Ok(Hash(iroh::blobs::Hash::from_bytes(bytes)))
+/// Write `destination` atomically.
+///
+/// `write` is called with a temporary path in the same directory as `destination`. Once it
+/// succeeds, the file is synced to disk and renamed to `destination`. The temporary file is
+/// removed if anything fails.
+async fn write_atomic<F, Fut>(destination: &Path, write: F) -> anyhow::Result<()>
+where
+    F: FnOnce(PathBuf) -> Fut,
+    Fut: std::future::Future<Output = anyhow::Result<()>>,
+{
+    let dir = match destination.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    tokio::fs::create_dir_all(dir).await?;
+    let tmp_dir = tempfile::Builder::new()
+        .prefix(".iroh-export-")
+        .tempdir_in(dir)?;
+    let tmp_path = tmp_dir.path().join("data");
+    write(tmp_path.clone()).await?;
+    tokio::fs::File::open(&tmp_path).await?.sync_all().await?;
+    tokio::fs::rename(&tmp_path, destination).await?;
+    Ok(())
+}
+
+/// A set of blobs that is protected from garbage collection until it is turned into a
+/// collection with [`BlobBatch::commit`].
+///
+/// Each blob added to the batch is protected by a temporary tag. Committing the batch creates a
+/// collection of all blobs and removes the temporary tags. If the batch is dropped without
+/// being committed, the temporary tags are removed, so the blobs can be garbage collected.
+pub struct BlobBatch {
+    client: MemIroh,
+    rt: tokio::runtime::Handle,
+    state: NodeState,
+    /// Name, hash and temporary tag of each blob added so far, `None` once committed.
+    blobs: Mutex<Option<Vec<(String, iroh::blobs::Hash, iroh::blobs::Tag)>>>,
+}
+
+impl BlobBatch {
+    /// Add a blob from bytes to the batch, under `name` in the resulting collection.
+    pub fn add_bytes(&self, name: String, bytes: Vec<u8>) -> Result<Arc<Hash>, IrohError> {
+        self.ensure_open()?;
+        self.state.ensure_running()?;
+        block_on(&self.rt, async {
+            let outcome = self.client.blobs().add_bytes(bytes).await?;
+            self.push(name, outcome.hash, outcome.tag)
+        })
     }
 
-    /// Make a Hash from hex string
-    pub fn from_string(s: String) -> Result<Self, IrohError> {
-        let key = iroh::blobs::Hash::from_str(&s).map_err(anyhow::Error::from)?;
-        Ok(key.into())
+    /// Add a blob from a file to the batch, under `name` in the resulting collection.
+    ///
+    /// `path` should be an absolute path valid for the file system on which the node runs.
+    /// If `in_place` is true, Iroh will assume that the data will not change and will share it
+    /// in place without copying to the Iroh data directory.
+    pub fn add_from_path(
+        &self,
+        name: String,
+        path: String,
+        in_place: bool,
+    ) -> Result<Arc<Hash>, IrohError> {
+        self.ensure_open()?;
+        self.state.ensure_running()?;
+        block_on(&self.rt, async {
+            let mut stream = self
+                .client
+                .blobs()
+                .add_from_path(
+                    path.into(),
+                    in_place,
+                    iroh::blobs::util::SetTagOption::Auto,
+                    iroh::client::blobs::WrapOption::NoWrap,
+                )
+                .await?;
+            while let Some(progress) = stream.next().await {
+                match progress? {
+                    iroh::blobs::provider::AddProgress::AllDone { hash, tag, .. } => {
+                        return self.push(name, hash, tag);
+                    }
+                    iroh::blobs::provider::AddProgress::Abort(err) => {
+                        return Err(anyhow::anyhow!("{}", err).into());
+                    }
+                    _ => {}
+                }
+            }
+            Err(anyhow::anyhow!("add stream ended before the blob was added").into())
+        })
     }
 
-    /// Convert the hash to a hex string.
-    pub fn to_hex(&self) -> String {
-        self.0.to_hex()
+    /// Create a collection of all blobs in the batch and remove their temporary tags.
+    ///
+    /// The collection is protected by `tag`. No blobs can be added after the batch was
+    /// committed.
+    pub fn commit(&self, tag: Arc<SetTagOption>) -> Result<HashAndTag, IrohError> {
+        let blobs = self
+            .blobs
+            .lock()
+            .unwrap()
+            .take()
+            .ok_or_else(batch_committed)?;
+        self.state.ensure_running()?;
+        block_on(&self.rt, async {
+            let collection = blobs
+                .iter()
+                .map(|(name, hash, _)| (name.clone(), *hash))
+                .collect();
+            let tags = blobs.iter().map(|(_, _, tag)| tag.clone()).collect();
+            match self
+                .client
+                .blobs()
+                .create_collection(collection, (*tag).clone().into(), tags)
+                .await
+            {
+                Ok((hash, tag)) => Ok(HashAndTag {
+                    hash: Arc::new(hash.into()),
+                    tag: tag.0.to_vec(),
+                }),
+                Err(err) => {
+                    // keep the batch usable, so the commit can be retried
+                    *self.blobs.lock().unwrap() = Some(blobs);
+                    Err(err.into())
+                }
+            }
+        })
     }
 
-    /// Returns true if the Hash's have the same value
-    pub fn equal(&self, other: &Hash) -> bool {
-        *self == *other
+    fn ensure_open(&self) -> Result<(), IrohError> {
+        match *self.blobs.lock().unwrap() {
+            Some(_) => Ok(()),
+            None => Err(batch_committed().into()),
+        }
     }
-}
 
-impl std::fmt::Display for Hash {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "{}", self.0)
+    fn push(
+        &self,
+        name: String,
+        hash: iroh::blobs::Hash,
+        tag: iroh::blobs::Tag,
+    ) -> Result<Arc<Hash>, IrohError> {
+        let mut blobs = self.blobs.lock().unwrap();
+        match blobs.as_mut() {
+            Some(blobs) => {
+                blobs.push((name, hash, tag));
+                Ok(Arc::new(hash.into()))
+            }
+            None => Err(batch_committed().into()),
+        }
     }
 }
 
-impl From<Hash> for iroh::blobs::Hash {
-    fn from(value: Hash) -> Self {
-        value.0
+impl Drop for BlobBatch {
+    fn drop(&mut self) {
+        let Some(blobs) = self.blobs.lock().unwrap().take() else {
+            return;
+        };
+        let client = self.client.clone();
+        self.rt.spawn(async move {
+            for (_, _, tag) in blobs {
+                if let Err(err) = client.tags().delete(tag).await {
+                    tracing::warn!("failed to delete batch tag: {:?}", err);
+                }
+            }
+        });
     }
 }
 
-/// The `progress` method will be called for each `AddProgress` event that is
-/// emitted during a `node.blobs_add_from_path`. Use the `AddProgress.type()`
-/// method to check the `AddProgressType`
-pub trait AddCallback: Send + Sync + 'static {
-    fn progress(&self, progress: Arc<AddProgress>) -> Result<(), CallbackError>;
+fn batch_committed() -> anyhow::Error {
+    anyhow::anyhow!("the batch was already committed")
 }
 
-/// The different types of AddProgress events
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
-pub enum AddProgressType {
-    /// An item was found with name `name`, from now on referred to via `id`
-    Found,
-    /// We got progress ingesting item `id`.
-    Progress,
-    /// We are done with `id`, and the hash is `hash`.
-    Done,
-    /// We are done with the whole operation.
-    AllDone,
-    /// We got an error and need to abort.
-    ///
-    /// This will be the last message in the stream.
-    Abort,
+/// Local metadata about a blob.
+///
+/// Metadata is only stored on the node it was set on, and is not part of the blob content.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlobMetadata {
+    /// The content type of the blob, e.g. `image/png`
+    pub content_type: String,
+    /// Additional application defined key-value pairs
+    pub extra: HashMap<String, String>,
+    /// The modification time to give exported files, in microseconds since the unix epoch
+    #[serde(default)]
+    pub modified_micros: Option<u64>,
 }
 
-/// An AddProgress event indicating an item was found with name `name`, that can be referred to by `id`
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct AddProgressFound {
-    /// A new unique id for this entry.
-    pub id: u64,
-    /// The name of the entry.
-    pub name: String,
-    /// The size of the entry in bytes.
-    pub size: u64,
+/// The `event` method is called by [`IrohNode::blobs_subscribe_store`] for each change of the
+/// blob store.
+pub trait StoreEventCallback: Send + Sync + 'static {
+    fn event(&self, event: Arc<StoreEvent>) -> Result<(), CallbackError>;
 }
 
-/// An AddProgress event indicating we got progress ingesting item `id`.
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct AddProgressProgress {
-    /// The unique id of the entry.
-    pub id: u64,
-    /// The offset of the progress, in bytes.
-    pub offset: u64,
+/// A change of the blob store, see [`IrohNode::blobs_subscribe_store`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StoreEvent {
+    /// A blob is now completely stored.
+    Added(StoreEventAdded),
+    /// A blob was removed from the store.
+    Removed(Arc<Hash>),
 }
 
-/// An AddProgress event indicated we are done with `id` and now have a hash `hash`
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct AddProgressDone {
-    /// The unique id of the entry.
-    pub id: u64,
-    /// The hash of the entry.
-    pub hash: Arc<Hash>,
+/// The type of a [`StoreEvent`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StoreEventType {
+    /// A blob is now completely stored.
+    Added,
+    /// A blob was removed from the store.
+    Removed,
 }
 
-/// An AddProgress event indicating we are done with the the whole operation
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct AddProgressAllDone {
-    /// The hash of the created data.
+/// A blob that is now completely stored, see [`StoreEvent::Added`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoreEventAdded {
+    /// The hash of the blob.
     pub hash: Arc<Hash>,
-    /// The format of the added data.
+    /// Whether the blob is tagged as a collection.
     pub format: BlobFormat,
-    /// The tag of the added data.
-    pub tag: Vec<u8>,
-}
-
-/// An AddProgress event indicating we got an error and need to abort
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct AddProgressAbort {
-    pub error: String,
-}
-
-/// Progress updates for the add operation.
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub enum AddProgress {
-    /// An item was found with name `name`, from now on referred to via `id`
-    Found(AddProgressFound),
-    /// We got progress ingesting item `id`.
-    Progress(AddProgressProgress),
-    /// We are done with `id`, and the hash is `hash`.
-    Done(AddProgressDone),
-    /// We are done with the whole operation.
-    AllDone(AddProgressAllDone),
-    /// We got an error and need to abort.
-    ///
-    /// This will be the last message in the stream.
-    Abort(AddProgressAbort),
 }
 
-impl From<iroh::blobs::provider::AddProgress> for AddProgress {
-    fn from(value: iroh::blobs::provider::AddProgress) -> Self {
-        match value {
-            iroh::blobs::provider::AddProgress::Found { id, name, size } => {
-                AddProgress::Found(AddProgressFound { id, name, size })
-            }
-            iroh::blobs::provider::AddProgress::Progress { id, offset } => {
-                AddProgress::Progress(AddProgressProgress { id, offset })
-            }
-            iroh::blobs::provider::AddProgress::Done { id, hash } => {
-                AddProgress::Done(AddProgressDone {
-                    id,
-                    hash: Arc::new(hash.into()),
-                })
-            }
-            iroh::blobs::provider::AddProgress::AllDone { hash, format, tag } => {
-                AddProgress::AllDone(AddProgressAllDone {
-                    hash: Arc::new(hash.into()),
-                    format: format.into(),
-                    tag: tag.0.to_vec(),
-                })
-            }
-            iroh::blobs::provider::AddProgress::Abort(err) => {
-                AddProgress::Abort(AddProgressAbort {
-                    error: err.to_string(),
-                })
-            }
+impl StoreEvent {
+    /// Get the type of this event.
+    pub fn r#type(&self) -> StoreEventType {
+        match self {
+            Self::Added(_) => StoreEventType::Added,
+            Self::Removed(_) => StoreEventType::Removed,
         }
     }
-}
 
-impl AddProgress {
-    /// Get the type of event
-    pub fn r#type(&self) -> AddProgressType {
+    /// For `StoreEventType::Added`, returns a StoreEventAdded
+    pub fn as_added(&self) -> StoreEventAdded {
         match self {
-            AddProgress::Found(_) => AddProgressType::Found,
-            AddProgress::Progress(_) => AddProgressType::Progress,
-            AddProgress::Done(_) => AddProgressType::Done,
-            AddProgress::AllDone(_) => AddProgressType::AllDone,
-            AddProgress::Abort(_) => AddProgressType::Abort,
+            Self::Added(added) => added.clone(),
+            _ => panic!("StoreEvent type is not 'Added'"),
         }
     }
-    /// Return the `AddProgressFound` event
-    pub fn as_found(&self) -> AddProgressFound {
+
+    /// For `StoreEventType::Removed`, returns the hash of the removed blob
+    pub fn as_removed(&self) -> Arc<Hash> {
         match self {
-            AddProgress::Found(f) => f.clone(),
-            _ => panic!("AddProgress type is not 'Found'"),
+            Self::Removed(hash) => hash.clone(),
+            _ => panic!("StoreEvent type is not 'Removed'"),
         }
     }
-    /// Return the `AddProgressProgress` event
-    pub fn as_progress(&self) -> AddProgressProgress {
-        match self {
-            AddProgress::Progress(p) => p.clone(),
-            _ => panic!("AddProgress type is not 'Progress'"),
-        }
+}
+
+/// A running [`IrohNode::blobs_subscribe_store`], ends when cancelled or dropped.
+#[derive(Debug, Default)]
+pub struct StoreSubscription {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl StoreSubscription {
+    /// Stop watching the store. The callback is not called again, except for an event that is
+    /// being delivered right now.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
     }
+}
 
-    /// Return the `AddProgressDone` event
-    pub fn as_done(&self) -> AddProgressDone {
-        match self {
-            AddProgress::Done(d) => d.clone(),
-            _ => panic!("AddProgress type is not 'Done'"),
-        }
+impl Drop for StoreSubscription {
+    fn drop(&mut self) {
+        self.cancel();
     }
+}
 
-    /// Return the `AddAllDone`
-    pub fn as_all_done(&self) -> AddProgressAllDone {
-        match self {
-            AddProgress::AllDone(a) => a.clone(),
-            _ => panic!("AddProgress type is not 'AllDone'"),
-        }
+/// How often the store is checked for changes in [`IrohNode::blobs_subscribe_store`].
+const STORE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The hashes of all complete blobs in the store.
+async fn complete_blobs(client: &MemIroh) -> anyhow::Result<HashSet<iroh::blobs::Hash>> {
+    client
+        .blobs()
+        .list()
+        .await?
+        .map_ok(|info| info.hash)
+        .try_collect()
+        .await
+}
+
+/// The hashes that are tagged as collections.
+async fn collection_tags(client: &MemIroh) -> anyhow::Result<HashSet<iroh::blobs::Hash>> {
+    client
+        .tags()
+        .list()
+        .await?
+        .try_filter_map(|tag| async move { Ok(tag.format.is_hash_seq().then_some(tag.hash)) })
+        .try_collect()
+        .await
+}
+
+/// Downloads currently running on this node, so that additional callbacks can be attached
+/// to them with [`IrohNode::blobs_subscribe_download`].
+#[derive(Debug, Default)]
+pub(crate) struct DownloadRegistry {
+    transfers: Mutex<HashMap<iroh::blobs::Hash, Arc<InFlightDownload>>>,
+}
+
+impl DownloadRegistry {
+    fn register(&self, hash: iroh::blobs::Hash) -> Arc<InFlightDownload> {
+        let transfer = Arc::new(InFlightDownload {
+            inner: Mutex::new(InFlightDownloadInner {
+                state: iroh::blobs::get::progress::TransferState::new(hash),
+                subscribers: Vec::new(),
+            }),
+        });
+        let mut transfers = self.transfers.lock().unwrap();
+        transfers.insert(hash, transfer.clone());
+        transfer
     }
 
-    /// Return the `AddProgressAbort`
-    pub fn as_abort(&self) -> AddProgressAbort {
-        match self {
-            AddProgress::Abort(a) => a.clone(),
-            _ => panic!("AddProgress type is not 'Abort'"),
+    fn unregister(&self, hash: iroh::blobs::Hash, transfer: &Arc<InFlightDownload>) {
+        let mut transfers = self.transfers.lock().unwrap();
+        // a later download of the same hash may have replaced this one
+        if transfers
+            .get(&hash)
+            .is_some_and(|t| Arc::ptr_eq(t, transfer))
+        {
+            transfers.remove(&hash);
         }
     }
+
+    fn get(&self, hash: &iroh::blobs::Hash) -> Option<Arc<InFlightDownload>> {
+        let transfers = self.transfers.lock().unwrap();
+        transfers.get(hash).cloned()
+    }
+
+    /// The hashes of the downloads that are running right now.
+    fn hashes(&self) -> Vec<iroh::blobs::Hash> {
+        let transfers = self.transfers.lock().unwrap();
+        transfers.keys().copied().collect()
+    }
 }
 
-/// A format identifier
-#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
-pub enum BlobFormat {
-    /// Raw blob
-    Raw,
-    /// A sequence of BLAKE3 hashes
-    HashSeq,
+/// How long [`IrohNode::blobs_download`] waits before the first retry on the same node, the
+/// wait grows by this much with each further retry.
+const DOWNLOAD_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// The state of a [`IrohNode::blobs_download`] that is carried across its attempts.
+struct DownloadState {
+    /// The transfer registered for the download, see [`DownloadRegistry`].
+    transfer: Arc<InFlightDownload>,
+    /// Blobs that were found but not completed yet.
+    partial: HashSet<iroh::blobs::Hash>,
+    /// Blobs that had data before the download started, never removed on abort.
+    preexisting: HashSet<iroh::blobs::Hash>,
+    /// The offset reached in each blob.
+    received: HashMap<iroh::blobs::Hash, u64>,
 }
 
-impl From<iroh::blobs::BlobFormat> for BlobFormat {
-    fn from(value: iroh::blobs::BlobFormat) -> Self {
-        match value {
-            iroh::blobs::BlobFormat::Raw => BlobFormat::Raw,
-            iroh::blobs::BlobFormat::HashSeq => BlobFormat::HashSeq,
+impl DownloadState {
+    fn new(transfer: Arc<InFlightDownload>) -> Self {
+        DownloadState {
+            transfer,
+            partial: HashSet::new(),
+            preexisting: HashSet::new(),
+            received: HashMap::new(),
         }
     }
 }
 
-impl From<BlobFormat> for iroh::blobs::BlobFormat {
-    fn from(value: BlobFormat) -> Self {
-        match value {
-            BlobFormat::Raw => iroh::blobs::BlobFormat::Raw,
-            BlobFormat::HashSeq => iroh::blobs::BlobFormat::HashSeq,
-        }
-    }
+/// How a single attempt of [`IrohNode::blobs_download`] ended.
+enum DownloadAttempt {
+    /// The download completed.
+    Done,
+    /// The download from this node failed, another attempt may succeed.
+    Failed(IrohError),
+    /// The download was stopped without further attempts, with an error unless it was
+    /// cancelled by the callback.
+    Stopped(Option<IrohError>),
+    /// The download was stopped because its byte budget was reached.
+    BudgetExhausted,
 }
 
-/// Options to download  data specified by the hash.
-pub struct BlobDownloadOptions(iroh::client::blobs::DownloadOptions);
-impl BlobDownloadOptions {
-    /// Create a BlobDownloadRequest
-    pub fn new(
-        format: BlobFormat,
-        node: Arc<NodeAddr>,
-        tag: Arc<SetTagOption>,
-    ) -> Result<Self, IrohError> {
-        Ok(BlobDownloadOptions(iroh::client::blobs::DownloadOptions {
-            format: format.into(),
-            nodes: vec![(*node).clone().try_into()?],
-            tag: (*tag).clone().into(),
-            mode: iroh::client::blobs::DownloadMode::Direct,
-        }))
-    }
+/// The tracked state and subscribers of a single running download.
+///
+/// Callbacks are never called while the state is locked, so a slow callback only holds up the
+/// download it is attached to, and a callback may subscribe to the download again.
+struct InFlightDownload {
+    inner: Mutex<InFlightDownloadInner>,
 }
 
-impl From<iroh::client::blobs::DownloadOptions> for BlobDownloadOptions {
-    fn from(value: iroh::client::blobs::DownloadOptions) -> Self {
-        BlobDownloadOptions(value)
+struct InFlightDownloadInner {
+    state: iroh::blobs::get::progress::TransferState,
+    subscribers: Vec<Arc<DownloadSubscriber>>,
+}
+
+/// A callback attached to an [`InFlightDownload`].
+struct DownloadSubscriber {
+    cb: Arc<dyn DownloadCallback>,
+    /// Held while an event is passed to `cb`, keeps the events of a subscriber in order.
+    delivery: Mutex<()>,
+}
+
+impl std::fmt::Debug for InFlightDownload {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let inner = self.inner.lock().unwrap();
+        f.debug_struct("InFlightDownload")
+            .field("state", &inner.state)
+            .field("subscribers", &inner.subscribers.len())
+            .finish()
     }
 }
 
-/// The expected format of a hash being exported.
-pub enum BlobExportFormat {
-    /// The hash refers to any blob and will be exported to a single file.
-    Blob,
-    /// The hash refers to a [`crate::format::collection::Collection`] blob
-    /// and all children of the collection shall be exported to one file per child.
-    ///
-    /// If the blob can be parsed as a [`BlobFormat::HashSeq`], and the first child contains
-    /// collection metadata, all other children of the collection will be exported to
-    /// a file each, with their collection name treated as a relative path to the export
-    /// destination path.
+impl InFlightDownload {
+    /// Apply a progress event to the tracked state and forward it to all subscribers.
     ///
-    /// If the blob cannot be parsed as a collection, the operation will fail.
-    Collection,
-}
+    /// Subscribers returning an error are dropped.
+    fn on_progress(&self, progress: &iroh::blobs::get::db::DownloadProgress) {
+        let subscribers = {
+            let mut inner = self.inner.lock().unwrap();
+            inner.state.on_progress(progress.clone());
+            inner.subscribers.clone()
+        };
+        let mut failed = Vec::new();
+        for sub in subscribers {
+            let _delivery = sub.delivery.lock().unwrap();
+            if sub.cb.progress(Arc::new(progress.clone().into())).is_err() {
+                failed.push(sub.clone());
+            }
+        }
+        if !failed.is_empty() {
+            let mut inner = self.inner.lock().unwrap();
+            inner
+                .subscribers
+                .retain(|sub| !failed.iter().any(|f| Arc::ptr_eq(f, sub)));
+        }
+    }
 
-impl From<BlobExportFormat> for iroh::blobs::store::ExportFormat {
-    fn from(value: BlobExportFormat) -> Self {
-        match value {
-            BlobExportFormat::Blob => iroh::blobs::store::ExportFormat::Blob,
-            BlobExportFormat::Collection => iroh::blobs::store::ExportFormat::Collection,
+    /// Forward an event of the transfer that did not come from the download itself, such as an
+    /// abort.
+    fn forward(&self, event: &DownloadProgress) {
+        let subscribers = self.inner.lock().unwrap().subscribers.clone();
+        for sub in subscribers {
+            let _delivery = sub.delivery.lock().unwrap();
+            sub.cb.progress(Arc::new(event.clone())).ok();
         }
     }
+
+    /// Send the current state to `cb` and add it to the subscribers.
+    fn subscribe(&self, cb: Arc<dyn DownloadCallback>) -> Result<(), CallbackError> {
+        let sub = Arc::new(DownloadSubscriber {
+            cb,
+            delivery: Mutex::new(()),
+        });
+        // the subscriber is added together with the snapshot of the state, and its delivery
+        // lock is taken before anyone else can see it, so no event overtakes the snapshot
+        let delivery = sub.delivery.lock().unwrap();
+        let initial = {
+            let mut inner = self.inner.lock().unwrap();
+            inner.subscribers.push(sub.clone());
+            iroh::blobs::get::db::DownloadProgress::InitialState(inner.state.clone())
+        };
+        let res = sub.cb.progress(Arc::new(initial.into()));
+        drop(delivery);
+        if res.is_err() {
+            let mut inner = self.inner.lock().unwrap();
+            inner.subscribers.retain(|other| !Arc::ptr_eq(other, &sub));
+        }
+        res
+    }
 }
 
-/// The export mode describes how files will be exported.
+/// A map kept in memory and persisted as json in a file of the node's data directory.
 ///
-/// This is a hint to the import trait method. For some implementations, this
-/// does not make any sense. E.g. an in memory implementation will always have
-/// to copy the file into memory. Also, a disk based implementation might choose
-/// to copy small files even if the mode is `Reference`.
-pub enum BlobExportMode {
-    /// This mode will copy the file to the target directory.
-    ///
-    /// This is the safe default because the file can not be accidentally modified
-    /// after it has been exported.
-    Copy,
-    /// This mode will try to move the file to the target directory and then reference it from
-    /// the database.
-    ///
-    /// This has a large performance and storage benefit, but it is less safe since
-    /// the file might be modified in the target directory after it has been exported.
-    ///
-    /// Stores are allowed to ignore this mode and always copy the file, e.g.
-    /// if the file is very small or if the store does not support referencing files.
-    TryReference,
+/// Used for the node-local state iroh does not store itself. The file is replaced atomically
+/// on each write, so a crash leaves either the previous or the new content. A file that cannot
+/// be parsed is logged and ignored: the state is optional, and the node must still start.
+#[derive(Debug)]
+pub(crate) struct JsonStore<T> {
+    path: PathBuf,
+    entries: Mutex<HashMap<String, T>>,
 }
 
-impl From<BlobExportMode> for iroh::blobs::store::ExportMode {
-    fn from(value: BlobExportMode) -> Self {
-        match value {
-            BlobExportMode::Copy => iroh::blobs::store::ExportMode::Copy,
-            BlobExportMode::TryReference => iroh::blobs::store::ExportMode::TryReference,
+impl<T: Serialize + DeserializeOwned + Clone> JsonStore<T> {
+    /// Load the entries stored at `path`, if any.
+    pub(crate) fn load(path: PathBuf) -> anyhow::Result<Self> {
+        let entries = match std::fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data).unwrap_or_else(|err| {
+                tracing::warn!("ignoring unreadable {}: {:?}", path.display(), err);
+                HashMap::new()
+            }),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(err) => return Err(err.into()),
+        };
+        Ok(JsonStore {
+            path,
+            entries: Mutex::new(entries),
+        })
+    }
+
+    pub(crate) fn get(&self, key: &str) -> Option<T> {
+        let entries = self.entries.lock().unwrap();
+        entries.get(key).cloned()
+    }
+
+    /// A copy of all entries.
+    pub(crate) fn entries(&self) -> HashMap<String, T> {
+        self.entries.lock().unwrap().clone()
+    }
+
+    pub(crate) fn insert(&self, key: String, value: T) -> anyhow::Result<()> {
+        self.update(|entries| {
+            entries.insert(key, value);
+        })
+    }
+
+    pub(crate) fn remove(&self, key: &str) -> anyhow::Result<()> {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.remove(key).is_some() {
+            self.persist(&entries)?;
         }
+        Ok(())
     }
-}
 
-/// The `progress` method will be called for each `DownloadProgress` event that is emitted during
-/// a `node.blobs_download`. Use the `DownloadProgress.type()` method to check the
-/// `DownloadProgressType` of the event.
-pub trait DownloadCallback: Send + Sync + 'static {
-    fn progress(&self, progress: Arc<DownloadProgress>) -> Result<(), CallbackError>;
+    /// Change an entry in memory only, it is persisted with the next write.
+    pub(crate) fn insert_unsaved(&self, key: String, value: T) {
+        self.entries.lock().unwrap().insert(key, value);
+    }
+
+    /// Change the entries with `f` and persist the result.
+    pub(crate) fn update<R>(
+        &self,
+        f: impl FnOnce(&mut HashMap<String, T>) -> R,
+    ) -> anyhow::Result<R> {
+        let mut entries = self.entries.lock().unwrap();
+        let res = f(&mut entries);
+        self.persist(&entries)?;
+        Ok(res)
+    }
+
+    /// Write `entries` to a temporary file next to the store and rename it over the store.
+    fn persist(&self, entries: &HashMap<String, T>) -> anyhow::Result<()> {
+        let dir = self
+            .path
+            .parent()
+            .ok_or_else(|| anyhow::anyhow!("{} has no parent", self.path.display()))?;
+        let mut file = tempfile::NamedTempFile::new_in(dir)?;
+        serde_json::to_writer(&mut file, entries)?;
+        file.as_file().sync_all()?;
+        file.persist(&self.path)?;
+        Ok(())
+    }
 }
 
-/// The different types of DownloadProgress events
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub enum DownloadProgressType {
-    InitialState,
-    FoundLocal,
-    Connected,
-    Found,
-    FoundHashSeq,
-    Progress,
-    Done,
-    AllDone,
-    Abort,
+/// Name of the file in the node's data directory the blob metadata is persisted in.
+const BLOB_METADATA_FILE: &str = "blob-metadata.json";
+
+/// Blob metadata, keyed by hash and persisted as json in the node's data directory.
+#[derive(Debug)]
+pub(crate) struct BlobMetadataStore(JsonStore<BlobMetadata>);
+
+impl BlobMetadataStore {
+    /// Load the metadata stored in the data directory `root`, if any.
+    pub(crate) fn load(root: &Path) -> anyhow::Result<Self> {
+        Ok(BlobMetadataStore(JsonStore::load(
+            root.join(BLOB_METADATA_FILE),
+        )?))
+    }
+
+    pub(crate) fn get(&self, hash: &iroh::blobs::Hash) -> Option<BlobMetadata> {
+        self.0.get(&hash.to_string())
+    }
+
+    pub(crate) fn set(&self, hash: iroh::blobs::Hash, meta: BlobMetadata) -> anyhow::Result<()> {
+        self.0.insert(hash.to_string(), meta)
+    }
+
+    pub(crate) fn remove(&self, hash: &iroh::blobs::Hash) -> anyhow::Result<()> {
+        self.0.remove(&hash.to_string())
+    }
 }
 
-/// A DownloadProgress event indicating an item was found with hash `hash`, that can be referred to by `id`
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct DownloadProgressFound {
-    /// A new unique id for this entry.
-    pub id: u64,
-    /// child offset
-    pub child: u64,
-    /// The hash of the entry.
-    pub hash: Arc<Hash>,
-    /// The size of the entry in bytes.
-    pub size: u64,
+/// Name of the file in the node's data directory the blob access times are persisted in.
+const BLOB_ACCESS_FILE: &str = "blob-access.json";
+
+/// How often the store size is checked against [`NodeOptions::max_store_bytes`].
+///
+/// [`NodeOptions::max_store_bytes`]: crate::NodeOptions::max_store_bytes
+const EVICTION_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// When each blob was last used, in microseconds since the unix epoch, keyed by hash.
+///
+/// Used to evict the least recently used blobs once the store exceeds
+/// [`NodeOptions::max_store_bytes`](crate::NodeOptions::max_store_bytes). Reads only update
+/// the times in memory, they are persisted by the eviction sweep.
+#[derive(Debug)]
+pub(crate) struct BlobAccessLog(JsonStore<u64>);
+
+impl BlobAccessLog {
+    /// Load the access times stored in the data directory `root`, if any.
+    pub(crate) fn load(root: &Path) -> anyhow::Result<Self> {
+        Ok(BlobAccessLog(JsonStore::load(root.join(BLOB_ACCESS_FILE))?))
+    }
+
+    /// Record that `hash` was used just now.
+    pub(crate) fn touch(&self, hash: iroh::blobs::Hash) {
+        self.0.insert_unsaved(hash.to_string(), now_micros());
+    }
+
+    /// The access times of `hashes`, dropping the entries of all other blobs.
+    ///
+    /// Blobs without an entry yet, e.g. because they were added since the last sweep, are
+    /// considered used just now.
+    fn retain(&self, hashes: &[iroh::blobs::Hash]) -> anyhow::Result<HashMap<String, u64>> {
+        let now = now_micros();
+        self.0.update(|entries| {
+            let mut retained = HashMap::with_capacity(hashes.len());
+            for hash in hashes {
+                let hash = hash.to_string();
+                let time = entries.get(&hash).copied().unwrap_or(now);
+                retained.insert(hash, time);
+            }
+            *entries = retained.clone();
+            retained
+        })
+    }
 }
 
-/// A DownloadProgress event indicating an entry was found locally
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct DownloadProgressFoundLocal {
-    /// child offset
-    pub child: u64,
-    /// The hash of the entry.
-    pub hash: Arc<Hash>,
-    /// The size of the entry in bytes.
-    pub size: u64,
-    /// The ranges that are available locally.
-    pub valid_ranges: Arc<RangeSpec>,
+/// Name of the file in the node's data directory the sources of downloads are persisted in.
+const DOWNLOAD_SOURCES_FILE: &str = "download-sources.json";
+
+/// Where a download started with [`IrohNode::blobs_download`] gets its data from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct DownloadSource {
+    format: iroh::blobs::BlobFormat,
+    nodes: Vec<iroh::net::endpoint::NodeAddr>,
+    tag: iroh::blobs::util::SetTagOption,
 }
 
-/// A DownloadProgress event indicating an item was found with hash `hash`, that can be referred to by `id`
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct DownloadProgressFoundHashSeq {
-    /// Number of children in the collection, if known.
-    pub children: u64,
-    /// The hash of the entry.
-    pub hash: Arc<Hash>,
+impl DownloadSource {
+    fn new(opts: &iroh::client::blobs::DownloadOptions) -> Self {
+        DownloadSource {
+            format: opts.format,
+            nodes: opts.nodes.clone(),
+            tag: opts.tag.clone(),
+        }
+    }
 }
 
-/// A DownloadProgress event indicating we got progress ingesting item `id`.
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct DownloadProgressProgress {
-    /// The unique id of the entry.
-    pub id: u64,
-    /// The offset of the progress, in bytes.
-    pub offset: u64,
+/// The sources of the downloads that did not complete yet, keyed by hash and persisted as json
+/// in the node's data directory, see [`IrohNode::blobs_resumable_downloads`].
+#[derive(Debug)]
+pub(crate) struct DownloadSourceStore(JsonStore<DownloadSource>);
+
+impl DownloadSourceStore {
+    /// Load the download sources stored in the data directory `root`, if any.
+    pub(crate) fn load(root: &Path) -> anyhow::Result<Self> {
+        Ok(DownloadSourceStore(JsonStore::load(
+            root.join(DOWNLOAD_SOURCES_FILE),
+        )?))
+    }
+
+    fn list(&self) -> Vec<(iroh::blobs::Hash, DownloadSource)> {
+        self.0
+            .entries()
+            .into_iter()
+            .filter_map(|(hash, source)| Some((hash.parse().ok()?, source)))
+            .collect()
+    }
+
+    fn get(&self, hash: &iroh::blobs::Hash) -> Option<DownloadSource> {
+        self.0.get(&hash.to_string())
+    }
+
+    fn set(&self, hash: iroh::blobs::Hash, source: DownloadSource) -> anyhow::Result<()> {
+        self.0.insert(hash.to_string(), source)
+    }
+
+    fn remove(&self, hash: &iroh::blobs::Hash) -> anyhow::Result<()> {
+        self.0.remove(&hash.to_string())
+    }
 }
 
-/// A DownloadProgress event indicated we are done with `id`
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct DownloadProgressDone {
-    /// The unique id of the entry.
-    pub id: u64,
+/// A download that did not complete, see [`IrohNode::blobs_resumable_downloads`].
+pub struct ResumableDownload {
+    node: Arc<IrohNode>,
+    hash: iroh::blobs::Hash,
+    size: u64,
+    expected_size: u64,
+    source: DownloadSource,
 }
 
-/// A DownloadProgress event indicating we are done with the whole operation
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct DownloadProgressAllDone {
-    /// The number of bytes written
-    pub bytes_written: u64,
-    /// The number of bytes read
-    pub bytes_read: u64,
-    /// The time it took to transfer the data
-    pub elapsed: Duration,
+impl ResumableDownload {
+    /// The hash of the blob or collection being downloaded.
+    pub fn hash(&self) -> Arc<Hash> {
+        Arc::new(self.hash.into())
+    }
+
+    /// The number of bytes of the blob received so far.
+    ///
+    /// For a collection, this is the size of the collection itself, not of its children.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// The size of the complete blob, 0 if it is not known yet because no data was received.
+    pub fn expected_size(&self) -> u64 {
+        self.expected_size
+    }
+
+    /// The nodes the download was using, in the order they were tried.
+    pub fn nodes(&self) -> Vec<Arc<NodeAddr>> {
+        self.source
+            .nodes
+            .iter()
+            .map(|node| Arc::new(node.clone().into()))
+            .collect()
+    }
+
+    /// Continue the download from its nodes, keeping the data received so far.
+    ///
+    /// Works like [`IrohNode::blobs_download`] with the format, nodes and tag of the original
+    /// download, trying each node once.
+    pub fn resume(&self, cb: Arc<dyn DownloadCallback>) -> Result<(), IrohError> {
+        let opts = iroh::client::blobs::DownloadOptions {
+            format: self.source.format,
+            nodes: self.source.nodes.clone(),
+            tag: self.source.tag.clone(),
+            mode: iroh::client::blobs::DownloadMode::Direct,
+        };
+        self.node
+            .blobs_download(self.hash(), Arc::new(opts.into()), cb)
+    }
 }
 
-/// A DownloadProgress event indicating we got an error and need to abort
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct DownloadProgressAbort {
-    pub error: String,
+/// How often the candidates are listed again in [`IrohNode::blobs_await_provider`].
+const PROVIDER_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long [`IrohNode::blobs_await_provider`] waits before asking a candidate again that did
+/// not have the content.
+const PROVIDER_BACKOFF_MIN: Duration = Duration::from_secs(2);
+
+/// The longest backoff of a candidate in [`IrohNode::blobs_await_provider`].
+const PROVIDER_BACKOFF_MAX: Duration = Duration::from_secs(30);
+
+/// How long a single candidate gets to answer in [`IrohNode::blobs_await_provider`].
+const PROVIDER_PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The nodes that might provide `hash`, see [`IrohNode::blobs_await_provider`].
+async fn provider_candidates(
+    node: &IrohNode,
+    hash: &iroh::blobs::Hash,
+) -> anyhow::Result<Vec<iroh::net::endpoint::NodeAddr>> {
+    let me = node.node.node_id();
+    let mut candidates: Vec<iroh::net::endpoint::NodeAddr> = node
+        .download_sources
+        .list()
+        .into_iter()
+        .filter(|(h, _)| h == hash)
+        .flat_map(|(_, source)| source.nodes)
+        .collect();
+    let mut infos = node.sync_client.connections().await?;
+    while let Some(info) = infos.next().await {
+        let info = info?;
+        let mut addr = iroh::net::endpoint::NodeAddr::new(info.node_id)
+            .with_direct_addresses(info.addrs.iter().map(|a| a.addr));
+        if let Some(relay) = info.relay_url {
+            addr = addr.with_relay_url(relay.relay_url);
+        }
+        candidates.push(addr);
+    }
+    let mut seen = HashSet::new();
+    candidates.retain(|addr| addr.node_id != me && seen.insert(addr.node_id));
+    Ok(candidates)
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct DownloadProgressInitialState {
-    // TODO(b5) - numerous fields missing
-    // /// The root blob of this transfer (may be a hash seq),
-    // pub root: BlobState,
-    /// Whether we are connected to a node
-    pub connected: bool,
-    // /// Children if the root blob is a hash seq, empty for raw blobs
-    // pub children: HashMap<NonZeroU64, BlobState>,
-    // /// Child being transferred at the moment.
-    // pub current: Option<BlobId>,
-    // /// Progress ids for individual blobs.
-    // pub progress_id_to_blob: HashMap<ProgressId, BlobId>,
+/// Whether the node at `addr` has the complete content of `hash`.
+async fn has_content(
+    endpoint: &iroh::net::Endpoint,
+    addr: iroh::net::endpoint::NodeAddr,
+    hash: &iroh::blobs::Hash,
+) -> bool {
+    let probe = async {
+        let conn = endpoint.connect(addr, iroh::blobs::protocol::ALPN).await?;
+        iroh::blobs::get::request::get_verified_size(&conn, hash).await
+    };
+    matches!(
+        tokio::time::timeout(PROVIDER_PROBE_TIMEOUT, probe).await,
+        Ok(Ok(_))
+    )
 }
 
-/// Progress updates for the get operation.
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub enum DownloadProgress {
-    /// Initial state if subscribing to a running or queued transfer.
-    InitialState(DownloadProgressInitialState),
-    /// A new connection was established.
-    Connected,
-    /// An item was found with hash `hash`, from now on referred to via `id`
-    Found(DownloadProgressFound),
-    /// Data was found locally
-    FoundLocal(DownloadProgressFoundLocal),
-    /// An item was found with hash `hash`, from now on referred to via `id`
-    FoundHashSeq(DownloadProgressFoundHashSeq),
-    /// We got progress ingesting item `id`.
-    Progress(DownloadProgressProgress),
-    /// We are done with `id`, and the hash is `hash`.
-    Done(DownloadProgressDone),
-    /// We are done with the whole operation.
-    AllDone(DownloadProgressAllDone),
-    /// We got an error and need to abort.
+/// How the files of an import get into the blob store.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportStrategy {
+    /// Copy the data into the store.
     ///
-    /// This will be the last message in the stream.
-    Abort(DownloadProgressAbort),
+    /// Safe in all cases: the source files can be changed, moved or removed afterwards.
+    Copy,
+    /// Keep the data in the source files and only reference them from the store.
+    ///
+    /// Saves space and time, but the source files must not be changed, moved or removed while
+    /// the blobs are stored, or reading the blobs fails.
+    Reference,
+    /// Hard-link the source files into the node's data directory and keep the data there.
+    ///
+    /// Saves space and time like [`Self::Reference`], and the source files may be moved or
+    /// removed afterwards. They must still not be modified in place: a hard link shares the
+    /// data with the source, so writing to the source changes the stored blob and reading it
+    /// fails. Tools that save by writing a new file and renaming it over the old one are fine.
+    /// Falls back to [`Self::Copy`] if the files cannot be hard-linked, e.g. because they are
+    /// on a different file system than the data directory.
+    HardLink,
 }
 
-impl From<iroh::blobs::get::db::DownloadProgress> for DownloadProgress {
-    fn from(value: iroh::blobs::get::db::DownloadProgress) -> Self {
-        match value {
-            iroh::blobs::get::db::DownloadProgress::InitialState(transfer_state) => {
-                DownloadProgress::InitialState(DownloadProgressInitialState {
-                    connected: transfer_state.connected,
-                })
-            }
-            iroh::blobs::get::db::DownloadProgress::FoundLocal {
-                child,
-                hash,
-                size,
-                valid_ranges,
-            } => DownloadProgress::FoundLocal(DownloadProgressFoundLocal {
-                child: child.into(),
-                hash: Arc::new(hash.into()),
-                // TODO(b5) - this is ignoring verification information!
-                size: size.value(),
-                valid_ranges: Arc::new(valid_ranges.into()),
-            }),
-            iroh::blobs::get::db::DownloadProgress::Connected => DownloadProgress::Connected,
-            iroh::blobs::get::db::DownloadProgress::Found {
-                id,
-                hash,
-                child,
-                size,
-            } => DownloadProgress::Found(DownloadProgressFound {
-                id,
-                hash: Arc::new(hash.into()),
-                child: child.into(),
-                size,
-            }),
-            iroh::blobs::get::db::DownloadProgress::FoundHashSeq { hash, children } => {
-                DownloadProgress::FoundHashSeq(DownloadProgressFoundHashSeq {
-                    hash: Arc::new(hash.into()),
-                    children,
-                })
-            }
-            iroh::blobs::get::db::DownloadProgress::Progress { id, offset } => {
-                DownloadProgress::Progress(DownloadProgressProgress { id, offset })
-            }
-            iroh::blobs::get::db::DownloadProgress::Done { id } => {
-                DownloadProgress::Done(DownloadProgressDone { id })
-            }
-            iroh::blobs::get::db::DownloadProgress::AllDone(stats) => {
-                DownloadProgress::AllDone(DownloadProgressAllDone {
-                    bytes_written: stats.bytes_written,
-                    bytes_read: stats.bytes_read,
-                    elapsed: stats.elapsed,
-                })
-            }
-            iroh::blobs::get::db::DownloadProgress::Abort(err) => {
-                DownloadProgress::Abort(DownloadProgressAbort {
-                    error: err.to_string(),
-                })
-            }
+impl ImportStrategy {
+    /// The strategy of an import with an explicit `strategy`, or else the `in_place` flag.
+    pub(crate) fn resolve(strategy: Option<ImportStrategy>, in_place: bool) -> Self {
+        match (strategy, in_place) {
+            (Some(strategy), _) => strategy,
+            (None, true) => ImportStrategy::Reference,
+            (None, false) => ImportStrategy::Copy,
         }
     }
 }
 
-impl DownloadProgress {
-    /// Get the type of event
-    /// note that there is no `as_connected` method, as the `Connected` event has no associated data
-    pub fn r#type(&self) -> DownloadProgressType {
-        match self {
-            DownloadProgress::InitialState(_) => DownloadProgressType::InitialState,
-            DownloadProgress::Connected => DownloadProgressType::Connected,
-            DownloadProgress::Found(_) => DownloadProgressType::Found,
-            DownloadProgress::FoundLocal(_) => DownloadProgressType::FoundLocal,
-            DownloadProgress::FoundHashSeq(_) => DownloadProgressType::FoundHashSeq,
-            DownloadProgress::Progress(_) => DownloadProgressType::Progress,
-            DownloadProgress::Done(_) => DownloadProgressType::Done,
-            DownloadProgress::AllDone(_) => DownloadProgressType::AllDone,
-            DownloadProgress::Abort(_) => DownloadProgressType::Abort,
+/// Name of the directory in the node's data directory hard-linked imports are staged in.
+const HARD_LINKS_DIR: &str = "hard-links";
+
+/// Name of the file in the node's data directory the hard-linked imports are recorded in.
+const HARD_LINKS_FILE: &str = "hard-links.json";
+
+/// How often hard-linked imports whose blobs are gone are removed while the node runs.
+const HARD_LINK_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The imports done with [`ImportStrategy::HardLink`], persisted as json in the node's data
+/// directory.
+///
+/// Each import is staged in its own directory below [`HARD_LINKS_DIR`], which is kept until
+/// none of the blobs imported from it is stored anymore.
+#[derive(Debug)]
+pub(crate) struct HardLinkStore {
+    dir: PathBuf,
+    /// The hashes imported from each staging directory, keyed by the directory name.
+    imports: JsonStore<Vec<String>>,
+}
+
+impl HardLinkStore {
+    /// Load the imports recorded in the data directory `root`, if any.
+    ///
+    /// Removes the staging directories of imports that never finished, e.g. because the app
+    /// was killed during the import.
+    pub(crate) fn load(root: &Path) -> anyhow::Result<Self> {
+        let imports = JsonStore::load(root.join(HARD_LINKS_FILE))?;
+        let recorded = imports.entries();
+        let dir = root.join(HARD_LINKS_DIR);
+        if let Ok(entries) = std::fs::read_dir(&dir) {
+            for entry in entries {
+                let entry = entry?;
+                if !recorded.contains_key(&*entry.file_name().to_string_lossy()) {
+                    std::fs::remove_dir_all(entry.path())?;
+                }
+            }
         }
+        Ok(HardLinkStore { dir, imports })
     }
 
-    /// Return the `DownloadProgressFound` event
-    pub fn as_found(&self) -> DownloadProgressFound {
-        match self {
-            DownloadProgress::Found(f) => f.clone(),
-            _ => panic!("DownloadProgress type is not 'Found'"),
+    /// The path to import for `strategy` and whether to import it in place, with the staging
+    /// directory to [`Self::record`] once the import is done.
+    pub(crate) fn prepare(
+        &self,
+        path: PathBuf,
+        strategy: ImportStrategy,
+    ) -> (PathBuf, bool, Option<String>) {
+        match strategy {
+            ImportStrategy::Copy => (path, false, None),
+            ImportStrategy::Reference => (path, true, None),
+            ImportStrategy::HardLink => match self.stage(&path) {
+                Ok((staging, staged)) => (staged, true, Some(staging)),
+                Err(err) => {
+                    tracing::debug!(
+                        "cannot hard-link {}, copying instead: {:?}",
+                        path.display(),
+                        err
+                    );
+                    (path, false, None)
+                }
+            },
         }
     }
 
-    /// Return the `DownloadProgressFoundLocal` event
-    pub fn as_found_local(&self) -> DownloadProgressFoundLocal {
-        match self {
-            DownloadProgress::FoundLocal(f) => f.clone(),
-            _ => panic!("DownloadProgress type is not 'FoundLocal'"),
-        }
+    /// Hard-link the files at `source` into a new staging directory, keeping the name of
+    /// `source`. Returns the name of the staging directory and the path to import.
+    fn stage(&self, source: &Path) -> anyhow::Result<(String, PathBuf)> {
+        let name = source
+            .file_name()
+            .ok_or_else(|| anyhow::anyhow!("{} has no file name", source.display()))?;
+        std::fs::create_dir_all(&self.dir)?;
+        let staging = tempfile::Builder::new()
+            .prefix("import-")
+            .tempdir_in(&self.dir)?;
+        let staged = staging.path().join(name);
+        link_tree(source, &staged)?;
+        // keep the directory, it is removed by the sweep once its blobs are gone
+        let staging = staging.into_path();
+        let staging = staging
+            .file_name()
+            .expect("created above")
+            .to_string_lossy()
+            .into_owned();
+        Ok((staging, staged))
     }
 
-    /// Return the `DownloadProgressFoundHashSeq` event
-    pub fn as_found_hash_seq(&self) -> DownloadProgressFoundHashSeq {
-        match self {
-            DownloadProgress::FoundHashSeq(f) => f.clone(),
-            _ => panic!("DownloadProgress type is not 'FoundHashSeq'"),
-        }
+    /// Record that the blobs `hashes` were imported from `staging`.
+    pub(crate) fn record(
+        &self,
+        staging: String,
+        hashes: Vec<iroh::blobs::Hash>,
+    ) -> anyhow::Result<()> {
+        self.imports.insert(
+            staging,
+            hashes.iter().map(|hash| hash.to_string()).collect(),
+        )
     }
+}
 
-    /// Return the `DownloadProgressProgress` event
-    pub fn as_progress(&self) -> DownloadProgressProgress {
-        match self {
-            DownloadProgress::Progress(p) => p.clone(),
-            _ => panic!("DownloadProgress type is not 'Progress'"),
+/// Hard-link the file at `source` to `target`, or all files below it if it is a directory.
+fn link_tree(source: &Path, target: &Path) -> std::io::Result<()> {
+    if source.is_dir() {
+        std::fs::create_dir(target)?;
+        for entry in std::fs::read_dir(source)? {
+            let entry = entry?;
+            link_tree(&entry.path(), &target.join(entry.file_name()))?;
         }
+        Ok(())
+    } else {
+        std::fs::hard_link(source, target)
     }
+}
 
-    /// Return the `DownloadProgressDone` event
-    pub fn as_done(&self) -> DownloadProgressDone {
-        match self {
-            DownloadProgress::Done(d) => d.clone(),
-            _ => panic!("DownloadProgress type is not 'Done'"),
+/// Remove the hard-linked imports none of whose blobs are stored anymore.
+async fn remove_unused_hard_links(client: &MemIroh, links: &HardLinkStore) -> anyhow::Result<()> {
+    let imports = links.imports.entries();
+    let mut unused = Vec::new();
+    for (staging, hashes) in imports {
+        let mut used = false;
+        for hash in hashes {
+            let hash = iroh::blobs::Hash::from_str(&hash)?;
+            if !matches!(
+                client.blobs().status(hash).await?,
+                iroh::client::blobs::BlobStatus::NotFound
+            ) {
+                used = true;
+                break;
+            }
+        }
+        if !used {
+            unused.push(staging);
         }
     }
-
-    /// Return the `DownloadProgressAllDone` event
-    pub fn as_all_done(&self) -> DownloadProgressAllDone {
-        match self {
-            DownloadProgress::AllDone(e) => e.clone(),
-            _ => panic!("DownloadProgress type is not 'AllDone'"),
+    if unused.is_empty() {
+        return Ok(());
+    }
+    let mut removed = Vec::new();
+    let mut res = Ok(());
+    for staging in unused {
+        match std::fs::remove_dir_all(links.dir.join(&staging)) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                res = Err(err.into());
+                break;
+            }
+            _ => removed.push(staging),
         }
     }
+    links.imports.update(|imports| {
+        for staging in &removed {
+            imports.remove(staging);
+        }
+    })?;
+    res
+}
 
-    /// Return the `DownloadProgressAbort` event
-    pub fn as_abort(&self) -> DownloadProgressAbort {
-        match self {
-            DownloadProgress::Abort(a) => a.clone(),
-            _ => panic!("DownloadProgress type is not 'Abort'"),
+/// Remove unused hard-linked imports until the node is shut down, see
+/// [`ImportStrategy::HardLink`].
+pub(crate) async fn sweep_hard_links(client: MemIroh, links: Arc<HardLinkStore>, state: NodeState) {
+    while state.is_running() {
+        if let Err(err) = remove_unused_hard_links(&client, &links).await {
+            tracing::warn!("failed to remove unused hard links: {:?}", err);
         }
+        tokio::time::sleep(HARD_LINK_SWEEP_INTERVAL).await;
     }
 }
 
-/// A chunk range specification as a sequence of chunk offsets
+/// The Hash and associated tag of a newly created collection
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct RangeSpec(pub(crate) iroh::blobs::protocol::RangeSpec);
+pub struct HashAndTag {
+    /// The hash of the collection
+    pub hash: Arc<Hash>,
+    /// The tag of the collection
+    pub tag: Vec<u8>,
+}
 
-impl RangeSpec {
-    /// Checks if this [`RangeSpec`] does not select any chunks in the blob
-    pub fn is_empty(&self) -> bool {
-        self.0.is_empty()
+/// Whether a blob is present on this node, see [`IrohNode::blobs_status_many`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlobStatus {
+    /// The blob is not present.
+    NotFound,
+    /// Some of the blob's data is present.
+    Partial {
+        /// The size of the whole blob, in bytes
+        size: u64,
+        /// Whether the size was verified against the data, or only announced by a peer
+        size_is_verified: bool,
+    },
+    /// The blob is completely present.
+    Complete {
+        /// The size of the blob, in bytes
+        size: u64,
+    },
+}
+
+impl From<iroh::client::blobs::BlobStatus> for BlobStatus {
+    fn from(value: iroh::client::blobs::BlobStatus) -> Self {
+        match value {
+            iroh::client::blobs::BlobStatus::NotFound => BlobStatus::NotFound,
+            iroh::client::blobs::BlobStatus::Partial { size } => BlobStatus::Partial {
+                size: size.value(),
+                size_is_verified: matches!(size, iroh::blobs::util::BaoBlobSize::Verified(_)),
+            },
+            iroh::client::blobs::BlobStatus::Complete { size } => BlobStatus::Complete { size },
+        }
     }
+}
 
-    /// Check if this [`RangeSpec`] selects all chunks in the blob
-    pub fn is_all(&self) -> bool {
-        self.0.is_all()
+/// A blob opened with [`IrohNode::blobs_open_reader`], read from a position that can be moved
+/// with [`Self::seek`].
+///
+/// The blob may be partial, i.e. not fully downloaded yet. Seeking works the same for partial
+/// blobs, but reading a range whose data is not present on the node fails. The size of a
+/// partial blob is the size announced when its download started and might not be verified yet.
+pub struct BlobReader {
+    client: MemIroh,
+    rt: tokio::runtime::Handle,
+    state: NodeState,
+    hash: iroh::blobs::Hash,
+    size: u64,
+    position: Mutex<u64>,
+}
+
+impl BlobReader {
+    /// The total size of the blob, in bytes.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// The position the next read starts at.
+    pub fn position(&self) -> u64 {
+        *self.position.lock().unwrap()
+    }
+
+    /// Move the position to `offset`, clamped to the size of the blob.
+    ///
+    /// Returns the new position.
+    pub fn seek(&self, offset: u64) -> u64 {
+        let position = offset.min(self.size);
+        *self.position.lock().unwrap() = position;
+        position
+    }
+
+    /// Read up to `max_len` bytes from the current position and advance past them.
+    ///
+    /// Returns an empty buffer at the end of the blob.
+    pub fn read(&self, max_len: u64) -> Result<Vec<u8>, IrohError> {
+        self.state.ensure_running()?;
+        let mut position = self.position.lock().unwrap();
+        let len = max_len.min(self.size - *position);
+        if len == 0 {
+            return Ok(Vec::new());
+        }
+        let len = usize::try_from(len).map_err(anyhow::Error::from)?;
+        let bytes = block_on(&self.rt, async {
+            let res = self
+                .client
+                .blobs()
+                .read_at_to_bytes(self.hash, *position, Some(len))
+                .await;
+            check_available(&self.client, self.hash, res).await
+        })?;
+        *position += bytes.len() as u64;
+        Ok(bytes.to_vec())
     }
 }
 
-impl From<iroh::blobs::protocol::RangeSpec> for RangeSpec {
-    fn from(h: iroh::blobs::protocol::RangeSpec) -> Self {
-        RangeSpec(h)
+/// Turn the error of a failed read of `hash` into [`ContentUnavailable`] if the blob is missing
+/// or incomplete on this node, so callers can tell it from other failures.
+pub(crate) async fn check_available<T>(
+    client: &MemIroh,
+    hash: iroh::blobs::Hash,
+    res: anyhow::Result<T>,
+) -> anyhow::Result<T> {
+    let err = match res {
+        Ok(value) => return Ok(value),
+        Err(err) => err,
+    };
+    match client.blobs().status(hash).await {
+        Ok(iroh::client::blobs::BlobStatus::NotFound) => Err(ContentUnavailable {
+            hash,
+            partial: false,
+        }
+        .into()),
+        Ok(iroh::client::blobs::BlobStatus::Partial { .. }) => Err(ContentUnavailable {
+            hash,
+            partial: true,
+        }
+        .into()),
+        Ok(iroh::client::blobs::BlobStatus::Complete { .. }) | Err(_) => Err(err),
     }
 }
 
-/// A response to a list blobs request
-#[derive(Debug, Clone)]
-pub struct BlobInfo {
-    /// Location of the blob
-    pub path: String,
+/// Outcome of a blob add operation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlobAddOutcome {
     /// The hash of the blob
     pub hash: Arc<Hash>,
+    /// The format the blob
+    pub format: BlobFormat,
     /// The size of the blob
     pub size: u64,
+    /// The tag of the blob
+    pub tag: Vec<u8>,
 }
 
-impl From<iroh::client::blobs::BlobInfo> for BlobInfo {
-    fn from(value: iroh::client::blobs::BlobInfo) -> Self {
-        BlobInfo {
-            path: value.path,
+impl From<iroh::client::blobs::AddOutcome> for BlobAddOutcome {
+    fn from(value: iroh::client::blobs::AddOutcome) -> Self {
+        BlobAddOutcome {
             hash: Arc::new(value.hash.into()),
+            format: value.format.into(),
             size: value.size,
+            tag: value.tag.0.to_vec(),
         }
     }
 }
 
-/// A response to a list blobs request
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct IncompleteBlobInfo {
-    /// The size we got
-    pub size: u64,
-    /// The size we expect
-    pub expected_size: u64,
-    /// The hash of the blob
-    pub hash: Arc<Hash>,
+/// An option for commands that allow setting a Tag
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SetTagOption {
+    /// A tag will be automatically generated
+    Auto,
+    /// The tag is explicitly vecnamed
+    Named(Vec<u8>),
 }
 
-impl From<iroh::client::blobs::IncompleteBlobInfo> for IncompleteBlobInfo {
-    fn from(value: iroh::client::blobs::IncompleteBlobInfo) -> Self {
-        IncompleteBlobInfo {
-            size: value.size,
-            expected_size: value.expected_size,
-            hash: Arc::new(value.hash.into()),
-        }
+impl SetTagOption {
+    /// Indicate you want an automatically generated tag
+    pub fn auto() -> Self {
+        SetTagOption::Auto
     }
-}
 
-/// A response to a list collections request
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CollectionInfo {
-    /// Tag of the collection
-    pub tag: Vec<u8>,
-    /// Hash of the collection
-    pub hash: Arc<Hash>,
-    /// Number of children in the collection
-    ///
-    /// This is an optional field, because the data is not always available.
-    pub total_blobs_count: Option<u64>,
-    /// Total size of the raw data referred to by all links
+    /// Indicate you want a named tag
     ///
-    /// This is an optional field, because the data is not always available.
-    pub total_blobs_size: Option<u64>,
-}
-
-impl From<iroh::client::blobs::CollectionInfo> for CollectionInfo {
-    fn from(value: iroh::client::blobs::CollectionInfo) -> Self {
-        CollectionInfo {
-            tag: value.tag.0.to_vec(),
-            hash: Arc::new(value.hash.into()),
-            total_blobs_count: value.total_blobs_count,
-            total_blobs_size: value.total_blobs_size,
-        }
+    /// Fails if `tag` starts with `auto-`, which is reserved for automatically generated tags.
+    pub fn named(tag: Vec<u8>) -> Result<Self, IrohError> {
+        check_tag_name(&tag)?;
+        Ok(SetTagOption::Named(tag))
     }
 }
 
-/// A collection of blobs
-#[derive(Debug)]
-pub struct Collection(pub(crate) RwLock<iroh::blobs::format::collection::Collection>);
+impl From<SetTagOption> for iroh::blobs::util::SetTagOption {
+    fn from(value: SetTagOption) -> Self {
+        match value {
+            SetTagOption::Auto => iroh::blobs::util::SetTagOption::Auto,
+            SetTagOption::Named(tag) => {
+                iroh::blobs::util::SetTagOption::Named(iroh::blobs::Tag(bytes::Bytes::from(tag)))
+            }
+        }
+    }
+}
 
-impl From<iroh::blobs::format::collection::Collection> for Collection {
-    fn from(value: iroh::blobs::format::collection::Collection) -> Self {
-        Collection(RwLock::new(value))
+/// Whether to wrap the added data in a collection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WrapOption {
+    /// Do not wrap the file or directory.
+    NoWrap,
+    /// Wrap the file or directory in a colletion.
+    Wrap {
+        /// Override the filename in the wrapping collection.
+        name: Option<String>,
+    },
+}
+
+impl WrapOption {
+    /// Indicate you do not wrap the file or directory.
+    pub fn no_wrap() -> Self {
+        WrapOption::NoWrap
+    }
+
+    /// Indicate you want to wrap the file or directory in a colletion, with an optional name
+    pub fn wrap(name: Option<String>) -> Self {
+        WrapOption::Wrap { name }
     }
 }
 
-impl From<Collection> for iroh::blobs::format::collection::Collection {
-    fn from(value: Collection) -> Self {
-        let col = value.0.read().expect("Collection lock poisoned");
-        col.clone()
+impl From<WrapOption> for iroh::client::blobs::WrapOption {
+    fn from(value: WrapOption) -> Self {
+        match value {
+            WrapOption::NoWrap => iroh::client::blobs::WrapOption::NoWrap,
+            WrapOption::Wrap { name } => iroh::client::blobs::WrapOption::Wrap { name },
+        }
     }
 }
 
-impl Collection {
-    /// Create a new empty collection
-    #[allow(clippy::new_without_default)]
-    pub fn new() -> Self {
-        Collection(RwLock::new(
-            iroh::blobs::format::collection::Collection::default(),
-        ))
+/// Hash type used throughout Iroh. A blake3 hash.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hash(pub(crate) iroh::blobs::Hash);
+
+impl From<iroh::blobs::Hash> for Hash {
+    fn from(h: iroh::blobs::Hash) -> Self {
+        Hash(h)
     }
+}
 
-    /// Add the given blob to the collection
-    pub fn push(&self, name: String, hash: &Hash) -> Result<(), IrohError> {
-        self.0.write().unwrap().push(name, hash.0);
-        Ok(())
+impl Hash {
+    /// Calculate the hash of the provide bytes.
+    pub fn new(buf: Vec<u8>) -> Self {
+        Hash(iroh::blobs::Hash::new(buf))
     }
 
-    /// Check if the collection is empty
-    pub fn is_empty(&self) -> Result<bool, IrohError> {
-        Ok(self.0.read().unwrap().is_empty())
+    /// Bytes of the hash.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0.as_bytes().to_vec()
     }
 
-    /// Get the names of the blobs in this collection
-    pub fn names(&self) -> Result<Vec<String>, IrohError> {
-        Ok(self
-            .0
-            .read()
-            .unwrap()
-            .iter()
-            .map(|(name, _)| name.clone())
-            .collect())
+    /// Create a `Hash` from its raw bytes representation.
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, IrohError> {
+        let bytes: [u8; 32] = bytes.try_into().map_err(|b: Vec<u8>| {
+            anyhow::anyhow!("expected byte array of length 32, got {}", b.len())
+        })?;
+        Ok(Hash(iroh::blobs::Hash::from_bytes(bytes)))
     }
 
-    /// Get the links to the blobs in this collection
-    pub fn links(&self) -> Result<Vec<Arc<Hash>>, IrohError> {
-        Ok(self
-            .0
-            .read()
-            .unwrap()
-            .iter()
-            .map(|(_, hash)| Arc::new(Hash(*hash)))
-            .collect())
+    /// Make a Hash from hex string
+    pub fn from_string(s: String) -> Result<Self, IrohError> {
+        let key = iroh::blobs::Hash::from_str(&s).map_err(anyhow::Error::from)?;
+        Ok(key.into())
     }
 
-    /// Get the blobs associated with this collection
-    pub fn blobs(&self) -> Result<Vec<LinkAndName>, IrohError> {
-        Ok(self
-            .0
-            .read()
-            .unwrap()
-            .iter()
-            .map(|(name, hash)| LinkAndName {
-                name: name.clone(),
-                link: Arc::new(Hash(*hash)),
-            })
-            .collect())
+    /// Convert the hash to a hex string.
+    pub fn to_hex(&self) -> String {
+        self.0.to_hex()
     }
 
-    /// Returns the number of blobs in this collection
-    pub fn len(&self) -> Result<u64, IrohError> {
-        Ok(self.0.read().unwrap().len() as _)
+    /// Returns true if the Hash's have the same value
+    pub fn equal(&self, other: &Hash) -> bool {
+        *self == *other
     }
 }
 
-/// `LinkAndName` includes a name and a hash for a blob in a collection
-#[derive(Clone, Debug)]
-pub struct LinkAndName {
-    /// The name associated with this [`Hash`]
-    pub name: String,
-    /// The [`Hash`] of the blob
-    pub link: Arc<Hash>,
+impl std::fmt::Display for Hash {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use std::io::Write;
-    use std::sync::{Arc, Mutex};
+impl From<Hash> for iroh::blobs::Hash {
+    fn from(value: Hash) -> Self {
+        value.0
+    }
+}
 
-    use super::*;
-    use crate::node::IrohNode;
-    use crate::{CallbackError, NodeOptions};
-    use bytes::Bytes;
-    use rand::RngCore;
-    use tokio::io::AsyncWriteExt;
-    use tracing_subscriber::FmtSubscriber;
+/// Prefix of the tags created by [`IrohNode::blobs_pin_until`].
+const PIN_TAG_PREFIX: &str = "iroh-ffi/pin-until/";
 
-    #[test]
-    fn test_hash() {
-        let hash_str = "6vp273v6cqbbq7xesa2xfrdt3oajykgeifprn3pj4p6y76654amq";
-        let hex_str = "f55fafeebe1402187ee4903572c473db809c28c4415f16ede9e3fd8ffbdde019";
-        let bytes = b"\xf5\x5f\xaf\xee\xbe\x14\x02\x18\x7e\xe4\x90\x35\x72\xc4\x73\xdb\x80\x9c\x28\xc4\x41\x5f\x16\xed\xe9\xe3\xfd\x8f\xfb\xdd\xe0\x19".to_vec();
+/// How often expired pins are removed while the node runs.
+const PIN_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
 
-        // create hash from string
-        let hash = Hash::from_string(hash_str.into()).unwrap();
+/// The current time in microseconds since the unix epoch.
+fn now_micros() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_micros() as u64
+}
 
-        // test methods are as expected
-        assert_eq!(hash_str.to_string(), hash.to_string());
-        assert_eq!(bytes.to_vec(), hash.to_bytes());
-        assert_eq!(hex_str.to_string(), hash.to_hex());
+/// The expiry of a tag created by [`IrohNode::blobs_pin_until`], `None` for other tags.
+fn pin_expiry(tag: &[u8]) -> Option<u64> {
+    let rest = tag.strip_prefix(PIN_TAG_PREFIX.as_bytes())?;
+    let (expiry, _) = std::str::from_utf8(rest).ok()?.split_once('/')?;
+    expiry.parse().ok()
+}
 
-        // create hash from bytes
-        let hash_0 = Hash::from_bytes(bytes.clone()).unwrap();
+/// The pinned blob of a tag created by [`IrohNode::blobs_pin_until`], `None` for other tags.
+fn pin_hash(tag: &[u8]) -> Option<iroh::blobs::Hash> {
+    let rest = tag.strip_prefix(PIN_TAG_PREFIX.as_bytes())?;
+    let (_, hash) = std::str::from_utf8(rest).ok()?.split_once('/')?;
+    hash.parse().ok()
+}
 
-        // test methods are as expected
-        assert_eq!(hash_str.to_string(), hash_0.to_string());
-        assert_eq!(bytes, hash_0.to_bytes());
-        assert_eq!(hex_str.to_string(), hash_0.to_hex());
+/// Delete the pins that expired at `now_micros`.
+async fn remove_expired_pins(client: &MemIroh, now_micros: u64) -> anyhow::Result<()> {
+    let expired = client
+        .tags()
+        .list()
+        .await?
+        .try_filter(|tag| {
+            let expired = pin_expiry(&tag.name.0).is_some_and(|expiry| expiry <= now_micros);
+            futures::future::ready(expired)
+        })
+        .try_collect::<Vec<_>>()
+        .await?;
+    for tag in expired {
+        client.tags().delete(tag.name).await?;
+    }
+    Ok(())
+}
+
+/// Remove expired pins until the node is shut down, see [`IrohNode::blobs_pin_until`].
+pub(crate) async fn sweep_expired_pins(client: MemIroh, state: NodeState) {
+    while state.is_running() {
+        if let Err(err) = remove_expired_pins(&client, now_micros()).await {
+            tracing::warn!("failed to remove expired pins: {:?}", err);
+        }
+        tokio::time::sleep(PIN_SWEEP_INTERVAL).await;
+    }
+}
+
+/// Collect the hashes of all blobs that are the content of an entry in a document.
+async fn doc_referenced_blobs(client: &MemIroh) -> anyhow::Result<HashSet<iroh::blobs::Hash>> {
+    let mut referenced = HashSet::new();
+    let mut docs = client.docs().list().await?;
+    while let Some(doc) = docs.next().await {
+        let (id, _) = doc?;
+        let Some(doc) = client.docs().open(id).await? else {
+            continue;
+        };
+        let mut entries = doc.get_many(iroh::docs::store::Query::all()).await?;
+        while let Some(entry) = entries.next().await {
+            referenced.insert(entry?.content_hash());
+        }
+    }
+    Ok(referenced)
+}
+
+/// Prefix of the tags iroh creates for [`SetTagOption::Auto`].
+///
+/// The prefix is reserved: the APIs creating named tags reject it, so that a tag with it is
+/// one iroh generated and eviction may delete it.
+const AUTO_TAG_PREFIX: &[u8] = b"auto-";
+
+/// Whether `name` is a tag iroh generated for [`SetTagOption::Auto`], as opposed to a tag
+/// named by the application.
+fn is_auto_tag(name: &iroh::blobs::Tag) -> bool {
+    name.0.starts_with(AUTO_TAG_PREFIX)
+}
+
+/// Check that `name` can be used for a named tag, i.e. that it doesn't use the reserved
+/// [`AUTO_TAG_PREFIX`].
+fn check_tag_name(name: &[u8]) -> anyhow::Result<()> {
+    if name.starts_with(AUTO_TAG_PREFIX) {
+        anyhow::bail!("tag names starting with \"auto-\" are reserved for automatic tags");
+    }
+    Ok(())
+}
+
+/// Evict the least recently used blobs until the complete blobs fit into `max_bytes`.
+///
+/// Only blobs that are untagged or only have auto tags are evicted: blobs with a named tag,
+/// including pins from [`IrohNode::blobs_pin_until`], children of a tagged hash sequence and
+/// the content of document entries are never evicted. Evicting a blob deletes its auto tags.
+/// A tagged hash sequence that is not stored locally is skipped. Returns the evicted hashes,
+/// least recently used first.
+pub(crate) async fn evict_lru_blobs(
+    client: &MemIroh,
+    access: &BlobAccessLog,
+    metadata: &BlobMetadataStore,
+    max_bytes: u64,
+) -> anyhow::Result<Vec<iroh::blobs::Hash>> {
+    let blobs = client
+        .blobs()
+        .list()
+        .await?
+        .map_ok(|blob| (blob.hash, blob.size))
+        .try_collect::<Vec<_>>()
+        .await?;
+    let hashes = blobs.iter().map(|(hash, _)| *hash).collect::<Vec<_>>();
+    let times = access.retain(&hashes)?;
+    let mut total: u64 = blobs.iter().map(|(_, size)| size).sum();
+    if total <= max_bytes {
+        return Ok(Vec::new());
+    }
+
+    let mut protected = doc_referenced_blobs(client).await?;
+    let tags = client.tags().list().await?.try_collect::<Vec<_>>().await?;
+    for tag in &tags {
+        if !is_auto_tag(&tag.name) {
+            protected.insert(tag.hash);
+        }
+        if tag.format.is_hash_seq() {
+            let children = client
+                .blobs()
+                .read_to_bytes(tag.hash)
+                .await
+                .and_then(iroh::blobs::hashseq::HashSeq::try_from);
+            match children {
+                Ok(children) => protected.extend(children.iter()),
+                Err(err) => {
+                    tracing::warn!(
+                        "skipping hash sequence {} for eviction: {:?}",
+                        tag.hash,
+                        err
+                    );
+                    protected.insert(tag.hash);
+                }
+            }
+        }
+    }
+
+    let mut candidates = blobs
+        .into_iter()
+        .filter(|(hash, _)| !protected.contains(hash))
+        .collect::<Vec<_>>();
+    candidates.sort_by_key(|(hash, _)| times.get(&hash.to_string()).copied().unwrap_or(0));
+
+    let mut evicted = Vec::new();
+    for (hash, size) in candidates {
+        if total <= max_bytes {
+            break;
+        }
+        for tag in tags.iter().filter(|tag| tag.hash == hash) {
+            client.tags().delete(tag.name.clone()).await?;
+        }
+        client.blobs().delete_blob(hash).await?;
+        metadata.remove(&hash)?;
+        total = total.saturating_sub(size);
+        evicted.push(hash);
+    }
+    Ok(evicted)
+}
+
+/// Keep the store within `max_bytes` until the node is shut down, see
+/// [`NodeOptions::max_store_bytes`](crate::NodeOptions::max_store_bytes).
+pub(crate) async fn sweep_store_size(
+    client: MemIroh,
+    access: Arc<BlobAccessLog>,
+    metadata: Arc<BlobMetadataStore>,
+    max_bytes: u64,
+    state: NodeState,
+) {
+    while state.is_running() {
+        match evict_lru_blobs(&client, &access, &metadata, max_bytes).await {
+            Ok(evicted) => {
+                for hash in evicted {
+                    tracing::debug!("evicted blob {hash} to stay within {max_bytes} bytes");
+                }
+            }
+            Err(err) => tracing::warn!("failed to evict blobs: {:?}", err),
+        }
+        tokio::time::sleep(EVICTION_SWEEP_INTERVAL).await;
+    }
+}
+
+/// The `progress` method will be called for each `AddProgress` event that is
+/// emitted during a `node.blobs_add_from_path`. Use the `AddProgress.type()`
+/// method to check the `AddProgressType`
+pub trait AddCallback: Send + Sync + 'static {
+    fn progress(&self, progress: Arc<AddProgress>) -> Result<(), CallbackError>;
+}
+
+/// The `on_hash` method will be called once for each hash listed by
+/// `node.blobs_list_stream`, or removed by `node.blobs_cleanup_orphans`.
+pub trait HashCallback: Send + Sync + 'static {
+    fn on_hash(&self, hash: Arc<Hash>) -> Result<(), CallbackError>;
+}
+
+impl AddCallback for ProgressSender<AddProgress> {
+    fn progress(&self, progress: Arc<AddProgress>) -> Result<(), CallbackError> {
+        self.send(progress)
+    }
+}
+
+/// The progress events of an import started with [`IrohNode::blobs_add_from_path_stream`].
+pub struct AddProgressStream(ProgressReceiver<AddProgress>);
+
+impl AddProgressStream {
+    /// Wait for the next event, `None` once the import is done.
+    ///
+    /// This is async, so waiting does not block the calling thread. Fails with the error of
+    /// the import if it failed.
+    pub async fn next(&self) -> Result<Option<Arc<AddProgress>>, IrohError> {
+        self.0.next().await
+    }
+}
+
+/// The different types of AddProgress events
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
+pub enum AddProgressType {
+    /// An item was found with name `name`, from now on referred to via `id`
+    Found,
+    /// We got progress ingesting item `id`.
+    Progress,
+    /// We are done with `id`, and the hash is `hash`.
+    Done,
+    /// We are done with the whole operation.
+    AllDone,
+    /// We got an error and need to abort.
+    ///
+    /// This will be the last message in the stream.
+    Abort,
+}
+
+/// An AddProgress event indicating an item was found with name `name`, that can be referred to by `id`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AddProgressFound {
+    /// A new unique id for this entry.
+    pub id: u64,
+    /// The name of the entry.
+    pub name: String,
+    /// The size of the entry in bytes.
+    pub size: u64,
+}
+
+/// An AddProgress event indicating we got progress ingesting item `id`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AddProgressProgress {
+    /// The unique id of the entry.
+    pub id: u64,
+    /// The offset of the progress, in bytes.
+    pub offset: u64,
+}
+
+/// An AddProgress event indicated we are done with `id` and now have a hash `hash`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AddProgressDone {
+    /// The unique id of the entry.
+    pub id: u64,
+    /// The hash of the entry.
+    pub hash: Arc<Hash>,
+}
+
+/// An AddProgress event indicating we are done with the the whole operation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AddProgressAllDone {
+    /// The hash of the created data.
+    pub hash: Arc<Hash>,
+    /// The format of the added data.
+    pub format: BlobFormat,
+    /// The tag of the added data.
+    pub tag: Vec<u8>,
+}
+
+/// An AddProgress event indicating we got an error and need to abort
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AddProgressAbort {
+    pub error: String,
+}
+
+/// Progress updates for the add operation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AddProgress {
+    /// An item was found with name `name`, from now on referred to via `id`
+    Found(AddProgressFound),
+    /// We got progress ingesting item `id`.
+    Progress(AddProgressProgress),
+    /// We are done with `id`, and the hash is `hash`.
+    Done(AddProgressDone),
+    /// We are done with the whole operation.
+    AllDone(AddProgressAllDone),
+    /// We got an error and need to abort.
+    ///
+    /// This will be the last message in the stream.
+    Abort(AddProgressAbort),
+}
+
+impl From<iroh::blobs::provider::AddProgress> for AddProgress {
+    fn from(value: iroh::blobs::provider::AddProgress) -> Self {
+        match value {
+            iroh::blobs::provider::AddProgress::Found { id, name, size } => {
+                AddProgress::Found(AddProgressFound { id, name, size })
+            }
+            iroh::blobs::provider::AddProgress::Progress { id, offset } => {
+                AddProgress::Progress(AddProgressProgress { id, offset })
+            }
+            iroh::blobs::provider::AddProgress::Done { id, hash } => {
+                AddProgress::Done(AddProgressDone {
+                    id,
+                    hash: Arc::new(hash.into()),
+                })
+            }
+            iroh::blobs::provider::AddProgress::AllDone { hash, format, tag } => {
+                AddProgress::AllDone(AddProgressAllDone {
+                    hash: Arc::new(hash.into()),
+                    format: format.into(),
+                    tag: tag.0.to_vec(),
+                })
+            }
+            iroh::blobs::provider::AddProgress::Abort(err) => {
+                AddProgress::Abort(AddProgressAbort {
+                    error: err.to_string(),
+                })
+            }
+        }
+    }
+}
+
+impl AddProgress {
+    /// Get the type of event
+    pub fn r#type(&self) -> AddProgressType {
+        match self {
+            AddProgress::Found(_) => AddProgressType::Found,
+            AddProgress::Progress(_) => AddProgressType::Progress,
+            AddProgress::Done(_) => AddProgressType::Done,
+            AddProgress::AllDone(_) => AddProgressType::AllDone,
+            AddProgress::Abort(_) => AddProgressType::Abort,
+        }
+    }
+    /// Return the `AddProgressFound` event
+    pub fn as_found(&self) -> AddProgressFound {
+        match self {
+            AddProgress::Found(f) => f.clone(),
+            _ => panic!("AddProgress type is not 'Found'"),
+        }
+    }
+    /// Return the `AddProgressProgress` event
+    pub fn as_progress(&self) -> AddProgressProgress {
+        match self {
+            AddProgress::Progress(p) => p.clone(),
+            _ => panic!("AddProgress type is not 'Progress'"),
+        }
+    }
+
+    /// Return the `AddProgressDone` event
+    pub fn as_done(&self) -> AddProgressDone {
+        match self {
+            AddProgress::Done(d) => d.clone(),
+            _ => panic!("AddProgress type is not 'Done'"),
+        }
+    }
+
+    /// Return the `AddAllDone`
+    pub fn as_all_done(&self) -> AddProgressAllDone {
+        match self {
+            AddProgress::AllDone(a) => a.clone(),
+            _ => panic!("AddProgress type is not 'AllDone'"),
+        }
+    }
+
+    /// Return the `AddProgressAbort`
+    pub fn as_abort(&self) -> AddProgressAbort {
+        match self {
+            AddProgress::Abort(a) => a.clone(),
+            _ => panic!("AddProgress type is not 'Abort'"),
+        }
+    }
+}
+
+/// A format identifier
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BlobFormat {
+    /// Raw blob
+    Raw,
+    /// A sequence of BLAKE3 hashes
+    HashSeq,
+}
+
+impl From<iroh::blobs::BlobFormat> for BlobFormat {
+    fn from(value: iroh::blobs::BlobFormat) -> Self {
+        match value {
+            iroh::blobs::BlobFormat::Raw => BlobFormat::Raw,
+            iroh::blobs::BlobFormat::HashSeq => BlobFormat::HashSeq,
+        }
+    }
+}
+
+impl From<BlobFormat> for iroh::blobs::BlobFormat {
+    fn from(value: BlobFormat) -> Self {
+        match value {
+            BlobFormat::Raw => iroh::blobs::BlobFormat::Raw,
+            BlobFormat::HashSeq => iroh::blobs::BlobFormat::HashSeq,
+        }
+    }
+}
+
+/// Optional settings of a download, see [`BlobDownloadOptions::new`].
+///
+/// Every field left unset takes its default.
+#[derive(Debug, Clone, Default)]
+pub struct BlobDownloadConfig {
+    /// How often a failed download from a node is tried in total before moving on to the
+    /// next node. Must be at least 1, defaults to 1.
+    pub max_attempts_per_node: Option<u32>,
+    /// The order in which the nodes are tried, see [`NodeOrder`]. Defaults to
+    /// [`NodeOrder::AsGiven`].
+    pub node_order: Option<NodeOrder>,
+    /// Which of the nodes' addresses are used, see [`PathPreference`]. Defaults to
+    /// [`PathPreference::Any`].
+    pub path_preference: Option<PathPreference>,
+    /// What happens to the data of blobs that were only partially received when the download
+    /// fails or is cancelled.
+    ///
+    /// If `true` (the default), the partial data is kept, so that a later download of the same
+    /// hash resumes where this one stopped. Note that partial data is not protected by a tag,
+    /// so the garbage collector may still remove it before the download is resumed. If
+    /// `false`, the partial data this download created is removed as soon as the download
+    /// stops. Blobs that were completely received, blobs that already had data before the
+    /// download started, e.g. from an earlier interrupted download, and blobs another running
+    /// download of the same hash writes to are never removed.
+    pub keep_partial_on_abort: Option<bool>,
+    /// Caps the data a download may consume, e.g. to preview the start of a large file.
+    ///
+    /// Once the offsets reached in the blobs of the download add up to the budget, the
+    /// download stops with a `DownloadProgress::BudgetExhausted` event instead of an error and
+    /// the partial data is kept, whatever `keep_partial_on_abort` says, so it can be resumed.
+    /// The check runs after each chunk of data, so slightly more than the budget may be
+    /// received. Data of a blob that was already present before the download counts toward the
+    /// budget as well. The budget applies to the download as a whole, across all attempts.
+    /// `None` means no budget.
+    pub byte_budget: Option<u64>,
+}
+
+/// Options to download  data specified by the hash.
+pub struct BlobDownloadOptions {
+    opts: iroh::client::blobs::DownloadOptions,
+    keep_partial_on_abort: bool,
+    max_attempts_per_node: u32,
+    node_order: NodeOrder,
+    byte_budget: Option<u64>,
+}
+impl BlobDownloadOptions {
+    /// Create a BlobDownloadRequest
+    ///
+    /// `config` holds the optional settings of the download, see [`BlobDownloadConfig`].
+    pub fn new(
+        format: BlobFormat,
+        node: Arc<NodeAddr>,
+        tag: Arc<SetTagOption>,
+        config: Option<BlobDownloadConfig>,
+    ) -> Result<Self, IrohError> {
+        Self::with_nodes(format, vec![node], tag, config)
+    }
+
+    /// Create a BlobDownloadRequest for several candidate nodes.
+    ///
+    /// The download is tried from one node after the other, in the order given by
+    /// `config.node_order`, see [`NodeOrder`]. A failed download from a node is tried again up
+    /// to `config.max_attempts_per_node` times in total before moving on to the next node.
+    /// The download fails once all attempts failed.
+    pub fn with_nodes(
+        format: BlobFormat,
+        nodes: Vec<Arc<NodeAddr>>,
+        tag: Arc<SetTagOption>,
+        config: Option<BlobDownloadConfig>,
+    ) -> Result<Self, IrohError> {
+        if nodes.is_empty() {
+            return Err(anyhow::anyhow!("at least one node is needed to download from").into());
+        }
+        let config = config.unwrap_or_default();
+        let max_attempts_per_node = config.max_attempts_per_node.unwrap_or(1);
+        if max_attempts_per_node == 0 {
+            return Err(anyhow::anyhow!("max_attempts_per_node must be at least 1").into());
+        }
+        let path_preference = config.path_preference.unwrap_or(PathPreference::Any);
+        Ok(BlobDownloadOptions {
+            opts: iroh::client::blobs::DownloadOptions {
+                format: format.into(),
+                nodes: nodes
+                    .into_iter()
+                    .map(|node| Ok(path_preference.apply((*node).clone().try_into()?)))
+                    .collect::<Result<_, IrohError>>()?,
+                tag: (*tag).clone().into(),
+                mode: iroh::client::blobs::DownloadMode::Direct,
+            },
+            keep_partial_on_abort: config.keep_partial_on_abort.unwrap_or(true),
+            max_attempts_per_node,
+            node_order: config.node_order.unwrap_or(NodeOrder::AsGiven),
+            byte_budget: config.byte_budget,
+        })
+    }
+
+    /// The nodes to download from, in the order they are tried.
+    async fn ordered_nodes(&self, client: &MemIroh) -> Vec<iroh::net::endpoint::NodeAddr> {
+        let mut nodes = self.opts.nodes.clone();
+        if self.node_order == NodeOrder::LowestLatencyFirst {
+            let mut latencies = HashMap::new();
+            for node in &nodes {
+                let latency = client
+                    .connection_info(node.node_id)
+                    .await
+                    .ok()
+                    .flatten()
+                    .and_then(|info| info.latency);
+                latencies.insert(node.node_id, latency);
+            }
+            // nodes without a known latency go last, in the order given
+            nodes.sort_by_key(|node| {
+                let latency = latencies[&node.node_id];
+                (latency.is_none(), latency)
+            });
+        }
+        nodes
+    }
+}
+
+impl From<iroh::client::blobs::DownloadOptions> for BlobDownloadOptions {
+    fn from(value: iroh::client::blobs::DownloadOptions) -> Self {
+        BlobDownloadOptions {
+            opts: value,
+            keep_partial_on_abort: true,
+            max_attempts_per_node: 1,
+            node_order: NodeOrder::AsGiven,
+            byte_budget: None,
+        }
+    }
+}
+
+/// The order in which the nodes of a download are tried, see [`BlobDownloadOptions::with_nodes`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeOrder {
+    /// Try the nodes in the order they were given.
+    AsGiven,
+    /// Try the nodes with the lowest latency of an existing connection first.
+    ///
+    /// Nodes this node has no latency information for are tried last, in the order given.
+    LowestLatencyFirst,
+}
+
+/// The expected format of a hash being exported.
+pub enum BlobExportFormat {
+    /// The hash refers to any blob and will be exported to a single file.
+    Blob,
+    /// The hash refers to a [`crate::format::collection::Collection`] blob
+    /// and all children of the collection shall be exported to one file per child.
+    ///
+    /// If the blob can be parsed as a [`BlobFormat::HashSeq`], and the first child contains
+    /// collection metadata, all other children of the collection will be exported to
+    /// a file each, with their collection name treated as a relative path to the export
+    /// destination path.
+    ///
+    /// If the blob cannot be parsed as a collection, the operation will fail.
+    Collection,
+}
+
+impl From<BlobExportFormat> for iroh::blobs::store::ExportFormat {
+    fn from(value: BlobExportFormat) -> Self {
+        match value {
+            BlobExportFormat::Blob => iroh::blobs::store::ExportFormat::Blob,
+            BlobExportFormat::Collection => iroh::blobs::store::ExportFormat::Collection,
+        }
+    }
+}
+
+/// The export mode describes how files will be exported.
+///
+/// This is a hint to the import trait method. For some implementations, this
+/// does not make any sense. E.g. an in memory implementation will always have
+/// to copy the file into memory. Also, a disk based implementation might choose
+/// to copy small files even if the mode is `Reference`.
+pub enum BlobExportMode {
+    /// This mode will copy the file to the target directory.
+    ///
+    /// This is the safe default because the file can not be accidentally modified
+    /// after it has been exported.
+    Copy,
+    /// This mode will try to move the file to the target directory and then reference it from
+    /// the database.
+    ///
+    /// This has a large performance and storage benefit, but it is less safe since
+    /// the file might be modified in the target directory after it has been exported.
+    ///
+    /// Stores are allowed to ignore this mode and always copy the file, e.g.
+    /// if the file is very small or if the store does not support referencing files.
+    TryReference,
+}
+
+impl From<BlobExportMode> for iroh::blobs::store::ExportMode {
+    fn from(value: BlobExportMode) -> Self {
+        match value {
+            BlobExportMode::Copy => iroh::blobs::store::ExportMode::Copy,
+            BlobExportMode::TryReference => iroh::blobs::store::ExportMode::TryReference,
+        }
+    }
+}
+
+/// The `progress` method will be called for each `DownloadProgress` event that is emitted during
+/// a `node.blobs_download`. Use the `DownloadProgress.type()` method to check the
+/// `DownloadProgressType` of the event.
+pub trait DownloadCallback: Send + Sync + 'static {
+    fn progress(&self, progress: Arc<DownloadProgress>) -> Result<(), CallbackError>;
+}
+
+impl DownloadCallback for ProgressSender<DownloadProgress> {
+    fn progress(&self, progress: Arc<DownloadProgress>) -> Result<(), CallbackError> {
+        self.send(progress)
+    }
+}
+
+/// The progress events of a download started with [`IrohNode::blobs_download_stream`].
+pub struct DownloadProgressStream(ProgressReceiver<DownloadProgress>);
+
+impl DownloadProgressStream {
+    /// Wait for the next event, `None` once the download is done.
+    ///
+    /// This is async, so waiting does not block the calling thread. Fails with the error of
+    /// the download if it failed.
+    pub async fn next(&self) -> Result<Option<Arc<DownloadProgress>>, IrohError> {
+        self.0.next().await
+    }
+}
+
+/// The different types of DownloadProgress events
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DownloadProgressType {
+    InitialState,
+    FoundLocal,
+    Connected,
+    Found,
+    FoundHashSeq,
+    Progress,
+    Done,
+    AllDone,
+    Abort,
+    BudgetExhausted,
+}
+
+/// A DownloadProgress event indicating an item was found with hash `hash`, that can be referred to by `id`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DownloadProgressFound {
+    /// A new unique id for this entry.
+    pub id: u64,
+    /// child offset
+    pub child: u64,
+    /// The hash of the entry.
+    pub hash: Arc<Hash>,
+    /// The size of the entry in bytes.
+    pub size: u64,
+}
+
+/// A DownloadProgress event indicating an entry was found locally
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DownloadProgressFoundLocal {
+    /// child offset
+    pub child: u64,
+    /// The hash of the entry.
+    pub hash: Arc<Hash>,
+    /// The size of the entry in bytes.
+    pub size: u64,
+    /// The ranges that are available locally.
+    pub valid_ranges: Arc<RangeSpec>,
+}
+
+/// A DownloadProgress event indicating an item was found with hash `hash`, that can be referred to by `id`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DownloadProgressFoundHashSeq {
+    /// Number of children in the collection, if known.
+    pub children: u64,
+    /// The hash of the entry.
+    pub hash: Arc<Hash>,
+}
+
+/// A DownloadProgress event indicating we got progress ingesting item `id`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DownloadProgressProgress {
+    /// The unique id of the entry.
+    pub id: u64,
+    /// The offset of the progress, in bytes.
+    pub offset: u64,
+}
+
+/// A DownloadProgress event indicated we are done with `id`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DownloadProgressDone {
+    /// The unique id of the entry.
+    pub id: u64,
+}
+
+/// A DownloadProgress event indicating we are done with the whole operation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DownloadProgressAllDone {
+    /// The number of bytes written
+    pub bytes_written: u64,
+    /// The number of bytes read
+    pub bytes_read: u64,
+    /// The time it took to transfer the data
+    pub elapsed: Duration,
+    /// The node the data was downloaded from, set for downloads through `blobs_download`.
+    pub served_by: Option<Arc<PublicKey>>,
+}
+
+/// A DownloadProgress event indicating the download stopped because its byte budget was reached
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DownloadProgressBudgetExhausted {
+    /// The bytes counted toward the budget when the download stopped.
+    pub bytes: u64,
+    /// The byte budget of the download.
+    pub budget: u64,
+}
+
+/// A DownloadProgress event indicating we got an error and need to abort
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DownloadProgressAbort {
+    pub error: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DownloadProgressInitialState {
+    // TODO(b5) - numerous fields missing
+    // /// The root blob of this transfer (may be a hash seq),
+    // pub root: BlobState,
+    /// Whether we are connected to a node
+    pub connected: bool,
+    // /// Children if the root blob is a hash seq, empty for raw blobs
+    // pub children: HashMap<NonZeroU64, BlobState>,
+    // /// Child being transferred at the moment.
+    // pub current: Option<BlobId>,
+    // /// Progress ids for individual blobs.
+    // pub progress_id_to_blob: HashMap<ProgressId, BlobId>,
+}
+
+/// Progress updates for the get operation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DownloadProgress {
+    /// Initial state if subscribing to a running or queued transfer.
+    InitialState(DownloadProgressInitialState),
+    /// A new connection was established.
+    Connected,
+    /// An item was found with hash `hash`, from now on referred to via `id`
+    Found(DownloadProgressFound),
+    /// Data was found locally
+    FoundLocal(DownloadProgressFoundLocal),
+    /// An item was found with hash `hash`, from now on referred to via `id`
+    FoundHashSeq(DownloadProgressFoundHashSeq),
+    /// We got progress ingesting item `id`.
+    Progress(DownloadProgressProgress),
+    /// We are done with `id`, and the hash is `hash`.
+    Done(DownloadProgressDone),
+    /// We are done with the whole operation.
+    AllDone(DownloadProgressAllDone),
+    /// We got an error and need to abort.
+    ///
+    /// This will be the last message in the stream.
+    Abort(DownloadProgressAbort),
+    /// The byte budget of the download was reached, the data received so far is kept.
+    ///
+    /// This will be the last message in the stream.
+    BudgetExhausted(DownloadProgressBudgetExhausted),
+}
+
+impl From<iroh::blobs::get::db::DownloadProgress> for DownloadProgress {
+    fn from(value: iroh::blobs::get::db::DownloadProgress) -> Self {
+        match value {
+            iroh::blobs::get::db::DownloadProgress::InitialState(transfer_state) => {
+                DownloadProgress::InitialState(DownloadProgressInitialState {
+                    connected: transfer_state.connected,
+                })
+            }
+            iroh::blobs::get::db::DownloadProgress::FoundLocal {
+                child,
+                hash,
+                size,
+                valid_ranges,
+            } => DownloadProgress::FoundLocal(DownloadProgressFoundLocal {
+                child: child.into(),
+                hash: Arc::new(hash.into()),
+                // TODO(b5) - this is ignoring verification information!
+                size: size.value(),
+                valid_ranges: Arc::new(valid_ranges.into()),
+            }),
+            iroh::blobs::get::db::DownloadProgress::Connected => DownloadProgress::Connected,
+            iroh::blobs::get::db::DownloadProgress::Found {
+                id,
+                hash,
+                child,
+                size,
+            } => DownloadProgress::Found(DownloadProgressFound {
+                id,
+                hash: Arc::new(hash.into()),
+                child: child.into(),
+                size,
+            }),
+            iroh::blobs::get::db::DownloadProgress::FoundHashSeq { hash, children } => {
+                DownloadProgress::FoundHashSeq(DownloadProgressFoundHashSeq {
+                    hash: Arc::new(hash.into()),
+                    children,
+                })
+            }
+            iroh::blobs::get::db::DownloadProgress::Progress { id, offset } => {
+                DownloadProgress::Progress(DownloadProgressProgress { id, offset })
+            }
+            iroh::blobs::get::db::DownloadProgress::Done { id } => {
+                DownloadProgress::Done(DownloadProgressDone { id })
+            }
+            iroh::blobs::get::db::DownloadProgress::AllDone(stats) => {
+                DownloadProgress::AllDone(DownloadProgressAllDone {
+                    bytes_written: stats.bytes_written,
+                    bytes_read: stats.bytes_read,
+                    elapsed: stats.elapsed,
+                    served_by: None,
+                })
+            }
+            iroh::blobs::get::db::DownloadProgress::Abort(err) => {
+                DownloadProgress::Abort(DownloadProgressAbort {
+                    error: err.to_string(),
+                })
+            }
+        }
+    }
+}
+
+impl DownloadProgress {
+    /// Get the type of event
+    /// note that there is no `as_connected` method, as the `Connected` event has no associated data
+    pub fn r#type(&self) -> DownloadProgressType {
+        match self {
+            DownloadProgress::InitialState(_) => DownloadProgressType::InitialState,
+            DownloadProgress::Connected => DownloadProgressType::Connected,
+            DownloadProgress::Found(_) => DownloadProgressType::Found,
+            DownloadProgress::FoundLocal(_) => DownloadProgressType::FoundLocal,
+            DownloadProgress::FoundHashSeq(_) => DownloadProgressType::FoundHashSeq,
+            DownloadProgress::Progress(_) => DownloadProgressType::Progress,
+            DownloadProgress::Done(_) => DownloadProgressType::Done,
+            DownloadProgress::AllDone(_) => DownloadProgressType::AllDone,
+            DownloadProgress::Abort(_) => DownloadProgressType::Abort,
+            DownloadProgress::BudgetExhausted(_) => DownloadProgressType::BudgetExhausted,
+        }
+    }
+
+    /// Return the `DownloadProgressFound` event
+    pub fn as_found(&self) -> DownloadProgressFound {
+        match self {
+            DownloadProgress::Found(f) => f.clone(),
+            _ => panic!("DownloadProgress type is not 'Found'"),
+        }
+    }
+
+    /// Return the `DownloadProgressFoundLocal` event
+    pub fn as_found_local(&self) -> DownloadProgressFoundLocal {
+        match self {
+            DownloadProgress::FoundLocal(f) => f.clone(),
+            _ => panic!("DownloadProgress type is not 'FoundLocal'"),
+        }
+    }
+
+    /// Return the `DownloadProgressFoundHashSeq` event
+    pub fn as_found_hash_seq(&self) -> DownloadProgressFoundHashSeq {
+        match self {
+            DownloadProgress::FoundHashSeq(f) => f.clone(),
+            _ => panic!("DownloadProgress type is not 'FoundHashSeq'"),
+        }
+    }
+
+    /// Return the `DownloadProgressProgress` event
+    pub fn as_progress(&self) -> DownloadProgressProgress {
+        match self {
+            DownloadProgress::Progress(p) => p.clone(),
+            _ => panic!("DownloadProgress type is not 'Progress'"),
+        }
+    }
+
+    /// Return the `DownloadProgressDone` event
+    pub fn as_done(&self) -> DownloadProgressDone {
+        match self {
+            DownloadProgress::Done(d) => d.clone(),
+            _ => panic!("DownloadProgress type is not 'Done'"),
+        }
+    }
+
+    /// Return the `DownloadProgressAllDone` event
+    pub fn as_all_done(&self) -> DownloadProgressAllDone {
+        match self {
+            DownloadProgress::AllDone(e) => e.clone(),
+            _ => panic!("DownloadProgress type is not 'AllDone'"),
+        }
+    }
+
+    /// Return the `DownloadProgressAbort` event
+    pub fn as_abort(&self) -> DownloadProgressAbort {
+        match self {
+            DownloadProgress::Abort(a) => a.clone(),
+            _ => panic!("DownloadProgress type is not 'Abort'"),
+        }
+    }
+
+    /// Return the `DownloadProgressBudgetExhausted` event
+    pub fn as_budget_exhausted(&self) -> DownloadProgressBudgetExhausted {
+        match self {
+            DownloadProgress::BudgetExhausted(b) => b.clone(),
+            _ => panic!("DownloadProgress type is not 'BudgetExhausted'"),
+        }
+    }
+}
+
+/// A chunk range specification as a sequence of chunk offsets
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RangeSpec(pub(crate) iroh::blobs::protocol::RangeSpec);
+
+impl RangeSpec {
+    /// Checks if this [`RangeSpec`] does not select any chunks in the blob
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Check if this [`RangeSpec`] selects all chunks in the blob
+    pub fn is_all(&self) -> bool {
+        self.0.is_all()
+    }
+}
+
+impl From<iroh::blobs::protocol::RangeSpec> for RangeSpec {
+    fn from(h: iroh::blobs::protocol::RangeSpec) -> Self {
+        RangeSpec(h)
+    }
+}
+
+/// A response to a list blobs request
+#[derive(Debug, Clone)]
+pub struct BlobInfo {
+    /// Location of the blob
+    pub path: String,
+    /// The hash of the blob
+    pub hash: Arc<Hash>,
+    /// The size of the blob
+    pub size: u64,
+}
+
+impl From<iroh::client::blobs::BlobInfo> for BlobInfo {
+    fn from(value: iroh::client::blobs::BlobInfo) -> Self {
+        BlobInfo {
+            path: value.path,
+            hash: Arc::new(value.hash.into()),
+            size: value.size,
+        }
+    }
+}
+
+/// A response to a list blobs request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IncompleteBlobInfo {
+    /// The size we got
+    pub size: u64,
+    /// The size we expect
+    pub expected_size: u64,
+    /// The hash of the blob
+    pub hash: Arc<Hash>,
+}
+
+impl From<iroh::client::blobs::IncompleteBlobInfo> for IncompleteBlobInfo {
+    fn from(value: iroh::client::blobs::IncompleteBlobInfo) -> Self {
+        IncompleteBlobInfo {
+            size: value.size,
+            expected_size: value.expected_size,
+            hash: Arc::new(value.hash.into()),
+        }
+    }
+}
+
+/// A response to a list collections request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollectionInfo {
+    /// Tag of the collection
+    pub tag: Vec<u8>,
+    /// Hash of the collection
+    pub hash: Arc<Hash>,
+    /// Number of children in the collection
+    ///
+    /// This is an optional field, because the data is not always available.
+    pub total_blobs_count: Option<u64>,
+    /// Total size of the raw data referred to by all links
+    ///
+    /// This is an optional field, because the data is not always available.
+    pub total_blobs_size: Option<u64>,
+}
+
+impl From<iroh::client::blobs::CollectionInfo> for CollectionInfo {
+    fn from(value: iroh::client::blobs::CollectionInfo) -> Self {
+        CollectionInfo {
+            tag: value.tag.0.to_vec(),
+            hash: Arc::new(value.hash.into()),
+            total_blobs_count: value.total_blobs_count,
+            total_blobs_size: value.total_blobs_size,
+        }
+    }
+}
+
+/// A collection of blobs
+#[derive(Debug)]
+pub struct Collection(pub(crate) RwLock<iroh::blobs::format::collection::Collection>);
+
+impl From<iroh::blobs::format::collection::Collection> for Collection {
+    fn from(value: iroh::blobs::format::collection::Collection) -> Self {
+        Collection(RwLock::new(value))
+    }
+}
+
+impl From<Collection> for iroh::blobs::format::collection::Collection {
+    fn from(value: Collection) -> Self {
+        let col = value.0.read().expect("Collection lock poisoned");
+        col.clone()
+    }
+}
+
+impl Collection {
+    /// Create a new empty collection
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Collection(RwLock::new(
+            iroh::blobs::format::collection::Collection::default(),
+        ))
+    }
+
+    /// Add the given blob to the collection
+    pub fn push(&self, name: String, hash: &Hash) -> Result<(), IrohError> {
+        self.0.write().unwrap().push(name, hash.0);
+        Ok(())
+    }
+
+    /// Check if the collection is empty
+    pub fn is_empty(&self) -> Result<bool, IrohError> {
+        Ok(self.0.read().unwrap().is_empty())
+    }
+
+    /// Get the names of the blobs in this collection
+    pub fn names(&self) -> Result<Vec<String>, IrohError> {
+        Ok(self
+            .0
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect())
+    }
+
+    /// Get the links to the blobs in this collection
+    pub fn links(&self) -> Result<Vec<Arc<Hash>>, IrohError> {
+        Ok(self
+            .0
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(_, hash)| Arc::new(Hash(*hash)))
+            .collect())
+    }
+
+    /// Get the blobs associated with this collection
+    pub fn blobs(&self) -> Result<Vec<LinkAndName>, IrohError> {
+        Ok(self
+            .0
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(name, hash)| LinkAndName {
+                name: name.clone(),
+                link: Arc::new(Hash(*hash)),
+            })
+            .collect())
+    }
+
+    /// Returns the number of blobs in this collection
+    pub fn len(&self) -> Result<u64, IrohError> {
+        Ok(self.0.read().unwrap().len() as _)
+    }
+
+    /// Get the blob at `index` in this collection, or `None` if `index` is out of bounds.
+    ///
+    /// Together with [`Self::len`] this allows paging through a large collection without
+    /// copying all of its links at once.
+    pub fn get(&self, index: u64) -> Result<Option<LinkAndName>, IrohError> {
+        let Ok(index) = usize::try_from(index) else {
+            return Ok(None);
+        };
+        Ok(self
+            .0
+            .read()
+            .unwrap()
+            .iter()
+            .nth(index)
+            .map(|(name, hash)| LinkAndName {
+                name: name.clone(),
+                link: Arc::new(Hash(*hash)),
+            }))
+    }
+}
+
+/// What an import would add to the store, see [`IrohNode::blobs_add_from_path_dry_run`].
+#[derive(Clone, Debug)]
+pub struct AddDryRunSummary {
+    /// The hash of each file that would be imported, named by its path relative to the
+    /// imported directory, or by its file name if a single file is imported
+    pub files: Vec<LinkAndName>,
+    /// The total size of all files, in bytes
+    pub size: u64,
+    /// The number of files that already exist as complete blobs on this node
+    pub existing_files: u64,
+    /// The total size of the files that already exist, in bytes
+    pub existing_size: u64,
+}
+
+/// What [`IrohNode::blobs_cleanup_orphans`] removed.
+#[derive(Clone, Debug)]
+pub struct OrphanCleanupSummary {
+    /// The number of blobs removed, complete and partial
+    pub removed_blobs: u64,
+    /// The total size of the removed blobs, in bytes
+    pub removed_bytes: u64,
+}
+
+/// The tags and documents referencing a blob, see [`IrohNode::blobs_references`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlobReferences {
+    /// The names of the tags referencing the blob
+    pub tags: Vec<Vec<u8>>,
+    /// The namespace ids of the documents referencing the blob
+    pub docs: Vec<String>,
+}
+
+/// Collect the paths of all files at `root`, recursing into directories.
+///
+/// Like an import, this follows `root` itself if it is a symlink, but skips symlinks below
+/// it, so a symlink loop cannot make the walk recurse forever.
+fn collect_files(root: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    let meta = std::fs::metadata(root)?;
+    if meta.is_dir() {
+        collect_dir_files(root, files)
+    } else {
+        if meta.is_file() {
+            files.push(root.to_path_buf());
+        }
+        Ok(())
+    }
+}
+
+fn collect_dir_files(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    let mut entries = std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort();
+    for entry in entries {
+        let file_type = std::fs::symlink_metadata(&entry)?.file_type();
+        if file_type.is_dir() {
+            collect_dir_files(&entry, files)?;
+        } else if file_type.is_file() {
+            files.push(entry);
+        }
+    }
+    Ok(())
+}
+
+/// The name of `file` in an import of `root`: its path relative to `root`, or its file name if
+/// `root` is the file itself.
+fn import_name(root: &Path, file: &Path) -> String {
+    let name = match file.strip_prefix(root) {
+        Ok(relative) if !relative.as_os_str().is_empty() => relative,
+        _ => file.file_name().map(Path::new).unwrap_or(file),
+    };
+    name.display().to_string()
+}
+
+/// `LinkAndName` includes a name and a hash for a blob in a collection
+#[derive(Clone, Debug)]
+pub struct LinkAndName {
+    /// The name associated with this [`Hash`]
+    pub name: String,
+    /// The [`Hash`] of the blob
+    pub link: Arc<Hash>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::node::IrohNode;
+    use crate::{CallbackError, NodeOptions};
+    use bytes::Bytes;
+    use rand::RngCore;
+    use tokio::io::AsyncWriteExt;
+    use tracing_subscriber::FmtSubscriber;
+
+    #[test]
+    fn test_hash() {
+        let hash_str = "6vp273v6cqbbq7xesa2xfrdt3oajykgeifprn3pj4p6y76654amq";
+        let hex_str = "f55fafeebe1402187ee4903572c473db809c28c4415f16ede9e3fd8ffbdde019";
+        let bytes = b"\xf5\x5f\xaf\xee\xbe\x14\x02\x18\x7e\xe4\x90\x35\x72\xc4\x73\xdb\x80\x9c\x28\xc4\x41\x5f\x16\xed\xe9\xe3\xfd\x8f\xfb\xdd\xe0\x19".to_vec();
+
+        // create hash from string
+        let hash = Hash::from_string(hash_str.into()).unwrap();
+
+        // test methods are as expected
+        assert_eq!(hash_str.to_string(), hash.to_string());
+        assert_eq!(bytes.to_vec(), hash.to_bytes());
+        assert_eq!(hex_str.to_string(), hash.to_hex());
+
+        // create hash from bytes
+        let hash_0 = Hash::from_bytes(bytes.clone()).unwrap();
+
+        // test methods are as expected
+        assert_eq!(hash_str.to_string(), hash_0.to_string());
+        assert_eq!(bytes, hash_0.to_bytes());
+        assert_eq!(hex_str.to_string(), hash_0.to_hex());
+
+        // test that the eq function works
+        assert!(hash.equal(&hash_0));
+        assert!(hash_0.equal(&hash));
+    }
+
+    #[test]
+    fn test_blobs_add_get_bytes() {
+        let dir = tempfile::tempdir().unwrap();
+        let node = IrohNode::new(dir.into_path().display().to_string()).unwrap();
+
+        let sizes = [1, 10, 100, 1000, 10000, 100000];
+        let mut hashes = Vec::new();
+        for size in sizes.iter() {
+            let hash = blobs_add_get_bytes_size(&node, *size);
+            hashes.push(hash)
+        }
+    }
+
+    fn blobs_add_get_bytes_size(node: &IrohNode, size: usize) -> Arc<Hash> {
+        // create bytes
+        let mut bytes = vec![0; size];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        // add blob
+        let add_outcome = node.blobs_add_bytes(bytes.to_vec()).unwrap();
+        // check outcome
+        assert_eq!(add_outcome.format, BlobFormat::Raw);
+        assert_eq!(add_outcome.size, size as u64);
+        // check size
+        let hash = add_outcome.hash;
+        let got_size = node.blobs_size(&hash).unwrap();
+        assert_eq!(got_size, size as u64);
+        //
+        // get blob
+        let got_bytes = node.blobs_read_to_bytes(hash.clone()).unwrap();
+        assert_eq!(got_bytes.len(), size);
+        assert_eq!(got_bytes, bytes);
+        hash
+    }
+
+    #[test]
+    fn test_blob_read_write_path() {
+        let iroh_dir = tempfile::tempdir().unwrap();
+        let node = IrohNode::new(iroh_dir.into_path().display().to_string()).unwrap();
+
+        // create bytes
+        let blob_size = 100;
+        let mut bytes = vec![0; blob_size];
+        rand::thread_rng().fill_bytes(&mut bytes);
+
+        // write to file
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("in");
+        let mut file = std::fs::File::create(path.clone()).unwrap();
+        file.write_all(&bytes).unwrap();
+
+        // add blob
+        let tag = SetTagOption::auto();
+        let wrap = WrapOption::no_wrap();
+
+        struct Output {
+            hash: Option<Arc<Hash>>,
+            format: Option<BlobFormat>,
+        }
+        let output = Arc::new(Mutex::new(Output {
+            hash: None,
+            format: None,
+        }));
+        struct Callback {
+            output: Arc<Mutex<Output>>,
+        }
+
+        impl AddCallback for Callback {
+            fn progress(&self, progress: Arc<AddProgress>) -> Result<(), CallbackError> {
+                match *progress {
+                    AddProgress::AllDone(ref d) => {
+                        let mut output = self.output.lock().unwrap();
+                        output.hash = Some(d.hash.clone());
+                        output.format = Some(d.format.clone());
+                    }
+                    AddProgress::Abort(ref _a) => {
+                        // anyhow::anyhow!("{}", a.error).into());
+                        return Err(CallbackError::Error);
+                    }
+                    _ => {}
+                }
+                Ok(())
+            }
+        }
+        let cb = Callback {
+            output: output.clone(),
+        };
+
+        node.blobs_add_from_path(
+            path.display().to_string(),
+            false,
+            Arc::new(tag),
+            Arc::new(wrap),
+            Arc::new(cb),
+            None,
+        )
+        .unwrap();
+
+        let (hash, format) = {
+            let output = output.lock().unwrap();
+            let hash = output.hash.as_ref().cloned().unwrap();
+            let format = output.format.as_ref().cloned().unwrap();
+            (hash, format)
+        };
+
+        // check outcome info is as expected
+        assert_eq!(BlobFormat::Raw, format);
+
+        // check we get the expected size from the hash
+        let got_size = node.blobs_size(&hash).unwrap();
+        assert_eq!(blob_size as u64, got_size);
+
+        // get bytes
+        let got_bytes = node.blobs_read_to_bytes(hash.clone()).unwrap();
+        assert_eq!(blob_size, got_bytes.len());
+        assert_eq!(bytes, got_bytes);
+
+        // write to file
+        let out_path = dir.path().join("out");
+        node.blobs_write_to_path(hash, out_path.display().to_string())
+            .unwrap();
+
+        // open file
+        let got_bytes = std::fs::read(&out_path).unwrap();
+        assert_eq!(blob_size, got_bytes.len());
+        assert_eq!(bytes, got_bytes);
+
+        // exporting over an existing file replaces it and leaves no temporary files behind
+        let other = node.blobs_add_bytes(b"other".to_vec()).unwrap();
+        node.blobs_export(
+            other.hash,
+            out_path.display().to_string(),
+            BlobExportFormat::Blob,
+            BlobExportMode::Copy,
+        )
+        .unwrap();
+        assert_eq!(b"other".to_vec(), std::fs::read(&out_path).unwrap());
+        let leftovers: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .filter(|name| name.to_string_lossy().starts_with(".iroh-export-"))
+            .collect();
+        assert!(leftovers.is_empty());
+    }
+
+    #[test]
+    fn test_blobs_list_collections() {
+        let dir = tempfile::tempdir().unwrap();
+        let num_blobs = 3;
+        let blob_size = 100;
+        for i in 0..num_blobs {
+            let path = dir.path().join(i.to_string());
+            let mut file = std::fs::File::create(path).unwrap();
+            let mut bytes = vec![0; blob_size];
+            rand::thread_rng().fill_bytes(&mut bytes);
+            file.write_all(&bytes).unwrap()
+        }
+
+        let iroh_dir = tempfile::tempdir().unwrap();
+        let node = IrohNode::new(iroh_dir.into_path().display().to_string()).unwrap();
+
+        // ensure there are no blobs to start
+        let blobs = node.blobs_list().unwrap();
+        assert!(blobs.is_empty());
+
+        struct Output {
+            collection_hash: Option<Arc<Hash>>,
+            format: Option<BlobFormat>,
+            blob_hashes: Vec<Arc<Hash>>,
+        }
+        let output = Arc::new(Mutex::new(Output {
+            collection_hash: None,
+            format: None,
+            blob_hashes: Vec::new(),
+        }));
+        struct Callback {
+            output: Arc<Mutex<Output>>,
+        }
+
+        impl AddCallback for Callback {
+            fn progress(&self, progress: Arc<AddProgress>) -> Result<(), CallbackError> {
+                match *progress {
+                    AddProgress::AllDone(ref d) => {
+                        let mut output = self.output.lock().unwrap();
+                        output.collection_hash = Some(d.hash.clone());
+                        output.format = Some(d.format.clone());
+                    }
+                    AddProgress::Abort(ref _a) => {
+                        return Err(CallbackError::Error);
+                        // return Err(anyhow::anyhow!("{}", a.error).into());
+                    }
+                    AddProgress::Done(ref d) => {
+                        let mut output = self.output.lock().unwrap();
+                        output.blob_hashes.push(d.hash.clone())
+                    }
+                    _ => {}
+                }
+                Ok(())
+            }
+        }
+
+        let cb = Callback {
+            output: output.clone(),
+        };
+
+        let files = node
+            .blobs_add_from_path(
+                dir.into_path().display().to_string(),
+                false,
+                Arc::new(SetTagOption::Auto),
+                Arc::new(WrapOption::NoWrap),
+                Arc::new(cb),
+                None,
+            )
+            .unwrap();
+        assert_eq!(num_blobs, files.len());
+        let mut names: Vec<_> = files
+            .iter()
+            .map(|f| {
+                let path = PathBuf::from(&f.name);
+                path.file_name().unwrap().to_string_lossy().into_owned()
+            })
+            .collect();
+        names.sort();
+        assert_eq!(vec!["0", "1", "2"], names);
+
+        let collections = node.blobs_list_collections().unwrap();
+        assert!(collections.len() == 1);
+        let (collection_hash, blob_hashes) = {
+            let output = output.lock().unwrap();
+            let collection_hash = output.collection_hash.as_ref().cloned().unwrap();
+            let mut blob_hashes = output.blob_hashes.clone();
+            blob_hashes.push(collection_hash.clone());
+            (collection_hash, blob_hashes)
+        };
+        assert_eq!(*(collections[0].hash), *collection_hash);
+        assert_eq!(
+            collections[0].total_blobs_count.unwrap(),
+            blob_hashes.len() as u64
+        );
+
+        let blobs = node.blobs_list().unwrap();
+        hashes_exist(&blob_hashes, &blobs);
+        println!("finished");
+    }
+
+    fn hashes_exist(expect: &Vec<Arc<Hash>>, got: &[Arc<Hash>]) {
+        for hash in expect {
+            if !got.contains(hash) {
+                panic!("expected to find hash {} in the list of hashes", hash);
+            }
+        }
+    }
+
+    #[test]
+    fn test_list_and_delete() {
+        setup_logging();
+
+        let iroh_dir = tempfile::tempdir().unwrap();
+        // we're going to use a very fast GC interval to get this test to delete stuff aggressively
+        let opts = NodeOptions {
+            gc_interval_millis: Some(100),
+            ..Default::default()
+        };
+        let node =
+            IrohNode::with_options(iroh_dir.into_path().display().to_string(), opts).unwrap();
+
+        // create bytes
+        let blob_size = 100;
+        let mut blobs = vec![];
+        let num_blobs = 3;
+
+        for _i in 0..num_blobs {
+            let mut bytes = vec![0; blob_size];
+            rand::thread_rng().fill_bytes(&mut bytes);
+            blobs.push(bytes);
+        }
+
+        let mut hashes = vec![];
+        let mut tags = vec![];
+        for blob in blobs {
+            let output = node.blobs_add_bytes(blob).unwrap();
+            hashes.push(output.hash);
+            tags.push(output.tag);
+        }
+
+        let got_hashes = node.blobs_list().unwrap();
+        assert_eq!(num_blobs, got_hashes.len());
+        hashes_exist(&hashes, &got_hashes);
+
+        let remove_hash = hashes.pop().unwrap();
+        let remove_tag = tags.pop().unwrap();
+        // delete the tag for the first blob
+        node.tags_delete(remove_tag).unwrap();
+        // wait for GC to clear the blob. windows test runner is slow & needs like 500ms
+        std::thread::sleep(Duration::from_millis(500));
+
+        let got_hashes = node.blobs_list().unwrap();
+        assert_eq!(num_blobs - 1, got_hashes.len());
+        hashes_exist(&hashes, &got_hashes);
+
+        for hash in got_hashes {
+            if remove_hash.equal(&hash) {
+                panic!("blob {} should have been removed", remove_hash);
+            }
+        }
+    }
+
+    async fn build_iroh_core(
+        path: &std::path::Path,
+    ) -> iroh::node::Node<iroh::blobs::store::fs::Store> {
+        iroh::node::Node::persistent(path)
+            .await
+            .unwrap()
+            .spawn()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn iroh_core_blobs_add_get_bytes() {
+        let dir = tempfile::tempdir().unwrap();
+        let node = build_iroh_core(dir.path()).await;
+        let sizes = [1, 10, 100, 1000, 10000, 100000];
+        let mut hashes = Vec::new();
+        for size in sizes.iter() {
+            let hash = iroh_core_blobs_add_get_bytes_size(&node, *size);
+            hashes.push(hash)
+        }
+    }
+
+    async fn iroh_core_blobs_add_get_bytes_size(
+        node: &iroh::node::Node<iroh::blobs::store::fs::Store>,
+        size: usize,
+    ) -> iroh::blobs::Hash {
+        let client = node.client();
+        // create bytes
+        let mut bytes = vec![0; size];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        let bytes: Bytes = bytes.into();
+        // add blob
+        let add_outcome = client.blobs().add_bytes(bytes.clone()).await.unwrap();
+        // check outcome
+        assert_eq!(add_outcome.format, iroh::blobs::BlobFormat::Raw);
+        assert_eq!(add_outcome.size, size as u64);
+        // check size
+        let hash = add_outcome.hash;
+        let reader = client.blobs().read(hash).await.unwrap();
+        let got_size = reader.size();
+        assert_eq!(got_size, size as u64);
+        //
+        // get blob
+        let got_bytes = client.blobs().read_to_bytes(hash).await.unwrap();
+        assert_eq!(got_bytes.len(), size);
+        assert_eq!(got_bytes, bytes);
+        hash
+    }
+
+    #[tokio::test]
+    async fn iroh_core_blobs_list_collections() {
+        let iroh_dir = tempfile::tempdir().unwrap();
+        let node = build_iroh_core(iroh_dir.path()).await;
+
+        // collection dir
+        let dir = tempfile::tempdir().unwrap();
+        let num_blobs = 3;
+        let blob_size = 100;
+        for i in 0..num_blobs {
+            let path = dir.path().join(i.to_string());
+            let mut file = tokio::fs::File::create(path).await.unwrap();
+            let mut bytes = vec![0; blob_size];
+            rand::thread_rng().fill_bytes(&mut bytes);
+            file.write_all(&bytes).await.unwrap()
+        }
+
+        let client = node.client();
+        // ensure there are no blobs to start
+        let blobs = client
+            .blobs()
+            .list()
+            .await
+            .unwrap()
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(0, blobs.len());
+
+        let mut stream = client
+            .blobs()
+            .add_from_path(
+                dir.into_path(),
+                false,
+                iroh::blobs::util::SetTagOption::Auto,
+                iroh::client::blobs::WrapOption::NoWrap,
+            )
+            .await
+            .unwrap();
+
+        let mut collection_hash = None;
+        let mut collection_format = None;
+        let mut hashes = Vec::new();
+
+        while let Some(progress) = stream.next().await {
+            let progress = progress.unwrap();
+            match progress {
+                iroh::blobs::provider::AddProgress::AllDone { hash, format, .. } => {
+                    collection_hash = Some(hash);
+                    collection_format = Some(format);
+                }
+                iroh::blobs::provider::AddProgress::Abort(err) => {
+                    panic!("{}", err);
+                }
+                iroh::blobs::provider::AddProgress::Done { hash, .. } => hashes.push(hash),
+                _ => {}
+            }
+        }
+
+        let collection_hash = collection_hash.unwrap();
+        let collection_format = collection_format.unwrap();
+
+        assert_eq!(iroh::blobs::BlobFormat::HashSeq, collection_format);
+
+        let collections = client
+            .blobs()
+            .list_collections()
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(1, collections.len());
+        assert_eq!(collections[0].hash, collection_hash);
+        // add length for the metadata blob
+        let total_blobs_count = hashes.len() + 1;
+        assert_eq!(
+            collections[0].total_blobs_count.unwrap(),
+            total_blobs_count as u64
+        );
+        // this is always `None`
+        // assert_eq!(collections[0].total_blobs_size.unwrap(), 300 as u64);
+    }
+
+    #[test]
+    fn test_blobs_list_stream() {
+        let dir = tempfile::tempdir().unwrap();
+        let node = IrohNode::new(dir.into_path().display().to_string()).unwrap();
+
+        let mut hashes = vec![];
+        for i in 0..5u8 {
+            hashes.push(node.blobs_add_bytes(vec![i; 10]).unwrap().hash);
+        }
+
+        struct Callback {
+            hashes: Mutex<Vec<Arc<Hash>>>,
+            limit: usize,
+            cancel: bool,
+        }
+
+        impl HashCallback for Callback {
+            fn on_hash(&self, hash: Arc<Hash>) -> Result<(), CallbackError> {
+                let mut hashes = self.hashes.lock().unwrap();
+                if hashes.len() == self.limit {
+                    if self.cancel {
+                        return Err(CallbackError::Cancelled);
+                    }
+                    return Err(CallbackError::Error);
+                }
+                hashes.push(hash);
+                Ok(())
+            }
+        }
 
-        // test that the eq function works
-        assert!(hash.equal(&hash_0));
-        assert!(hash_0.equal(&hash));
+        // all hashes are listed
+        let cb = Arc::new(Callback {
+            hashes: Mutex::new(vec![]),
+            limit: usize::MAX,
+            cancel: false,
+        });
+        node.blobs_list_stream(cb.clone()).unwrap();
+        let got_hashes = cb.hashes.lock().unwrap().clone();
+        assert_eq!(hashes.len(), got_hashes.len());
+        hashes_exist(&hashes, &got_hashes);
+
+        // a callback error stops the enumeration
+        let cb = Arc::new(Callback {
+            hashes: Mutex::new(vec![]),
+            limit: 2,
+            cancel: false,
+        });
+        assert!(node.blobs_list_stream(cb.clone()).is_err());
+        assert_eq!(2, cb.hashes.lock().unwrap().len());
+
+        // cancelling stops the enumeration without an error
+        let cb = Arc::new(Callback {
+            hashes: Mutex::new(vec![]),
+            limit: 3,
+            cancel: true,
+        });
+        node.blobs_list_stream(cb.clone()).unwrap();
+        assert_eq!(3, cb.hashes.lock().unwrap().len());
     }
 
     #[test]
-    fn test_blobs_add_get_bytes() {
+    fn test_blobs_subscribe_download_not_running() {
         let dir = tempfile::tempdir().unwrap();
         let node = IrohNode::new(dir.into_path().display().to_string()).unwrap();
 
-        let sizes = [1, 10, 100, 1000, 10000, 100000];
-        let mut hashes = Vec::new();
-        for size in sizes.iter() {
-            let hash = blobs_add_get_bytes_size(&node, *size);
-            hashes.push(hash)
+        struct Callback;
+        impl DownloadCallback for Callback {
+            fn progress(&self, _progress: Arc<DownloadProgress>) -> Result<(), CallbackError> {
+                Ok(())
+            }
         }
-    }
 
-    fn blobs_add_get_bytes_size(node: &IrohNode, size: usize) -> Arc<Hash> {
-        // create bytes
-        let mut bytes = vec![0; size];
-        rand::thread_rng().fill_bytes(&mut bytes);
-        // add blob
-        let add_outcome = node.blobs_add_bytes(bytes.to_vec()).unwrap();
-        // check outcome
-        assert_eq!(add_outcome.format, BlobFormat::Raw);
-        assert_eq!(add_outcome.size, size as u64);
-        // check size
-        let hash = add_outcome.hash;
-        let got_size = node.blobs_size(&hash).unwrap();
-        assert_eq!(got_size, size as u64);
-        //
-        // get blob
-        let got_bytes = node.blobs_read_to_bytes(hash.clone()).unwrap();
-        assert_eq!(got_bytes.len(), size);
-        assert_eq!(got_bytes, bytes);
-        hash
+        let hash = node.blobs_add_bytes(b"hello".to_vec()).unwrap().hash;
+        assert!(node
+            .blobs_subscribe_download(hash, Arc::new(Callback))
+            .is_err());
     }
 
     #[test]
-    fn test_blob_read_write_path() {
-        let iroh_dir = tempfile::tempdir().unwrap();
-        let node = IrohNode::new(iroh_dir.into_path().display().to_string()).unwrap();
+    fn test_blobs_download_max_download_blob_size() {
+        let dir_0 = tempfile::tempdir().unwrap();
+        let node_0 = IrohNode::new(dir_0.path().display().to_string()).unwrap();
+        let dir_1 = tempfile::tempdir().unwrap();
+        let opts = NodeOptions {
+            max_download_blob_size: Some(10),
+            ..Default::default()
+        };
+        let node_1 = IrohNode::with_options(dir_1.path().display().to_string(), opts).unwrap();
 
-        // create bytes
-        let blob_size = 100;
-        let mut bytes = vec![0; blob_size];
-        rand::thread_rng().fill_bytes(&mut bytes);
+        struct Callback {
+            aborts: Mutex<Vec<String>>,
+        }
+        impl DownloadCallback for Callback {
+            fn progress(&self, progress: Arc<DownloadProgress>) -> Result<(), CallbackError> {
+                if let DownloadProgress::Abort(ref abort) = *progress {
+                    self.aborts.lock().unwrap().push(abort.error.clone());
+                }
+                Ok(())
+            }
+        }
 
-        // write to file
-        let dir = tempfile::tempdir().unwrap();
-        let path = dir.path().join("in");
-        let mut file = std::fs::File::create(path.clone()).unwrap();
-        file.write_all(&bytes).unwrap();
+        let hash = node_0.blobs_add_bytes(vec![1u8; 100]).unwrap().hash;
+        let opts = BlobDownloadOptions::new(
+            BlobFormat::Raw,
+            node_0.status().unwrap().node_addr(),
+            Arc::new(SetTagOption::Auto),
+            None,
+        )
+        .unwrap();
+        let cb = Arc::new(Callback {
+            aborts: Mutex::new(vec![]),
+        });
+        assert!(node_1
+            .blobs_download(hash.clone(), Arc::new(opts), cb.clone())
+            .is_err());
+        assert_eq!(1, cb.aborts.lock().unwrap().len());
+        assert!(node_1.blobs_list().unwrap().iter().all(|h| !h.equal(&hash)));
+    }
 
-        // add blob
-        let tag = SetTagOption::auto();
-        let wrap = WrapOption::no_wrap();
+    #[test]
+    fn test_blobs_resumable_downloads() {
+        let dir_0 = tempfile::tempdir().unwrap();
+        let node_0 = IrohNode::new(dir_0.path().display().to_string()).unwrap();
+        let dir_1 = tempfile::tempdir().unwrap();
+        let node_1 = Arc::new(IrohNode::new(dir_1.path().display().to_string()).unwrap());
 
-        struct Output {
-            hash: Option<Arc<Hash>>,
-            format: Option<BlobFormat>,
-        }
-        let output = Arc::new(Mutex::new(Output {
-            hash: None,
-            format: None,
-        }));
+        /// Cancels the download at the first progress event, if `cancel` is set.
         struct Callback {
-            output: Arc<Mutex<Output>>,
+            cancel: bool,
         }
-
-        impl AddCallback for Callback {
-            fn progress(&self, progress: Arc<AddProgress>) -> Result<(), CallbackError> {
+        impl DownloadCallback for Callback {
+            fn progress(&self, progress: Arc<DownloadProgress>) -> Result<(), CallbackError> {
                 match *progress {
-                    AddProgress::AllDone(ref d) => {
-                        let mut output = self.output.lock().unwrap();
-                        output.hash = Some(d.hash.clone());
-                        output.format = Some(d.format.clone());
-                    }
-                    AddProgress::Abort(ref _a) => {
-                        // anyhow::anyhow!("{}", a.error).into());
-                        return Err(CallbackError::Error);
-                    }
-                    _ => {}
+                    DownloadProgress::Progress(_) if self.cancel => Err(CallbackError::Cancelled),
+                    _ => Ok(()),
                 }
-                Ok(())
             }
         }
-        let cb = Callback {
-            output: output.clone(),
-        };
 
-        node.blobs_add_from_path(
-            path.display().to_string(),
-            false,
-            Arc::new(tag),
-            Arc::new(wrap),
-            Arc::new(cb),
+        let mut data = vec![0u8; 8 * 1024 * 1024];
+        rand::thread_rng().fill_bytes(&mut data);
+        let hash = node_0.blobs_add_bytes(data.clone()).unwrap().hash;
+        let addr = node_0.status().unwrap().node_addr();
+        let opts = BlobDownloadOptions::new(
+            BlobFormat::Raw,
+            addr.clone(),
+            Arc::new(SetTagOption::Auto),
+            None,
         )
         .unwrap();
+        node_1
+            .blobs_download(
+                hash.clone(),
+                Arc::new(opts),
+                Arc::new(Callback { cancel: true }),
+            )
+            .unwrap();
 
-        let (hash, format) = {
-            let output = output.lock().unwrap();
-            let hash = output.hash.as_ref().cloned().unwrap();
-            let format = output.format.as_ref().cloned().unwrap();
-            (hash, format)
-        };
-
-        // check outcome info is as expected
-        assert_eq!(BlobFormat::Raw, format);
+        // the download survives a restart of the node
+        node_1.shutdown().unwrap();
+        drop(node_1);
+        let node_1 = Arc::new(IrohNode::new(dir_1.path().display().to_string()).unwrap());
+        let downloads = node_1.clone().blobs_resumable_downloads().unwrap();
+        assert_eq!(1, downloads.len());
+        let download = &downloads[0];
+        assert_eq!(hash, download.hash());
+        assert!(download.size() < download.expected_size());
+        assert_eq!(data.len() as u64, download.expected_size());
+        let nodes = download.nodes();
+        assert_eq!(1, nodes.len());
+        assert!(nodes[0].equal(&addr));
+
+        download
+            .resume(Arc::new(Callback { cancel: false }))
+            .unwrap();
+        assert_eq!(data, node_1.blobs_read_to_bytes(hash).unwrap());
+        assert!(node_1
+            .clone()
+            .blobs_resumable_downloads()
+            .unwrap()
+            .is_empty());
+    }
 
-        // check we get the expected size from the hash
-        let got_size = node.blobs_size(&hash).unwrap();
-        assert_eq!(blob_size as u64, got_size);
+    #[test]
+    fn test_blobs_download_abort_keeps_preexisting_data() {
+        let dir_0 = tempfile::tempdir().unwrap();
+        let node_0 = IrohNode::new(dir_0.path().display().to_string()).unwrap();
+        let dir_1 = tempfile::tempdir().unwrap();
+        let node_1 = IrohNode::new(dir_1.path().display().to_string()).unwrap();
+
+        /// Cancels the download at the first progress event.
+        struct Cancel;
+        impl DownloadCallback for Cancel {
+            fn progress(&self, progress: Arc<DownloadProgress>) -> Result<(), CallbackError> {
+                match *progress {
+                    DownloadProgress::Progress(_) => Err(CallbackError::Cancelled),
+                    _ => Ok(()),
+                }
+            }
+        }
+        let download = |hash: &Arc<Hash>, keep_partial| {
+            let opts = BlobDownloadOptions::new(
+                BlobFormat::Raw,
+                node_0.status().unwrap().node_addr(),
+                Arc::new(SetTagOption::Auto),
+                Some(BlobDownloadConfig {
+                    keep_partial_on_abort: Some(keep_partial),
+                    ..Default::default()
+                }),
+            )
+            .unwrap();
+            node_1
+                .blobs_download(hash.clone(), Arc::new(opts), Arc::new(Cancel))
+                .unwrap();
+            node_1.blobs_status_many(vec![hash.clone()]).unwrap()[0].clone()
+        };
+        let add = || {
+            let mut data = vec![0u8; 8 * 1024 * 1024];
+            rand::thread_rng().fill_bytes(&mut data);
+            node_0.blobs_add_bytes(data).unwrap().hash
+        };
 
-        // get bytes
-        let got_bytes = node.blobs_read_to_bytes(hash.clone()).unwrap();
-        assert_eq!(blob_size, got_bytes.len());
-        assert_eq!(bytes, got_bytes);
+        // data created by the aborted download is removed
+        let hash = add();
+        assert_eq!(BlobStatus::NotFound, download(&hash, false));
 
-        // write to file
-        let out_path = dir.path().join("out");
-        node.blobs_write_to_path(hash, out_path.display().to_string())
-            .unwrap();
+        // data of an earlier interrupted download is kept
+        let hash = add();
+        assert!(matches!(download(&hash, true), BlobStatus::Partial { .. }));
+        assert!(matches!(download(&hash, false), BlobStatus::Partial { .. }));
+    }
 
-        // open file
-        let got_bytes = std::fs::read(out_path).unwrap();
-        assert_eq!(blob_size, got_bytes.len());
-        assert_eq!(bytes, got_bytes);
+    #[test]
+    fn test_blobs_await_provider() {
+        let dir_0 = tempfile::tempdir().unwrap();
+        let node_0 = IrohNode::new(dir_0.path().display().to_string()).unwrap();
+        let dir_1 = tempfile::tempdir().unwrap();
+        let node_1 = IrohNode::new(dir_1.path().display().to_string()).unwrap();
+
+        let hash = node_0.blobs_add_bytes(b"provided".to_vec()).unwrap().hash;
+        let addr = node_0.status().unwrap().node_addr();
+
+        // no known node has the content
+        let err = node_1.blobs_await_provider(hash.clone(), 500).unwrap_err();
+        assert!(err.to_string().contains("no provider"));
+
+        // once node_0 is known, it is found as the provider
+        node_1.add_node_addr(addr.clone()).unwrap();
+        let provider = node_1.blobs_await_provider(hash, 10_000).unwrap();
+        let provider: iroh::net::endpoint::NodeAddr = (*provider).clone().try_into().unwrap();
+        assert_eq!(node_0.node_id(), provider.node_id.to_string());
+
+        // node_0 does not provide content it does not have
+        let missing = node_1.blobs_add_bytes(b"missing".to_vec()).unwrap().hash;
+        assert!(node_1.blobs_await_provider(missing, 1_000).is_err());
     }
 
     #[test]
-    fn test_blobs_list_collections() {
-        let dir = tempfile::tempdir().unwrap();
-        let num_blobs = 3;
-        let blob_size = 100;
-        for i in 0..num_blobs {
-            let path = dir.path().join(i.to_string());
-            let mut file = std::fs::File::create(path).unwrap();
-            let mut bytes = vec![0; blob_size];
-            rand::thread_rng().fill_bytes(&mut bytes);
-            file.write_all(&bytes).unwrap()
+    fn test_blobs_download_byte_budget() {
+        let dir_0 = tempfile::tempdir().unwrap();
+        let node_0 = IrohNode::new(dir_0.path().display().to_string()).unwrap();
+        let dir_1 = tempfile::tempdir().unwrap();
+        let node_1 = IrohNode::new(dir_1.path().display().to_string()).unwrap();
+
+        struct Callback {
+            last: Mutex<Option<Arc<DownloadProgress>>>,
+        }
+        impl DownloadCallback for Callback {
+            fn progress(&self, progress: Arc<DownloadProgress>) -> Result<(), CallbackError> {
+                *self.last.lock().unwrap() = Some(progress);
+                Ok(())
+            }
         }
 
-        let iroh_dir = tempfile::tempdir().unwrap();
-        let node = IrohNode::new(iroh_dir.into_path().display().to_string()).unwrap();
+        let mut data = vec![0u8; 4 * 1024 * 1024];
+        rand::thread_rng().fill_bytes(&mut data);
+        let hash = node_0.blobs_add_bytes(data).unwrap().hash;
+        let budget = 1024 * 1024;
+        // the partial data is kept even though it would be removed on an abort
+        let opts = BlobDownloadOptions::new(
+            BlobFormat::Raw,
+            node_0.status().unwrap().node_addr(),
+            Arc::new(SetTagOption::Auto),
+            Some(BlobDownloadConfig {
+                keep_partial_on_abort: Some(false),
+                byte_budget: Some(budget),
+                ..Default::default()
+            }),
+        )
+        .unwrap();
+        let cb = Arc::new(Callback {
+            last: Mutex::new(None),
+        });
+        node_1
+            .blobs_download(hash.clone(), Arc::new(opts), cb.clone())
+            .unwrap();
 
-        // ensure there are no blobs to start
-        let blobs = node.blobs_list().unwrap();
-        assert!(blobs.is_empty());
+        let last = cb.last.lock().unwrap().clone().unwrap();
+        assert_eq!(DownloadProgressType::BudgetExhausted, last.r#type());
+        let exhausted = last.as_budget_exhausted();
+        assert_eq!(budget, exhausted.budget);
+        assert!(exhausted.bytes >= budget);
+
+        assert!(!node_1.blobs_list().unwrap().contains(&hash));
+        let incomplete = node_1.blobs_list_incomplete().unwrap();
+        assert!(incomplete.iter().any(|blob| blob.hash == hash));
+    }
+
+    #[test]
+    fn test_blobs_download_with_nodes() {
+        let dir_0 = tempfile::tempdir().unwrap();
+        let node_0 = IrohNode::new(dir_0.path().display().to_string()).unwrap();
+        let dir_1 = tempfile::tempdir().unwrap();
+        let node_1 = IrohNode::new(dir_1.path().display().to_string()).unwrap();
 
-        struct Output {
-            collection_hash: Option<Arc<Hash>>,
-            format: Option<BlobFormat>,
-            blob_hashes: Vec<Arc<Hash>>,
-        }
-        let output = Arc::new(Mutex::new(Output {
-            collection_hash: None,
-            format: None,
-            blob_hashes: Vec::new(),
-        }));
         struct Callback {
-            output: Arc<Mutex<Output>>,
+            served_by: Mutex<Option<Arc<PublicKey>>>,
         }
-
-        impl AddCallback for Callback {
-            fn progress(&self, progress: Arc<AddProgress>) -> Result<(), CallbackError> {
-                match *progress {
-                    AddProgress::AllDone(ref d) => {
-                        let mut output = self.output.lock().unwrap();
-                        output.collection_hash = Some(d.hash.clone());
-                        output.format = Some(d.format.clone());
-                    }
-                    AddProgress::Abort(ref _a) => {
-                        return Err(CallbackError::Error);
-                        // return Err(anyhow::anyhow!("{}", a.error).into());
-                    }
-                    AddProgress::Done(ref d) => {
-                        let mut output = self.output.lock().unwrap();
-                        output.blob_hashes.push(d.hash.clone())
-                    }
-                    _ => {}
+        impl DownloadCallback for Callback {
+            fn progress(&self, progress: Arc<DownloadProgress>) -> Result<(), CallbackError> {
+                if let DownloadProgress::AllDone(ref done) = *progress {
+                    *self.served_by.lock().unwrap() = done.served_by.clone();
                 }
                 Ok(())
             }
         }
 
-        let cb = Callback {
-            output: output.clone(),
-        };
+        let addr = node_0.status().unwrap().node_addr();
+        let tag = Arc::new(SetTagOption::Auto);
+        assert!(
+            BlobDownloadOptions::with_nodes(BlobFormat::Raw, vec![], tag.clone(), None).is_err()
+        );
+        assert!(BlobDownloadOptions::with_nodes(
+            BlobFormat::Raw,
+            vec![addr.clone()],
+            tag.clone(),
+            Some(BlobDownloadConfig {
+                max_attempts_per_node: Some(0),
+                ..Default::default()
+            })
+        )
+        .is_err());
+
+        // a node that is gone, tried first, so the download has to fail over to node_0
+        let dir_2 = tempfile::tempdir().unwrap();
+        let node_2 = IrohNode::new(dir_2.path().display().to_string()).unwrap();
+        let dead = node_2.status().unwrap().node_addr();
+        node_2.shutdown().unwrap();
+
+        let hash = node_0.blobs_add_bytes(b"failover".to_vec()).unwrap().hash;
+        let opts = BlobDownloadOptions::with_nodes(
+            BlobFormat::Raw,
+            vec![dead, addr.clone()],
+            tag,
+            Some(BlobDownloadConfig {
+                max_attempts_per_node: Some(2),
+                node_order: Some(NodeOrder::LowestLatencyFirst),
+                ..Default::default()
+            }),
+        )
+        .unwrap();
+        let cb = Arc::new(Callback {
+            served_by: Mutex::new(None),
+        });
+        node_1
+            .blobs_download(hash.clone(), Arc::new(opts), cb.clone())
+            .unwrap();
+        let served_by = cb.served_by.lock().unwrap().clone().unwrap();
+        assert_eq!(node_0.node_id(), served_by.to_string());
+        assert!(node_1.blobs_list().unwrap().contains(&hash));
+    }
 
-        node.blobs_add_from_path(
-            dir.into_path().display().to_string(),
-            false,
-            Arc::new(SetTagOption::Auto),
-            Arc::new(WrapOption::NoWrap),
-            Arc::new(cb),
+    #[test]
+    fn test_blobs_add_bytes_named() {
+        let dir = tempfile::tempdir().unwrap();
+        let node = IrohNode::new(dir.path().display().to_string()).unwrap();
+
+        let auto = node.blobs_add_bytes(b"hello".to_vec()).unwrap();
+        let named = node
+            .blobs_add_bytes_named(b"hello".to_vec(), b"greeting".to_vec())
+            .unwrap();
+        assert_eq!(auto.hash, named.hash);
+        assert_eq!(auto.format, named.format);
+        assert_eq!(auto.size, named.size);
+        assert_eq!(b"greeting".to_vec(), named.tag);
+
+        let tag = node.tags_get(b"greeting".to_vec()).unwrap().unwrap();
+        assert_eq!(named.hash, tag.hash);
+        assert_eq!(named.format, tag.format);
+
+        // the prefix of automatic tags is reserved
+        assert!(node
+            .blobs_add_bytes_named(b"hello".to_vec(), b"auto-greeting".to_vec())
+            .is_err());
+        assert!(node.tags_get(b"auto-greeting".to_vec()).unwrap().is_none());
+        assert!(SetTagOption::named(b"auto-greeting".to_vec()).is_err());
+    }
+
+    #[test]
+    fn test_blobs_add_and_share() {
+        let dir = tempfile::tempdir().unwrap();
+        let node = IrohNode::new(dir.path().display().to_string()).unwrap();
+
+        let file = dir.path().join("share.txt");
+        std::fs::write(&file, b"share me").unwrap();
+        let ticket = node
+            .blobs_add_and_share(
+                file.display().to_string(),
+                false,
+                Arc::new(SetTagOption::Auto),
+                AddrInfoOptions::Addresses,
+            )
+            .unwrap();
+        let ticket = crate::BlobTicket::new(ticket).unwrap();
+        assert!(ticket.hash().equal(&Hash::new(b"share me".to_vec())));
+        assert_eq!(BlobFormat::Raw, ticket.format());
+        assert!(!ticket.node_addr().direct_addresses().is_empty());
+    }
+
+    #[test]
+    fn test_blobs_pin_until() {
+        let dir = tempfile::tempdir().unwrap();
+        let node = IrohNode::new(dir.path().display().to_string()).unwrap();
+
+        let outcome = node.blobs_add_bytes(b"cached".to_vec()).unwrap();
+        assert!(node
+            .blobs_pin_until(outcome.hash.clone(), now_micros() - 1)
+            .is_err());
+        // only complete blobs can be pinned
+        let missing = Arc::new(Hash::new(b"missing".to_vec()));
+        assert!(node
+            .blobs_pin_until(missing, now_micros() + 60_000_000)
+            .is_err());
+        let expiry = now_micros() + 60_000_000;
+        let first = node
+            .blobs_pin_until(outcome.hash.clone(), expiry + 1)
+            .unwrap();
+        let pin = node.blobs_pin_until(outcome.hash.clone(), expiry).unwrap();
+        assert_eq!(Some(expiry), pin_expiry(&pin));
+        // the pin tags the blob itself and replaces the earlier pin
+        let tag = node.tags_get(pin.clone()).unwrap().unwrap();
+        assert_eq!(outcome.hash, tag.hash);
+        assert_eq!(BlobFormat::Raw, tag.format);
+        assert!(node.tags_get(first).unwrap().is_none());
+        node.tags_delete(outcome.tag).unwrap();
+
+        // the pin keeps the blob
+        node.blobs_cleanup_orphans(true, None).unwrap();
+        assert!(node.blobs_list().unwrap().contains(&outcome.hash));
+
+        // once expired, the pin is removed and the blob can be collected
+        block_on(
+            &node.rt(),
+            remove_expired_pins(&node.sync_client, expiry - 1),
         )
         .unwrap();
+        assert!(node.tags_get(pin.clone()).unwrap().is_some());
+        block_on(&node.rt(), remove_expired_pins(&node.sync_client, expiry)).unwrap();
+        assert!(node.tags_get(pin).unwrap().is_none());
+        node.blobs_cleanup_orphans(true, None).unwrap();
+        assert!(!node.blobs_list().unwrap().contains(&outcome.hash));
+    }
 
-        let collections = node.blobs_list_collections().unwrap();
-        assert!(collections.len() == 1);
-        let (collection_hash, blob_hashes) = {
-            let output = output.lock().unwrap();
-            let collection_hash = output.collection_hash.as_ref().cloned().unwrap();
-            let mut blob_hashes = output.blob_hashes.clone();
-            blob_hashes.push(collection_hash.clone());
-            (collection_hash, blob_hashes)
-        };
-        assert_eq!(*(collections[0].hash), *collection_hash);
+    #[test]
+    fn test_blobs_status_many() {
+        let dir = tempfile::tempdir().unwrap();
+        let node = IrohNode::new(dir.path().display().to_string()).unwrap();
+
+        let present = node.blobs_add_bytes(b"hello".to_vec()).unwrap().hash;
+        let missing = Arc::new(Hash::new(b"not added".to_vec()));
+        let statuses = node
+            .blobs_status_many(vec![missing.clone(), present.clone(), missing])
+            .unwrap();
         assert_eq!(
-            collections[0].total_blobs_count.unwrap(),
-            blob_hashes.len() as u64
+            vec![
+                BlobStatus::NotFound,
+                BlobStatus::Complete { size: 5 },
+                BlobStatus::NotFound
+            ],
+            statuses
         );
-
-        let blobs = node.blobs_list().unwrap();
-        hashes_exist(&blob_hashes, &blobs);
-        println!("finished");
     }
 
-    fn hashes_exist(expect: &Vec<Arc<Hash>>, got: &[Arc<Hash>]) {
-        for hash in expect {
-            if !got.contains(hash) {
-                panic!("expected to find hash {} in the list of hashes", hash);
+    #[test]
+    fn test_blobs_cleanup_orphans() {
+        let dir = tempfile::tempdir().unwrap();
+        let node = IrohNode::new(dir.path().display().to_string()).unwrap();
+
+        let tagged = node.blobs_add_bytes(b"tagged".to_vec()).unwrap();
+        let orphan = node.blobs_add_bytes(b"orphan".to_vec()).unwrap();
+        node.tags_delete(orphan.tag.clone()).unwrap();
+
+        let doc = node.doc_create(None).unwrap();
+        let author = node.author_create().unwrap();
+        let entry_hash = doc
+            .set_bytes(&author, b"key".to_vec(), b"in a doc".to_vec())
+            .unwrap();
+
+        struct Callback {
+            removed: Mutex<Vec<Arc<Hash>>>,
+        }
+        impl HashCallback for Callback {
+            fn on_hash(&self, hash: Arc<Hash>) -> Result<(), CallbackError> {
+                self.removed.lock().unwrap().push(hash);
+                Ok(())
             }
         }
+        let cb = Arc::new(Callback {
+            removed: Mutex::new(vec![]),
+        });
+
+        // complete blobs are only removed when asked for
+        let summary = node.blobs_cleanup_orphans(false, Some(cb.clone())).unwrap();
+        assert_eq!(0, summary.removed_blobs);
+        assert!(node.blobs_list().unwrap().contains(&orphan.hash));
+
+        let summary = node.blobs_cleanup_orphans(true, Some(cb.clone())).unwrap();
+        assert_eq!(1, summary.removed_blobs);
+        assert_eq!(6, summary.removed_bytes);
+        let removed = cb.removed.lock().unwrap();
+        assert_eq!(1, removed.len());
+        assert!(removed[0].equal(&orphan.hash));
+
+        let remaining = node.blobs_list().unwrap();
+        assert!(remaining.iter().any(|h| h.equal(&tagged.hash)));
+        assert!(remaining.iter().any(|h| h.equal(&entry_hash)));
+        assert!(!remaining.iter().any(|h| h.equal(&orphan.hash)));
     }
 
     #[test]
-    fn test_list_and_delete() {
-        setup_logging();
+    fn test_blobs_batch() {
+        let dir = tempfile::tempdir().unwrap();
+        let node = IrohNode::new(dir.into_path().display().to_string()).unwrap();
 
-        let iroh_dir = tempfile::tempdir().unwrap();
-        // we're going to use a very fast GC interval to get this test to delete stuff aggressively
-        let opts = NodeOptions {
-            gc_interval_millis: Some(100),
-        };
-        let node =
-            IrohNode::with_options(iroh_dir.into_path().display().to_string(), opts).unwrap();
+        let batch = node.blobs_batch();
+        let hash_a = batch.add_bytes("a".to_string(), b"aaa".to_vec()).unwrap();
+        let hash_b = batch.add_bytes("b".to_string(), b"bbb".to_vec()).unwrap();
+        // the blobs are protected by temporary tags until the batch is committed
+        assert_eq!(2, node.tags_list().unwrap().len());
+
+        let outcome = batch.commit(Arc::new(SetTagOption::Auto)).unwrap();
+        // only the tag of the collection is left
+        let tags = node.tags_list().unwrap();
+        assert_eq!(1, tags.len());
+        assert_eq!(outcome.tag, tags[0].name);
+        assert!(batch.add_bytes("c".to_string(), b"ccc".to_vec()).is_err());
+
+        let collection = node.blobs_get_collection(outcome.hash).unwrap();
+        let links = collection.links().unwrap();
+        assert_eq!(vec![hash_a, hash_b.clone()], links);
+
+        let second = collection.get(1).unwrap().unwrap();
+        assert_eq!("b", second.name);
+        assert_eq!(hash_b, second.link);
+        assert!(collection.get(2).unwrap().is_none());
+    }
 
-        // create bytes
-        let blob_size = 100;
-        let mut blobs = vec![];
-        let num_blobs = 3;
+    #[test]
+    fn test_blobs_add_from_path_dry_run() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a"), b"aaa").unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        std::fs::write(dir.path().join("sub").join("b"), b"bbbb").unwrap();
 
-        for _i in 0..num_blobs {
-            let mut bytes = vec![0; blob_size];
-            rand::thread_rng().fill_bytes(&mut bytes);
-            blobs.push(bytes);
-        }
+        let iroh_dir = tempfile::tempdir().unwrap();
+        let node = IrohNode::new(iroh_dir.path().display().to_string()).unwrap();
+        let existing = node.blobs_add_bytes(b"aaa".to_vec()).unwrap().hash;
 
-        let mut hashes = vec![];
-        let mut tags = vec![];
-        for blob in blobs {
-            let output = node.blobs_add_bytes(blob).unwrap();
-            hashes.push(output.hash);
-            tags.push(output.tag);
+        struct Callback;
+        impl AddCallback for Callback {
+            fn progress(&self, _progress: Arc<AddProgress>) -> Result<(), CallbackError> {
+                Ok(())
+            }
         }
 
-        let got_hashes = node.blobs_list().unwrap();
-        assert_eq!(num_blobs, got_hashes.len());
-        hashes_exist(&hashes, &got_hashes);
-
-        let remove_hash = hashes.pop().unwrap();
-        let remove_tag = tags.pop().unwrap();
-        // delete the tag for the first blob
-        node.tags_delete(remove_tag).unwrap();
-        // wait for GC to clear the blob. windows test runner is slow & needs like 500ms
-        std::thread::sleep(Duration::from_millis(500));
+        let summary = node
+            .blobs_add_from_path_dry_run(dir.path().display().to_string(), Arc::new(Callback))
+            .unwrap();
+        assert_eq!(2, summary.files.len());
+        assert_eq!(7, summary.size);
+        assert_eq!(1, summary.existing_files);
+        assert_eq!(3, summary.existing_size);
+        assert_eq!(existing, summary.files[0].link);
+        assert_eq!(Hash::new(b"bbbb".to_vec()), *summary.files[1].link);
+        // files are named relative to the imported directory
+        assert_eq!("a", summary.files[0].name);
+        assert_eq!(
+            Path::new("sub").join("b").display().to_string(),
+            summary.files[1].name
+        );
 
-        let got_hashes = node.blobs_list().unwrap();
-        assert_eq!(num_blobs - 1, got_hashes.len());
-        hashes_exist(&hashes, &got_hashes);
+        // nothing was added
+        assert_eq!(1, node.blobs_list().unwrap().len());
 
-        for hash in got_hashes {
-            if remove_hash.equal(&hash) {
-                panic!("blob {} should have been removed", remove_hash);
-            }
+        // a single file is named by its file name
+        let summary = node
+            .blobs_add_from_path_dry_run(
+                dir.path().join("a").display().to_string(),
+                Arc::new(Callback),
+            )
+            .unwrap();
+        assert_eq!("a", summary.files[0].name);
+
+        // a symlink loop is skipped instead of followed
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(dir.path(), dir.path().join("sub").join("loop")).unwrap();
+            let summary = node
+                .blobs_add_from_path_dry_run(dir.path().display().to_string(), Arc::new(Callback))
+                .unwrap();
+            assert_eq!(2, summary.files.len());
         }
     }
 
-    async fn build_iroh_core(
-        path: &std::path::Path,
-    ) -> iroh::node::Node<iroh::blobs::store::fs::Store> {
-        iroh::node::Node::persistent(path)
-            .await
-            .unwrap()
-            .spawn()
-            .await
+    #[test]
+    fn test_blobs_add_from_path_stream() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file");
+        std::fs::write(&path, b"hello").unwrap();
+
+        let iroh_dir = tempfile::tempdir().unwrap();
+        let node = Arc::new(IrohNode::new(iroh_dir.path().display().to_string()).unwrap());
+        let stream = node
+            .blobs_add_from_path_stream(
+                path.display().to_string(),
+                false,
+                Arc::new(SetTagOption::Auto),
+                Arc::new(WrapOption::NoWrap),
+                None,
+            )
+            .unwrap();
+        let mut events = Vec::new();
+        // the stream needs no runtime to be polled on, like on the foreign side
+        while let Some(event) = futures::executor::block_on(stream.next()).unwrap() {
+            events.push(event);
+        }
+        let last = events.last().unwrap();
+        assert_eq!(AddProgressType::AllDone, last.r#type());
+        assert_eq!(Hash::new(b"hello".to_vec()), *last.as_all_done().hash);
+        assert!(futures::executor::block_on(stream.next())
             .unwrap()
+            .is_none());
     }
 
-    #[tokio::test]
-    async fn iroh_core_blobs_add_get_bytes() {
+    #[test]
+    fn test_blobs_add_hard_link() {
         let dir = tempfile::tempdir().unwrap();
-        let node = build_iroh_core(dir.path()).await;
-        let sizes = [1, 10, 100, 1000, 10000, 100000];
-        let mut hashes = Vec::new();
-        for size in sizes.iter() {
-            let hash = iroh_core_blobs_add_get_bytes_size(&node, *size);
-            hashes.push(hash)
+        let source = dir.path().join("video.mp4");
+        // larger than what the store inlines, so the data stays in the file
+        let data = vec![7u8; 64 * 1024];
+        std::fs::write(&source, &data).unwrap();
+
+        let iroh_dir = tempfile::tempdir().unwrap();
+        let node = IrohNode::new(iroh_dir.path().display().to_string()).unwrap();
+
+        struct Callback;
+        impl AddCallback for Callback {
+            fn progress(&self, _progress: Arc<AddProgress>) -> Result<(), CallbackError> {
+                Ok(())
+            }
         }
+        let files = node
+            .blobs_add_from_path(
+                source.display().to_string(),
+                false,
+                Arc::new(SetTagOption::Auto),
+                Arc::new(WrapOption::NoWrap),
+                Arc::new(Callback),
+                Some(ImportStrategy::HardLink),
+            )
+            .unwrap();
+        assert_eq!(1, files.len());
+        assert!(!files[0].name.contains(HARD_LINKS_DIR));
+        let hash = files[0].link.clone();
+
+        // the source can be removed, the data lives on in the link
+        std::fs::remove_file(&source).unwrap();
+        assert_eq!(data, node.blobs_read_to_bytes(hash.clone()).unwrap());
+
+        // the link is removed once the blob is gone
+        let links = iroh_dir.path().join(HARD_LINKS_DIR);
+        assert_eq!(1, std::fs::read_dir(&links).unwrap().count());
+        node.blobs_delete_blob(hash).unwrap();
+        block_on(
+            &node.rt(),
+            remove_unused_hard_links(&node.sync_client, &node.hard_links),
+        )
+        .unwrap();
+        assert_eq!(0, std::fs::read_dir(&links).unwrap().count());
     }
 
-    async fn iroh_core_blobs_add_get_bytes_size(
-        node: &iroh::node::Node<iroh::blobs::store::fs::Store>,
-        size: usize,
-    ) -> iroh::blobs::Hash {
-        let client = node.client();
-        // create bytes
-        let mut bytes = vec![0; size];
-        rand::thread_rng().fill_bytes(&mut bytes);
-        let bytes: Bytes = bytes.into();
-        // add blob
-        let add_outcome = client.blobs().add_bytes(bytes.clone()).await.unwrap();
-        // check outcome
-        assert_eq!(add_outcome.format, iroh::blobs::BlobFormat::Raw);
-        assert_eq!(add_outcome.size, size as u64);
-        // check size
-        let hash = add_outcome.hash;
-        let reader = client.blobs().read(hash).await.unwrap();
-        let got_size = reader.size();
-        assert_eq!(got_size, size as u64);
-        //
-        // get blob
-        let got_bytes = client.blobs().read_to_bytes(hash).await.unwrap();
-        assert_eq!(got_bytes.len(), size);
-        assert_eq!(got_bytes, bytes);
-        hash
+    #[test]
+    fn test_blobs_content_unavailable() {
+        let iroh_dir = tempfile::tempdir().unwrap();
+        let node = IrohNode::new(iroh_dir.path().display().to_string()).unwrap();
+
+        let hash = Arc::new(Hash::new(b"not downloaded".to_vec()));
+        let err = node.blobs_read_to_bytes(hash.clone()).unwrap_err();
+        assert!(err.is_content_unavailable());
+        assert_eq!(Some(hash.clone()), err.content_unavailable_hash());
+        let err = node.blobs_get_collection(hash.clone()).unwrap_err();
+        assert_eq!(Some(hash.clone()), err.content_unavailable_hash());
+
+        // other errors are not mistaken for missing content
+        let outcome = node.blobs_add_bytes(b"hello".to_vec()).unwrap();
+        let err = node.blobs_get_collection(outcome.hash).unwrap_err();
+        assert!(!err.is_content_unavailable());
+        node.shutdown().unwrap();
+        let err = node.blobs_read_to_bytes(hash).unwrap_err();
+        assert!(!err.is_content_unavailable());
     }
 
-    #[tokio::test]
-    async fn iroh_core_blobs_list_collections() {
+    #[test]
+    fn test_evict_lru_blobs() {
         let iroh_dir = tempfile::tempdir().unwrap();
-        let node = build_iroh_core(iroh_dir.path()).await;
+        let node = IrohNode::new(iroh_dir.path().display().to_string()).unwrap();
 
-        // collection dir
-        let dir = tempfile::tempdir().unwrap();
-        let num_blobs = 3;
-        let blob_size = 100;
-        for i in 0..num_blobs {
-            let path = dir.path().join(i.to_string());
-            let mut file = tokio::fs::File::create(path).await.unwrap();
-            let mut bytes = vec![0; blob_size];
-            rand::thread_rng().fill_bytes(&mut bytes);
-            file.write_all(&bytes).await.unwrap()
-        }
+        // the least recently used blob, but protected by a named tag
+        let named = node
+            .blobs_add_bytes_named(vec![0; 1000], b"keep".to_vec())
+            .unwrap();
+        let a = node.blobs_add_bytes(vec![1; 1000]).unwrap();
+        let b = node.blobs_add_bytes(vec![2; 1000]).unwrap();
+        let c = node.blobs_add_bytes(vec![3; 1000]).unwrap();
+        let evict = |max_bytes| {
+            block_on(
+                &node.rt(),
+                evict_lru_blobs(
+                    &node.sync_client,
+                    &node.blob_access,
+                    &node.blob_metadata,
+                    max_bytes,
+                ),
+            )
+            .unwrap()
+        };
 
-        let client = node.client();
-        // ensure there are no blobs to start
-        let blobs = client
-            .blobs()
-            .list()
-            .await
+        // nothing is evicted while the store fits
+        assert!(evict(u64::MAX).is_empty());
+
+        // reading `a` makes `c` the least recently used blob, `b` is pinned
+        std::thread::sleep(Duration::from_millis(10));
+        node.blobs_read_to_bytes(a.hash.clone()).unwrap();
+        node.blobs_pin_until(b.hash.clone(), now_micros() + 60_000_000)
+            .unwrap();
+
+        let total: u64 = node
+            .blobs_list()
             .unwrap()
-            .collect::<Vec<_>>()
-            .await;
-        assert_eq!(0, blobs.len());
+            .into_iter()
+            .map(|hash| node.blobs_size(&hash).unwrap())
+            .sum();
+        assert_eq!(vec![c.hash.0], evict(total - 1));
 
-        let mut stream = client
-            .blobs()
-            .add_from_path(
-                dir.into_path(),
-                false,
-                iroh::blobs::util::SetTagOption::Auto,
-                iroh::client::blobs::WrapOption::NoWrap,
+        let blobs = node.blobs_list().unwrap();
+        assert!(blobs.contains(&a.hash));
+        assert!(blobs.contains(&b.hash));
+        assert!(!blobs.contains(&c.hash));
+        assert!(!node
+            .tags_list()
+            .unwrap()
+            .iter()
+            .any(|tag| tag.hash == c.hash));
+
+        // pinned and named blobs are kept even if the store stays above the limit
+        let evicted = evict(0);
+        assert_eq!(vec![a.hash.0], evicted);
+        let blobs = node.blobs_list().unwrap();
+        assert!(blobs.contains(&b.hash));
+        assert!(blobs.contains(&named.hash));
+        assert!(node
+            .tags_list()
+            .unwrap()
+            .iter()
+            .any(|tag| tag.name == b"keep".to_vec()));
+    }
+
+    #[test]
+    fn test_blobs_references() {
+        let dir = tempfile::tempdir().unwrap();
+        let node = IrohNode::new(dir.path().display().to_string()).unwrap();
+        let added = node.blobs_add_bytes(b"shared".to_vec()).unwrap();
+        let hash = added.hash.clone();
+
+        let collection = Collection::new();
+        collection.push("shared".to_string(), &hash).unwrap();
+        let wrapped = node
+            .blobs_create_collection(
+                Arc::new(collection),
+                Arc::new(SetTagOption::Named(b"wrapped".to_vec())),
+                vec![],
             )
-            .await
             .unwrap();
 
-        let mut collection_hash = None;
-        let mut collection_format = None;
-        let mut hashes = Vec::new();
+        let doc = node.doc_create(None).unwrap();
+        let author = node.author_create().unwrap();
+        doc.set_hash(author, b"key".to_vec(), hash.clone(), 6)
+            .unwrap();
 
-        while let Some(progress) = stream.next().await {
-            let progress = progress.unwrap();
-            match progress {
-                iroh::blobs::provider::AddProgress::AllDone { hash, format, .. } => {
-                    collection_hash = Some(hash);
-                    collection_format = Some(format);
-                }
-                iroh::blobs::provider::AddProgress::Abort(err) => {
-                    panic!("{}", err);
-                }
-                iroh::blobs::provider::AddProgress::Done { hash, .. } => hashes.push(hash),
-                _ => {}
-            }
-        }
+        let references = node.blobs_references(hash).unwrap();
+        let mut tags = references.tags;
+        tags.sort();
+        let mut expected = vec![added.tag, wrapped.tag];
+        expected.sort();
+        assert_eq!(expected, tags);
+        assert_eq!(vec![doc.id()], references.docs);
+
+        let unreferenced = Arc::new(Hash::new(b"unreferenced".to_vec()));
+        let references = node.blobs_references(unreferenced).unwrap();
+        assert!(references.tags.is_empty());
+        assert!(references.docs.is_empty());
+    }
 
-        let collection_hash = collection_hash.unwrap();
-        let collection_format = collection_format.unwrap();
+    #[test]
+    fn test_blobs_add_file_wrapped() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("photo.jpg");
+        std::fs::write(&path, b"jpeg").unwrap();
 
-        assert_eq!(iroh::blobs::BlobFormat::HashSeq, collection_format);
+        let iroh_dir = tempfile::tempdir().unwrap();
+        let node = IrohNode::new(iroh_dir.path().display().to_string()).unwrap();
 
-        let collections = client
-            .blobs()
-            .list_collections()
-            .unwrap()
-            .try_collect::<Vec<_>>()
-            .await
+        let added = node
+            .blobs_add_file_wrapped(path.display().to_string(), None)
             .unwrap();
-        assert_eq!(1, collections.len());
-        assert_eq!(collections[0].hash, collection_hash);
-        // add length for the metadata blob
-        let total_blobs_count = hashes.len() + 1;
+        let collection = node.blobs_get_collection(added.hash).unwrap();
+        assert_eq!(vec!["photo.jpg".to_string()], collection.names().unwrap());
         assert_eq!(
-            collections[0].total_blobs_count.unwrap(),
-            total_blobs_count as u64
+            vec![Arc::new(Hash::new(b"jpeg".to_vec()))],
+            collection.links().unwrap()
         );
-        // this is always `None`
-        // assert_eq!(collections[0].total_blobs_size.unwrap(), 300 as u64);
+
+        let added = node
+            .blobs_add_file_wrapped(path.display().to_string(), Some("cat.jpg".to_string()))
+            .unwrap();
+        let collection = node.blobs_get_collection(added.hash).unwrap();
+        assert_eq!(vec!["cat.jpg".to_string()], collection.names().unwrap());
+
+        assert!(node
+            .blobs_add_file_wrapped(dir.path().display().to_string(), None)
+            .is_err());
+    }
+
+    #[test]
+    fn test_blob_reader() {
+        let dir = tempfile::tempdir().unwrap();
+        let node = IrohNode::new(dir.path().display().to_string()).unwrap();
+        let hash = node.blobs_add_bytes(b"hello world".to_vec()).unwrap().hash;
+
+        let reader = node.blobs_open_reader(hash).unwrap();
+        assert_eq!(11, reader.size());
+        assert_eq!(b"hello".to_vec(), reader.read(5).unwrap());
+        assert_eq!(5, reader.position());
+
+        assert_eq!(6, reader.seek(6));
+        assert_eq!(b"world".to_vec(), reader.read(100).unwrap());
+        assert!(reader.read(100).unwrap().is_empty());
+
+        // seeking past the end clamps to the size
+        assert_eq!(11, reader.seek(100));
+        assert!(reader.read(1).unwrap().is_empty());
+
+        let missing = Arc::new(Hash::new(b"missing".to_vec()));
+        assert!(node.blobs_open_reader(missing).is_err());
+    }
+
+    #[test]
+    fn test_blobs_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().display().to_string();
+        let hash = {
+            let node = IrohNode::new(path.clone()).unwrap();
+            let hash = node.blobs_add_bytes(b"hello".to_vec()).unwrap().hash;
+            assert!(node.blobs_get_metadata(hash.clone()).is_none());
+
+            let extra = HashMap::from([("name".to_string(), "hello.txt".to_string())]);
+            node.blobs_set_metadata(hash.clone(), "text/plain".to_string(), extra.clone(), None)
+                .unwrap();
+            let meta = node.blobs_get_metadata(hash.clone()).unwrap();
+            assert_eq!(meta.content_type, "text/plain");
+            assert_eq!(meta.extra, extra);
+            assert_eq!(meta.modified_micros, None);
+            hash
+        };
+
+        // metadata survives a restart of the node
+        let node = IrohNode::new(path).unwrap();
+        let meta = node.blobs_get_metadata(hash.clone()).unwrap();
+        assert_eq!(meta.content_type, "text/plain");
+
+        // and is removed together with the blob
+        node.blobs_delete_blob(hash.clone()).unwrap();
+        assert!(node.blobs_get_metadata(hash).is_none());
+    }
+
+    #[test]
+    fn test_blobs_subscribe_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let node = IrohNode::new(dir.path().display().to_string()).unwrap();
+        let existing = node.blobs_add_bytes(b"existing".to_vec()).unwrap().hash;
+
+        struct Callback(std::sync::mpsc::Sender<Arc<StoreEvent>>);
+        impl StoreEventCallback for Callback {
+            fn event(&self, event: Arc<StoreEvent>) -> Result<(), CallbackError> {
+                self.0.send(event).map_err(|_| CallbackError::Cancelled)
+            }
+        }
+        let (s, r) = std::sync::mpsc::channel();
+        let subscription = node.blobs_subscribe_store(Arc::new(Callback(s))).unwrap();
+        let next = || r.recv_timeout(Duration::from_secs(10)).unwrap();
+
+        // blobs already stored are not reported, the collection and its metadata blob are
+        let collection = Arc::new(Collection::new());
+        collection.push("a".to_string(), &existing).unwrap();
+        let added = node
+            .blobs_create_collection(collection, Arc::new(SetTagOption::Auto), vec![])
+            .unwrap();
+        let events = [next(), next()];
+        assert!(events
+            .iter()
+            .all(|event| event.r#type() == StoreEventType::Added
+                && event.as_added().hash != existing));
+        assert!(events.iter().any(|event| event.as_added()
+            == StoreEventAdded {
+                hash: added.hash.clone(),
+                format: BlobFormat::HashSeq
+            }));
+
+        node.blobs_delete_blob(existing.clone()).unwrap();
+        let event = next();
+        assert_eq!(event.r#type(), StoreEventType::Removed);
+        assert_eq!(event.as_removed(), existing);
+
+        // nothing is reported after cancelling
+        subscription.cancel();
+        node.blobs_add_bytes(b"later".to_vec()).unwrap();
+        assert!(r.recv_timeout(Duration::from_secs(2)).is_err());
+    }
+
+    #[test]
+    fn test_blobs_export_modified_time() {
+        let dir = tempfile::tempdir().unwrap();
+        let node = IrohNode::new(dir.path().join("node").display().to_string()).unwrap();
+        let hash = node.blobs_add_bytes(b"mirrored".to_vec()).unwrap().hash;
+        let out = dir.path().join("out");
+        let modified = |name: &str| {
+            let meta = std::fs::metadata(out.join(name)).unwrap();
+            meta.modified()
+                .unwrap()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+        };
+
+        // without a stored time the files are as new as the export
+        node.blobs_write_to_path(hash.clone(), out.join("plain").display().to_string())
+            .unwrap();
+        assert!(modified("plain") > Duration::from_secs(1_000_000_000));
+
+        let micros = 1_500_000_000_000_000;
+        node.blobs_set_metadata(hash.clone(), String::new(), HashMap::new(), Some(micros))
+            .unwrap();
+        node.blobs_write_to_path(hash.clone(), out.join("written").display().to_string())
+            .unwrap();
+        assert_eq!(modified("written"), Duration::from_micros(micros));
+        node.blobs_export(
+            hash,
+            out.join("exported").display().to_string(),
+            BlobExportFormat::Blob,
+            BlobExportMode::Copy,
+        )
+        .unwrap();
+        assert_eq!(modified("exported"), Duration::from_micros(micros));
+    }
+
+    #[test]
+    fn test_json_store_recovers_from_corrupt_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().display().to_string();
+        // a write interrupted by a crash with a non-atomic write leaves truncated json
+        std::fs::write(dir.path().join(BLOB_METADATA_FILE), b"{\"abc").unwrap();
+
+        // the node starts with empty metadata instead of failing
+        let hash = {
+            let node = IrohNode::new(path.clone()).unwrap();
+            let hash = node.blobs_add_bytes(b"hello".to_vec()).unwrap().hash;
+            assert!(node.blobs_get_metadata(hash.clone()).is_none());
+            node.blobs_set_metadata(hash.clone(), "text/plain".to_string(), HashMap::new(), None)
+                .unwrap();
+            hash
+        };
+
+        // the file was replaced as a whole, without leftover temporary files
+        let node = IrohNode::new(path).unwrap();
+        assert!(node.blobs_get_metadata(hash).is_some());
+        let leftovers = std::fs::read_dir(dir.path())
+            .unwrap()
+            .filter(|entry| {
+                let name = entry.as_ref().unwrap().file_name();
+                name.to_string_lossy().starts_with(".tmp")
+            })
+            .count();
+        assert_eq!(0, leftovers);
     }
 
     pub fn setup_logging() {
@@ -1,13 +1,27 @@
-use std::{path::PathBuf, str::FromStr, sync::Arc, time::SystemTime};
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime},
+};
 
 use bytes::Bytes;
 use futures::{StreamExt, TryStreamExt};
-use iroh::client::MemDoc;
+use iroh::client::{MemDoc, MemIroh};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    block_on, ticket::AddrInfoOptions, AuthorId, CallbackError, Hash, IrohError, IrohNode,
-    PublicKey,
+    blob::{check_available, HardLinkStore},
+    block_on,
+    error::{callback_continue, PermissionDenied},
+    node::NodeState,
+    ticket::{refresh_doc_ticket, AddrInfoOptions, DocTicket},
+    AuthorId, CallbackError, DownloadProgress, Hash, ImportStrategy, IrohError, IrohNode,
+    ProgressReceiver, ProgressSender, PublicKey, WorkspaceTicket,
 };
 
 #[derive(Debug)]
@@ -27,65 +41,356 @@ impl From<iroh::docs::CapabilityKind> for CapabilityKind {
     }
 }
 
+/// Salt of the argon2id key derivation in [`IrohNode::doc_create_deterministic`].
+///
+/// The version suffix must change together with the derivation parameters, as any change
+/// gives every seed a different document.
+const DETERMINISTIC_DOC_SALT: &[u8] = b"iroh-ffi deterministic document namespace v1";
+
+/// Memory cost of the argon2id key derivation of deterministic documents, in KiB.
+const DETERMINISTIC_DOC_MEMORY_KIB: u32 = 64 * 1024;
+
+/// Number of passes of the argon2id key derivation of deterministic documents.
+const DETERMINISTIC_DOC_ITERATIONS: u32 = 3;
+
+/// Derive the namespace secret of a deterministic document from `seed`.
+///
+/// Uses argon2id, so that guessing seeds offline against the public namespace id is costly.
+fn derive_namespace_secret(seed: &[u8]) -> anyhow::Result<iroh::docs::NamespaceSecret> {
+    let params = argon2::Params::new(
+        DETERMINISTIC_DOC_MEMORY_KIB,
+        DETERMINISTIC_DOC_ITERATIONS,
+        1,
+        Some(32),
+    )
+    .map_err(|err| anyhow::anyhow!("invalid key derivation parameters: {err}"))?;
+    let argon2 = argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params);
+    let mut key = [0u8; 32];
+    argon2
+        .hash_password_into(seed, DETERMINISTIC_DOC_SALT, &mut key)
+        .map_err(|err| anyhow::anyhow!("failed to derive the namespace key: {err}"))?;
+    Ok(iroh::docs::NamespaceSecret::from_bytes(&key))
+}
+
+/// The maximum combined content length [`Doc::content_bytes_many`] reads, 16 MiB.
+pub const CONTENT_BYTES_MANY_MAX_SIZE: u64 = 16 * 1024 * 1024;
+
 impl IrohNode {
     /// Create a new doc.
-    pub fn doc_create(&self) -> Result<Arc<Doc>, IrohError> {
+    ///
+    /// If `download_policy` is set, it is in effect from the start, see
+    /// [`Doc::set_download_policy`]. Otherwise the doc downloads the content of all entries.
+    pub fn doc_create(
+        &self,
+        download_policy: Option<Arc<DownloadPolicy>>,
+    ) -> Result<Arc<Doc>, IrohError> {
+        self.state.ensure_running()?;
         block_on(&self.rt(), async {
             let doc = self.sync_client.docs().create().await?;
+            if let Some(policy) = download_policy {
+                // no peer knows the doc yet, so nothing was downloaded under the default policy
+                doc.set_download_policy((*policy).clone().into()).await?;
+            }
+
+            Ok(Arc::new(Doc {
+                inner: doc,
+                client: self.sync_client.clone(),
+                rt: self.rt().clone(),
+                state: self.state.clone(),
+                max_value_size: self.max_doc_value_size,
+                hard_links: self.hard_links.clone(),
+                key_locks: self.key_locks.clone(),
+                initial_sync: None,
+                writable: Arc::new(AtomicBool::new(true)),
+            }))
+        })
+    }
+
+    /// Create a doc, write `initial` entries to it as `author`, and share it.
+    ///
+    /// Waits until the node knows the addresses `addr_options` asks for before creating the
+    /// ticket, like [`IrohNode::blobs_add_and_share`], so the ticket is usable right away. If
+    /// any step fails, the new doc is dropped again.
+    pub fn doc_create_and_share(
+        &self,
+        author: Arc<AuthorId>,
+        initial: Vec<KeyValue>,
+        mode: ShareMode,
+        addr_options: AddrInfoOptions,
+    ) -> Result<DocAndTicket, IrohError> {
+        let doc = self.doc_create(None)?;
+        let res = block_on(&self.rt(), async {
+            for entry in initial {
+                doc.check_value_size(entry.value.len() as u64)?;
+                doc.inner
+                    .set_bytes(author.0, entry.key, entry.value)
+                    .await?;
+            }
+            self.wait_for_ticket_addrs(addr_options).await?;
+            let ticket = doc.inner.share(mode.into(), addr_options.into()).await?;
+            Ok(ticket.to_string())
+        });
+        match res {
+            Ok(ticket) => Ok(DocAndTicket { doc, ticket }),
+            Err(err) => {
+                if let Err(err) = self.doc_drop(doc.id()) {
+                    tracing::warn!("failed to drop doc {}: {:?}", doc.id(), err);
+                }
+                Err(err)
+            }
+        }
+    }
+
+    /// Create a document whose namespace is derived from `seed`, or open it if it exists.
+    ///
+    /// Every node calling this with the same seed gets the same writable document, without
+    /// exchanging a ticket. Anyone who knows the seed has full write access to the document,
+    /// so treat it like a secret key.
+    ///
+    /// The namespace key is derived with argon2id, using 64 MiB of memory and 3 passes, which
+    /// makes guessing seeds offline against the public namespace id expensive. The derivation
+    /// takes a noticeable fraction of a second, so avoid calling this from a UI thread. It
+    /// only slows guessing down: a seed from a small set, such as a common phrase, can still
+    /// be found.
+    pub fn doc_create_deterministic(&self, seed: Vec<u8>) -> Result<Arc<Doc>, IrohError> {
+        self.state.ensure_running()?;
+        block_on(&self.rt(), async {
+            let secret = tokio::task::spawn_blocking(move || derive_namespace_secret(&seed))
+                .await
+                .map_err(|err| anyhow::anyhow!("key derivation failed: {err}"))??;
+            let doc = self
+                .sync_client
+                .docs()
+                .import_namespace(iroh::docs::Capability::Write(secret))
+                .await?;
 
             Ok(Arc::new(Doc {
                 inner: doc,
+                client: self.sync_client.clone(),
                 rt: self.rt().clone(),
+                state: self.state.clone(),
+                max_value_size: self.max_doc_value_size,
+                hard_links: self.hard_links.clone(),
+                key_locks: self.key_locks.clone(),
+                initial_sync: None,
+                writable: Arc::new(AtomicBool::new(true)),
             }))
         })
     }
 
     /// Join and sync with an already existing document.
-    pub fn doc_join(&self, ticket: String) -> Result<Arc<Doc>, IrohError> {
+    ///
+    /// If `timeout_millis` is set, this waits for the first successful sync with one of the
+    /// peers in the ticket and errors if that does not happen within the timeout. The document
+    /// stays imported on this node even if the timeout is hit. Without a timeout, the first
+    /// sync is watched in the background until it happens or the returned doc is dropped, see
+    /// [`Doc::await_initial_sync`].
+    ///
+    /// If `download_policy` is set, it is applied before syncing starts, so no content is
+    /// downloaded under the default policy, which downloads the content of all entries.
+    pub fn doc_join(
+        &self,
+        ticket: String,
+        timeout_millis: Option<u64>,
+        download_policy: Option<Arc<DownloadPolicy>>,
+    ) -> Result<Arc<Doc>, IrohError> {
+        self.state.ensure_running()?;
         block_on(&self.rt(), async {
-            let ticket = iroh::docs::DocTicket::from_str(&ticket).map_err(anyhow::Error::from)?;
-            let doc = self.sync_client.docs().import(ticket).await?;
+            let iroh::docs::DocTicket { capability, nodes } =
+                iroh::docs::DocTicket::from_str(&ticket.to_ascii_lowercase())
+                    .map_err(anyhow::Error::from)?;
+            // like `import_and_subscribe`, with the policy set before the sync starts
+            let doc = self.sync_client.docs().import_namespace(capability).await?;
+            if let Some(policy) = download_policy {
+                doc.set_download_policy((*policy).clone().into()).await?;
+            }
+            let mut stream = doc.subscribe().await?;
+            doc.start_sync(nodes).await?;
+
+            let (synced_s, synced_r) = tokio::sync::watch::channel(None);
+            self.rt().spawn(async move {
+                loop {
+                    let event = tokio::select! {
+                        // nobody can wait for the sync anymore: the join timed out, or the doc
+                        // was dropped
+                        _ = synced_s.closed() => break,
+                        event = stream.next() => event,
+                    };
+                    match event {
+                        Some(Ok(iroh::client::docs::LiveEvent::SyncFinished(event)))
+                            if event.result.is_ok() =>
+                        {
+                            synced_s.send(Some(event.into())).ok();
+                            break;
+                        }
+                        Some(Ok(_)) => {}
+                        Some(Err(err)) => {
+                            tracing::error!("rpc error: {:?}", err);
+                        }
+                        None => break,
+                    }
+                }
+            });
+
+            if let Some(millis) = timeout_millis {
+                wait_initial_sync(synced_r.clone(), millis).await?;
+            }
             Ok(Arc::new(Doc {
                 inner: doc,
+                client: self.sync_client.clone(),
                 rt: self.rt().clone(),
+                state: self.state.clone(),
+                max_value_size: self.max_doc_value_size,
+                hard_links: self.hard_links.clone(),
+                key_locks: self.key_locks.clone(),
+                initial_sync: Some(synced_r),
+                writable: Arc::new(AtomicBool::new(false)),
             }))
         })
     }
 
+    /// Share several documents with a single [`WorkspaceTicket`].
+    ///
+    /// The ticket contains a document ticket for each of `doc_ids`, created like with
+    /// [`Doc::share`]. Errors if one of the documents does not exist on this node.
+    pub fn doc_share_workspace(
+        &self,
+        doc_ids: Vec<String>,
+        mode: ShareMode,
+        addr_options: AddrInfoOptions,
+    ) -> Result<Arc<WorkspaceTicket>, IrohError> {
+        if doc_ids.is_empty() {
+            return Err(anyhow::anyhow!("a workspace needs at least one document").into());
+        }
+        let mut tickets = Vec::with_capacity(doc_ids.len());
+        for id in doc_ids {
+            let doc = self
+                .doc_open(id.clone())?
+                .ok_or_else(|| anyhow::anyhow!("document {id} not found"))?;
+            let ticket = doc.share(mode, addr_options)?;
+            tickets.push(iroh::docs::DocTicket::from_str(&ticket).map_err(anyhow::Error::from)?);
+        }
+        Ok(Arc::new(tickets.into()))
+    }
+
+    /// Re-issue a document ticket with the current addresses of this node.
+    ///
+    /// Long-lived tickets go stale when the node changes networks. The refreshed ticket has the
+    /// same capability, and the entry for this node gets its current relay url and direct
+    /// addresses, limited to the kinds of address info the entry has. Entries for other nodes
+    /// are kept as they are. Errors if this node is not one of the ticket's nodes.
+    pub fn doc_refresh_ticket(&self, ticket: Arc<DocTicket>) -> Result<Arc<DocTicket>, IrohError> {
+        self.state.ensure_running()?;
+        block_on(&self.rt(), async {
+            let current = self.sync_client.status().await?.addr;
+            let ticket = refresh_doc_ticket(&ticket.0, &current)?;
+            Ok(Arc::new(DocTicket(ticket)))
+        })
+    }
+
+    /// Re-issue a [`WorkspaceTicket`] with the current addresses of this node.
+    ///
+    /// Refreshes each document ticket like [`Self::doc_refresh_ticket`]. Errors if this node is
+    /// not one of the nodes of every document ticket.
+    pub fn doc_refresh_workspace_ticket(
+        &self,
+        ticket: Arc<WorkspaceTicket>,
+    ) -> Result<Arc<WorkspaceTicket>, IrohError> {
+        self.state.ensure_running()?;
+        block_on(&self.rt(), async {
+            let current = self.sync_client.status().await?.addr;
+            let tickets = ticket
+                .0
+                .iter()
+                .map(|ticket| refresh_doc_ticket(ticket, &current))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(Arc::new(tickets.into()))
+        })
+    }
+
+    /// Join all documents of a [`WorkspaceTicket`], see [`Self::doc_join`].
+    ///
+    /// Returns the documents in the order of the ticket. The documents are joined one after
+    /// the other; if joining one fails, the documents joined before stay imported.
+    pub fn doc_join_workspace(
+        &self,
+        ticket: Arc<WorkspaceTicket>,
+    ) -> Result<Vec<Arc<Doc>>, IrohError> {
+        ticket
+            .doc_tickets()
+            .into_iter()
+            .map(|ticket| self.doc_join(ticket.to_string(), None, None))
+            .collect()
+    }
+
     /// Join and sync with an already existing document and subscribe to events on that document.
+    ///
+    /// If `timeout_millis` is set, this waits for the first successful sync with one of the
+    /// peers in the ticket and errors if that does not happen within the timeout. The document
+    /// stays imported and the subscription stays active even if the timeout is hit.
     pub fn doc_join_and_subscribe(
         &self,
         ticket: String,
         cb: Arc<dyn SubscribeCallback>,
+        timeout_millis: Option<u64>,
     ) -> Result<Arc<Doc>, IrohError> {
+        self.state.ensure_running()?;
         let (doc, mut stream) = block_on(&self.rt(), async {
-            let ticket = iroh::docs::DocTicket::from_str(&ticket)?;
+            let ticket = iroh::docs::DocTicket::from_str(&ticket.to_ascii_lowercase())?;
             self.sync_client.docs().import_and_subscribe(ticket).await
         })?;
 
+        let (synced_s, synced_r) = tokio::sync::watch::channel(None);
+        let policy_doc = doc.clone();
         self.rt().spawn(async move {
+            let mut pending = PendingContent::new(policy_doc).await;
             while let Some(event) = stream.next().await {
                 match event {
                     Ok(event) => {
-                        if let Err(err) = cb.event(Arc::new(event.into())) {
-                            println!("cb error: {:?}", err);
+                        if let iroh::client::docs::LiveEvent::SyncFinished(e) = &event {
+                            if e.result.is_ok() && synced_s.borrow().is_none() {
+                                synced_s.send(Some(e.clone().into())).ok();
+                            }
+                        }
+                        for event in pending.on_event(event).await {
+                            if let Err(err) = cb.event(Arc::new(event)) {
+                                if err != CallbackError::Cancelled {
+                                    tracing::warn!(
+                                        "callback failed, ending subscription: {:?}",
+                                        err
+                                    );
+                                }
+                                return;
+                            }
                         }
                     }
                     Err(err) => {
-                        println!("rpc error: {:?}", err);
+                        tracing::error!("rpc error: {:?}", err);
                     }
                 }
             }
         });
 
+        if let Some(millis) = timeout_millis {
+            block_on(&self.rt(), wait_initial_sync(synced_r.clone(), millis))?;
+        }
+
         Ok(Arc::new(Doc {
             inner: doc,
+            client: self.sync_client.clone(),
             rt: self.rt().clone(),
+            state: self.state.clone(),
+            max_value_size: self.max_doc_value_size,
+            hard_links: self.hard_links.clone(),
+            key_locks: self.key_locks.clone(),
+            initial_sync: Some(synced_r),
+            writable: Arc::new(AtomicBool::new(false)),
         }))
     }
 
     /// List all the docs we have access to on this node.
     pub fn doc_list(&self) -> Result<Vec<NamespaceAndCapability>, IrohError> {
+        self.state.ensure_running()?;
         block_on(&self.rt(), async {
             let docs = self
                 .sync_client
@@ -94,6 +399,7 @@ impl IrohNode {
                 .await?
                 .map_ok(|(namespace, capability)| NamespaceAndCapability {
                     namespace: namespace.to_string(),
+                    has_secret: matches!(capability, iroh::docs::CapabilityKind::Write),
                     capability: capability.into(),
                 })
                 .try_collect::<Vec<_>>()
@@ -108,13 +414,21 @@ impl IrohNode {
     /// Returns None if the document cannot be found.
     pub fn doc_open(&self, id: String) -> Result<Option<Arc<Doc>>, IrohError> {
         let namespace_id = iroh::docs::NamespaceId::from_str(&id)?;
+        self.state.ensure_running()?;
         block_on(&self.rt(), async {
             let doc = self.sync_client.docs().open(namespace_id).await?;
 
             Ok(doc.map(|d| {
                 Arc::new(Doc {
                     inner: d,
+                    client: self.sync_client.clone(),
                     rt: self.rt().clone(),
+                    state: self.state.clone(),
+                    max_value_size: self.max_doc_value_size,
+                    hard_links: self.hard_links.clone(),
+                    key_locks: self.key_locks.clone(),
+                    initial_sync: None,
+                    writable: Arc::new(AtomicBool::new(false)),
                 })
             }))
         })
@@ -127,6 +441,7 @@ impl IrohNode {
     /// through garbage collection unless they are referenced from another document or tag.
     pub fn doc_drop(&self, doc_id: String) -> Result<(), IrohError> {
         let doc_id = iroh::docs::NamespaceId::from_str(&doc_id)?;
+        self.state.ensure_running()?;
         block_on(&self.rt(), async {
             self.sync_client
                 .docs()
@@ -135,6 +450,129 @@ impl IrohNode {
                 .map_err(IrohError::from)
         })
     }
+
+    /// Copy the entries of `source` into `target`, written by `author`.
+    ///
+    /// For each key, only the latest entry of `source` is considered, like
+    /// [`Query::single_latest_per_key`]. It is copied if `target` has no entry for the key, or if
+    /// the latest entry of `target` for the key, by any author, has an older timestamp. On equal
+    /// timestamps the entry in `target` is kept. A deletion in `target` counts as an entry: a
+    /// key deleted in `target` after it was written in `source` stays deleted. Deletions in
+    /// `source` are not merged: a key deleted in `source` keeps its entry in `target`.
+    ///
+    /// The copies reference the content hashes of the source entries, no content is imported
+    /// again. As they are new writes, they get the current time as their timestamp. Fails before
+    /// writing anything if `target` is read-only or a copied entry exceeds
+    /// [`NodeOptions::max_doc_value_size`](crate::NodeOptions::max_doc_value_size).
+    ///
+    /// Returns the number of entries copied.
+    pub fn doc_merge(
+        &self,
+        target: Arc<Doc>,
+        source: Arc<Doc>,
+        author: Arc<AuthorId>,
+    ) -> Result<u64, IrohError> {
+        self.state.ensure_running()?;
+        block_on(&self.rt(), async {
+            target.ensure_writable().await?;
+            let query = iroh::docs::store::Query::single_latest_per_key().build();
+            let mut entries = source.inner.get_many(query).await?;
+            let mut copies = Vec::new();
+            while let Some(entry) = entries.next().await {
+                let entry = entry?;
+                // a newer deletion in the target, an empty entry, wins as well
+                let query = iroh::docs::store::Query::single_latest_per_key()
+                    .key_exact(entry.key())
+                    .include_empty()
+                    .build();
+                if let Some(existing) = target.inner.get_one(query).await? {
+                    if existing.timestamp() >= entry.timestamp() {
+                        continue;
+                    }
+                }
+                target.check_value_size(entry.content_len())?;
+                copies.push(entry);
+            }
+
+            for entry in &copies {
+                target
+                    .inner
+                    .set_hash(
+                        author.0,
+                        entry.key().to_vec(),
+                        entry.content_hash(),
+                        entry.content_len(),
+                    )
+                    .await?;
+            }
+            Ok(copies.len() as u64)
+        })
+    }
+}
+
+/// Wait until the first successful sync has been recorded in `synced`, or error after `millis`.
+async fn wait_initial_sync(
+    mut synced: tokio::sync::watch::Receiver<Option<SyncEvent>>,
+    millis: u64,
+) -> Result<SyncEvent, IrohError> {
+    let wait = async {
+        synced
+            .wait_for(Option::is_some)
+            .await
+            .map(|event| event.clone().expect("checked by wait_for"))
+            .map_err(|_| anyhow::anyhow!("event stream closed before the initial sync finished"))
+    };
+    let event = tokio::time::timeout(Duration::from_millis(millis), wait)
+        .await
+        .map_err(|_| initial_sync_timeout(millis))??;
+    Ok(event)
+}
+
+fn initial_sync_timeout(millis: u64) -> anyhow::Error {
+    anyhow::anyhow!("initial sync did not finish within {millis}ms")
+}
+
+/// How much of a doc's content is available on this node, see [`Doc::availability`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocAvailability {
+    /// The combined size of the content referenced by the doc, in bytes.
+    pub total_bytes: u64,
+    /// The combined size of the content that is completely stored on this node, in bytes.
+    pub complete_bytes: u64,
+    /// The number of distinct blobs referenced by the doc.
+    pub total_blobs: u64,
+    /// The number of those blobs that are completely stored on this node.
+    pub complete_blobs: u64,
+}
+
+/// The aggregate progress of [`Doc::download_all`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DocDownloadProgress {
+    /// The number of wanted blobs that are complete on this node.
+    pub blobs_done: u64,
+    /// The number of distinct blobs wanted.
+    pub blobs_total: u64,
+    /// How much of the wanted content is on this node, in bytes.
+    pub bytes_done: u64,
+    /// The combined size of the wanted content, in bytes.
+    pub bytes_total: u64,
+}
+
+/// A key and value to write to a doc, see [`IrohNode::doc_create_and_share`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyValue {
+    /// The key of the entry.
+    pub key: Vec<u8>,
+    /// The value of the entry.
+    pub value: Vec<u8>,
+}
+
+/// A doc and a ticket to join it, see [`IrohNode::doc_create_and_share`].
+pub struct DocAndTicket {
+    /// The created doc.
+    pub doc: Arc<Doc>,
+    /// The ticket to join the doc.
+    pub ticket: String,
 }
 
 /// The namespace id and CapabilityKind (read/write) of the doc
@@ -143,13 +581,84 @@ pub struct NamespaceAndCapability {
     pub namespace: String,
     /// The capability you have for the doc (read/write)
     pub capability: CapabilityKind,
+    /// Whether this node holds the namespace secret of the doc.
+    ///
+    /// Only documents with a known secret can be exported with [`Doc::namespace_secret`].
+    /// In iroh the write capability of a document is its namespace secret, so this is always
+    /// the same as `capability` being [`CapabilityKind::Write`]. There is no write access
+    /// without the secret.
+    pub has_secret: bool,
+}
+
+/// Per document and key locks of a node, see [`Doc::set_bytes_if`].
+#[derive(Debug, Default)]
+pub(crate) struct KeyLocks {
+    locks: std::sync::Mutex<HashMap<LockedKey, Arc<tokio::sync::Mutex<()>>>>,
+}
+
+/// A key of a document, locked by [`KeyLocks`].
+type LockedKey = (iroh::docs::NamespaceId, Vec<u8>);
+
+impl KeyLocks {
+    /// Wait until `key` of `namespace` is free and lock it, until the guard is dropped.
+    async fn lock(&self, namespace: iroh::docs::NamespaceId, key: &[u8]) -> KeyLockGuard<'_> {
+        let id = (namespace, key.to_vec());
+        let lock = self
+            .locks
+            .lock()
+            .unwrap()
+            .entry(id.clone())
+            .or_default()
+            .clone();
+        let guard = lock.lock_owned().await;
+        KeyLockGuard {
+            locks: self,
+            id,
+            guard: Some(guard),
+        }
+    }
+}
+
+/// A key locked with [`KeyLocks::lock`].
+struct KeyLockGuard<'a> {
+    locks: &'a KeyLocks,
+    id: LockedKey,
+    guard: Option<tokio::sync::OwnedMutexGuard<()>>,
+}
+
+impl Drop for KeyLockGuard<'_> {
+    fn drop(&mut self) {
+        let mut locks = self.locks.locks.lock().unwrap();
+        self.guard.take();
+        // nobody else holds or waits for the lock, so forget it
+        if locks
+            .get(&self.id)
+            .is_some_and(|lock| Arc::strong_count(lock) == 1)
+        {
+            locks.remove(&self.id);
+        }
+    }
 }
 
 /// A representation of a mutable, synchronizable key-value store.
 #[derive(Clone)]
 pub struct Doc {
     pub(crate) inner: MemDoc,
+    /// Client of the node this doc was opened on, used to fetch the doc's content.
+    pub(crate) client: MemIroh,
     pub(crate) rt: tokio::runtime::Handle,
+    /// Running state of the node this doc was opened on.
+    pub(crate) state: NodeState,
+    /// See [`NodeOptions::max_doc_value_size`](crate::NodeOptions::max_doc_value_size).
+    pub(crate) max_value_size: Option<u64>,
+    /// Imports done with [`ImportStrategy::HardLink`] on the node this doc was opened on.
+    pub(crate) hard_links: Arc<HardLinkStore>,
+    /// First successful sync of a joined doc, recorded from the moment it was imported.
+    pub(crate) initial_sync: Option<tokio::sync::watch::Receiver<Option<SyncEvent>>>,
+    /// Whether this node is known to have write access, see [`Self::ensure_writable`].
+    pub(crate) writable: Arc<AtomicBool>,
+    /// Keys locked by [`Self::set_bytes_if`] on the node this doc was opened on.
+    pub(crate) key_locks: Arc<KeyLocks>,
 }
 
 impl Doc {
@@ -158,27 +667,115 @@ impl Doc {
         self.inner.id().to_string()
     }
 
+    /// Get the public key of the document's namespace.
+    pub fn namespace_public_key(&self) -> Result<Arc<PublicKey>, IrohError> {
+        let key = iroh::net::key::PublicKey::from_bytes(self.inner.id().as_bytes())?;
+        Ok(Arc::new(key.into()))
+    }
+
+    /// Get the 32 byte secret key of the document's namespace.
+    ///
+    /// The secret grants write access to the document, so handle it with care. If this node
+    /// only has read access to the document, this fails with an error for which
+    /// [`IrohError::is_permission_denied`] is true.
+    pub fn namespace_secret(&self) -> Result<Vec<u8>, IrohError> {
+        self.state.ensure_running()?;
+        block_on(&self.rt, async {
+            self.ensure_writable().await?;
+            let ticket = self
+                .inner
+                .share(ShareMode::Write.into(), AddrInfoOptions::Id.into())
+                .await?;
+            match ticket.capability {
+                iroh::docs::Capability::Write(secret) => Ok(secret.to_bytes().to_vec()),
+                iroh::docs::Capability::Read(_) => Err(anyhow::Error::from(PermissionDenied {
+                    namespace: self.inner.id(),
+                })
+                .into()),
+            }
+        })
+    }
+
     /// Close the document.
     pub fn close_me(&self) -> Result<(), IrohError> {
+        self.state.ensure_running()?;
         block_on(&self.rt, async {
             self.inner.close().await.map_err(IrohError::from)
         })
     }
 
     /// Set the content of a key to a byte array.
+    ///
+    /// Fails if `value` is larger than the node's `max_doc_value_size`.
     pub fn set_bytes(
         &self,
         author_id: &AuthorId,
         key: Vec<u8>,
         value: Vec<u8>,
     ) -> Result<Arc<Hash>, IrohError> {
+        self.state.ensure_running()?;
+        self.check_value_size(value.len() as u64)?;
         block_on(&self.rt, async {
+            self.ensure_writable().await?;
             let hash = self.inner.set_bytes(author_id.0, key, value).await?;
             Ok(Arc::new(Hash(hash)))
         })
     }
 
+    /// Set the content of a string key to a byte array.
+    ///
+    /// The key is stored as its UTF-8 bytes, so this is the same as calling [`Self::set_bytes`]
+    /// with the encoded string.
+    pub fn set_bytes_str(
+        &self,
+        author_id: &AuthorId,
+        key: String,
+        value: Vec<u8>,
+    ) -> Result<Arc<Hash>, IrohError> {
+        self.set_bytes(author_id, key.into_bytes(), value)
+    }
+
+    /// Set the content of a key to a byte array, if the key's current content is `expected`.
+    ///
+    /// The content is only written if the hash of the latest entry for `key`, of any author,
+    /// equals `expected`, or if `expected` is `None` and there is no entry for `key`. Returns
+    /// the hash of the new content, or `None` if the content was not written.
+    ///
+    /// Calls of this method for the same document and key on this node are serialized, so
+    /// the check and the write of one call are not interleaved with another call's. Other
+    /// writes are not: a plain [`Self::set_bytes`] on this node, or an entry arriving from a
+    /// remote peer between the check and the write, is not detected. This is not a
+    /// distributed lock.
+    pub fn set_bytes_if(
+        &self,
+        author_id: &AuthorId,
+        key: Vec<u8>,
+        value: Vec<u8>,
+        expected: Option<Arc<Hash>>,
+    ) -> Result<Option<Arc<Hash>>, IrohError> {
+        self.state.ensure_running()?;
+        self.check_value_size(value.len() as u64)?;
+        block_on(&self.rt, async {
+            self.ensure_writable().await?;
+            let _guard = self.key_locks.lock(self.inner.id(), &key).await;
+            let query = Query::single_latest_per_key_exact(key.clone());
+            let current = self.inner.get_one(query.0).await?;
+            let matches = match (current, expected) {
+                (None, None) => true,
+                (Some(entry), Some(expected)) => entry.content_hash() == expected.0,
+                _ => false,
+            };
+            if !matches {
+                return Ok(None);
+            }
+            let hash = self.inner.set_bytes(author_id.0, key, value).await?;
+            Ok(Some(Arc::new(Hash(hash))))
+        })
+    }
+
     /// Set an entries on the doc via its key, hash, and size.
+    ///
+    /// Fails if `size` is larger than the node's `max_doc_value_size`.
     pub fn set_hash(
         &self,
         author_id: Arc<AuthorId>,
@@ -186,13 +783,19 @@ impl Doc {
         hash: Arc<Hash>,
         size: u64,
     ) -> Result<(), IrohError> {
+        self.state.ensure_running()?;
+        self.check_value_size(size)?;
         block_on(&self.rt, async {
+            self.ensure_writable().await?;
             self.inner.set_hash(author_id.0, key, hash.0, size).await?;
             Ok(())
         })
     }
 
     /// Add an entry from an absolute file path
+    ///
+    /// If `strategy` is set, it decides how the file gets into the store instead of
+    /// `in_place`, see [`ImportStrategy`].
     pub fn import_file(
         &self,
         author: Arc<AuthorId>,
@@ -200,21 +803,155 @@ impl Doc {
         path: String,
         in_place: bool,
         cb: Option<Arc<dyn DocImportFileCallback>>,
+        strategy: Option<ImportStrategy>,
     ) -> Result<(), IrohError> {
+        let strategy = ImportStrategy::resolve(strategy, in_place);
+        self.state.ensure_running()?;
+        block_on(&self.rt, async {
+            self.import_file_inner(&author, key, path, strategy, cb)
+                .await?;
+            Ok(())
+        })
+    }
+
+    /// Add an entry from an absolute file path, pulling the progress events from a stream.
+    ///
+    /// Like [`Self::import_file`], but instead of passing the events to a callback the import
+    /// runs in the background and the events are read with [`DocImportProgressStream::next`].
+    /// Dropping the stream cancels the import.
+    pub fn import_file_stream(
+        self: Arc<Self>,
+        author: Arc<AuthorId>,
+        key: Vec<u8>,
+        path: String,
+        in_place: bool,
+        strategy: Option<ImportStrategy>,
+    ) -> Result<Arc<DocImportProgressStream>, IrohError> {
+        self.state.ensure_running()?;
+        let rt = self.rt.clone();
+        let events = ProgressReceiver::spawn(&rt, move |cb| {
+            self.import_file(author, key, path, in_place, Some(cb), strategy)
+        });
+        Ok(Arc::new(DocImportProgressStream(events)))
+    }
+
+    /// Import a file and return the entry pointing at it.
+    ///
+    /// Like [`Self::import_file`], but returns the entry that was set for `key` once the
+    /// import is done. Returns `None` if the callback cancelled the import before the entry
+    /// was set.
+    pub fn import_blob(
+        &self,
+        author: Arc<AuthorId>,
+        key: Vec<u8>,
+        path: String,
+        in_place: bool,
+        cb: Option<Arc<dyn DocImportFileCallback>>,
+        strategy: Option<ImportStrategy>,
+    ) -> Result<Option<Arc<Entry>>, IrohError> {
+        let strategy = ImportStrategy::resolve(strategy, in_place);
+        self.state.ensure_running()?;
         block_on(&self.rt, async {
+            if !self
+                .import_file_inner(&author, key.clone(), path, strategy, cb)
+                .await?
+            {
+                return Ok(None);
+            }
+            let entry = self
+                .inner
+                .get_exact(author.0, key, false)
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("imported entry not found"))?;
+            Ok(Some(Arc::new(entry.into())))
+        })
+    }
+
+    /// Run an import, returns whether it ran to completion.
+    async fn import_file_inner(
+        &self,
+        author: &AuthorId,
+        key: Vec<u8>,
+        path: String,
+        strategy: ImportStrategy,
+        cb: Option<Arc<dyn DocImportFileCallback>>,
+    ) -> Result<bool, IrohError> {
+        self.ensure_writable().await?;
+        if self.max_value_size.is_some() {
+            let size = tokio::fs::metadata(&path)
+                .await
+                .map_err(anyhow::Error::from)?
+                .len();
+            self.check_value_size(size)?;
+        }
+        let source = PathBuf::from(path);
+        let (path, in_place, staging) = self.hard_links.prepare(source.clone(), strategy);
+        let mut hashes = Vec::new();
+        let res: Result<bool, IrohError> = async {
             let mut stream = self
                 .inner
-                .import_file(author.0, Bytes::from(key), PathBuf::from(path), in_place)
+                .import_file(author.0, Bytes::from(key), path.clone(), in_place)
                 .await?;
 
             while let Some(progress) = stream.next().await {
                 let progress = progress?;
+                if let iroh::client::docs::ImportProgress::IngestDone { hash, .. } = &progress {
+                    hashes.push(*hash);
+                }
                 if let Some(ref cb) = cb {
-                    cb.progress(Arc::new(progress.into()))?;
+                    let mut progress: DocImportProgress = progress.into();
+                    // report a staged file by its source path
+                    if let DocImportProgress::Found(ref mut found) = progress {
+                        if found.name == path.display().to_string() {
+                            found.name = source.display().to_string();
+                        }
+                    }
+                    if !callback_continue(cb.progress(Arc::new(progress)))? {
+                        return Ok(false);
+                    }
                 }
             }
-            Ok(())
-        })
+            Ok(true)
+        }
+        .await;
+        if let Some(staging) = staging {
+            self.hard_links.record(staging, hashes)?;
+        }
+        res
+    }
+
+    /// Fail with [`PermissionDenied`] if this node only has read access to the document.
+    ///
+    /// A replica never loses write access once it has it, so that is cached and later writes
+    /// skip the lookup. Read access is not cached, since importing a write ticket upgrades it.
+    async fn ensure_writable(&self) -> Result<(), IrohError> {
+        if self.writable.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        let id = self.inner.id();
+        let mut docs = self.client.docs().list().await?;
+        while let Some(doc) = docs.next().await {
+            let (namespace, capability) = doc?;
+            if namespace == id {
+                if matches!(capability, iroh::docs::CapabilityKind::Read) {
+                    return Err(anyhow::Error::from(PermissionDenied { namespace }).into());
+                }
+                self.writable.store(true, Ordering::Relaxed);
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Fail if an entry with content of `size` bytes exceeds the node's `max_doc_value_size`.
+    fn check_value_size(&self, size: u64) -> Result<(), IrohError> {
+        match self.max_value_size {
+            Some(max) if size > max => Err(anyhow::anyhow!(
+                "entry content of {size} bytes exceeds the maximum doc value size of {max} bytes"
+            )
+            .into()),
+            _ => Ok(()),
+        }
     }
 
     /// Export an entry as a file to a given absolute path
@@ -224,6 +961,7 @@ impl Doc {
         path: String,
         cb: Option<Arc<dyn DocExportFileCallback>>,
     ) -> Result<(), IrohError> {
+        self.state.ensure_running()?;
         block_on(&self.rt, async {
             let mut stream = self
                 .inner
@@ -237,7 +975,9 @@ impl Doc {
             while let Some(progress) = stream.next().await {
                 let progress = progress?;
                 if let Some(ref cb) = cb {
-                    cb.progress(Arc::new(progress.into()))?;
+                    if !callback_continue(cb.progress(Arc::new(progress.into())))? {
+                        break;
+                    }
                 }
             }
             Ok(())
@@ -251,13 +991,49 @@ impl Doc {
     ///
     /// Returns the number of entries deleted.
     pub fn del(&self, author_id: Arc<AuthorId>, prefix: Vec<u8>) -> Result<u64, IrohError> {
+        self.state.ensure_running()?;
         block_on(&self.rt, async {
+            self.ensure_writable().await?;
             let num_del = self.inner.del(author_id.0, prefix).await?;
 
             u64::try_from(num_del).map_err(|e| anyhow::Error::from(e).into())
         })
     }
 
+    /// Delete the entry for the exact `key` and `author`.
+    ///
+    /// Unlike [`Self::del`], this never clears other entries. Because deletions in a document
+    /// always apply to all keys starting with the deleted key, this fails if the author has
+    /// other entries whose key starts with `key`, instead of deleting those as well.
+    ///
+    /// Returns whether an entry was deleted.
+    pub fn del_exact(&self, author_id: Arc<AuthorId>, key: Vec<u8>) -> Result<bool, IrohError> {
+        self.state.ensure_running()?;
+        block_on(&self.rt, async {
+            self.ensure_writable().await?;
+            let query = iroh::docs::store::Query::author(author_id.0)
+                .key_prefix(key.clone())
+                .build();
+            let mut entries = self.inner.get_many(query).await?;
+            let mut found = false;
+            while let Some(entry) = entries.next().await {
+                let entry = entry?;
+                if entry.key() != key.as_slice() {
+                    return Err(anyhow::anyhow!(
+                        "cannot delete exact key: other entries of this author start with the same key"
+                    )
+                    .into());
+                }
+                found = true;
+            }
+            if !found {
+                return Ok(false);
+            }
+            self.inner.del(author_id.0, key).await?;
+            Ok(true)
+        })
+    }
+
     /// Get an entry for a key and author.
     pub fn get_exact(
         &self,
@@ -265,6 +1041,7 @@ impl Doc {
         key: Vec<u8>,
         include_empty: bool,
     ) -> Result<Option<Arc<Entry>>, IrohError> {
+        self.state.ensure_running()?;
         block_on(&self.rt, async {
             self.inner
                 .get_exact(author.0, key, include_empty)
@@ -274,11 +1051,24 @@ impl Doc {
         })
     }
 
+    /// Get an entry for a string key and author, see [`Self::get_exact`].
+    ///
+    /// The key is looked up by its UTF-8 bytes.
+    pub fn get_exact_str(
+        &self,
+        author: Arc<AuthorId>,
+        key: String,
+        include_empty: bool,
+    ) -> Result<Option<Arc<Entry>>, IrohError> {
+        self.get_exact(author, key.into_bytes(), include_empty)
+    }
+
     /// Get entries.
     ///
     /// Note: this allocates for each `Entry`, if you have many `Entry`s this may be a prohibitively large list.
     /// Please file an [issue](https://github.com/n0-computer/iroh-ffi/issues/new) if you run into this issue
     pub fn get_many(&self, query: Arc<Query>) -> Result<Vec<Arc<Entry>>, IrohError> {
+        self.state.ensure_running()?;
         block_on(&self.rt, async {
             let entries = self
                 .inner
@@ -291,8 +1081,31 @@ impl Doc {
         })
     }
 
+    /// List the distinct authors that have written to this document, in ascending order.
+    ///
+    /// Includes the authors whose entries have all been deleted, since a deletion is itself an
+    /// empty entry written by the deleting author. The store has no author index, so this
+    /// scans the entries of the replica, without loading their content.
+    pub fn authors(&self) -> Result<Vec<Arc<AuthorId>>, IrohError> {
+        self.state.ensure_running()?;
+        block_on(&self.rt, async {
+            let query = iroh::docs::store::Query::all().include_empty().build();
+            let mut entries = self.inner.get_many(query).await?;
+            let mut authors: Vec<Arc<AuthorId>> = Vec::new();
+            while let Some(entry) = entries.try_next().await? {
+                // entries are sorted by author, so duplicates are adjacent
+                let author = entry.author();
+                if authors.last().map(|last| last.0) != Some(author) {
+                    authors.push(Arc::new(AuthorId(author)));
+                }
+            }
+            Ok(authors)
+        })
+    }
+
     /// Get the latest entry for a key and author.
     pub fn get_one(&self, query: Arc<Query>) -> Result<Option<Arc<Entry>>, IrohError> {
+        self.state.ensure_running()?;
         block_on(&self.rt, async {
             let res = self
                 .inner
@@ -303,30 +1116,126 @@ impl Doc {
         })
     }
 
-    /// Share this document with peers over a ticket.
-    pub fn share(
-        &self,
-        mode: ShareMode,
-        addr_options: AddrInfoOptions,
-    ) -> Result<String, IrohError> {
+    /// How much of the content referenced by this document is available on this node.
+    ///
+    /// Counts the latest entry of every key, like [`Query::single_latest_per_key`]. Content
+    /// referenced by several entries is counted once. Partially downloaded content counts
+    /// towards the total only, not towards the complete bytes. The byte counts are the sizes
+    /// the entries announce and stop at `u64::MAX` instead of overflowing.
+    pub fn availability(&self) -> Result<DocAvailability, IrohError> {
+        self.state.ensure_running()?;
         block_on(&self.rt, async {
-            let res = self
-                .inner
-                .share(mode.into(), addr_options.into())
-                .await
-                .map(|ticket| ticket.to_string())?;
-            Ok(res)
+            let query = iroh::docs::store::Query::single_latest_per_key().build();
+            let mut entries = self.inner.get_many(query).await?;
+            let mut sizes = HashMap::new();
+            while let Some(entry) = entries.try_next().await? {
+                sizes.insert(entry.content_hash(), entry.content_len());
+            }
+            let mut availability = DocAvailability {
+                total_bytes: 0,
+                complete_bytes: 0,
+                total_blobs: sizes.len() as u64,
+                complete_blobs: 0,
+            };
+            for (hash, size) in sizes {
+                // sizes are announced by peers, so add them up without overflowing
+                availability.total_bytes = availability.total_bytes.saturating_add(size);
+                if let iroh::client::blobs::BlobStatus::Complete { .. } =
+                    self.client.blobs().status(hash).await?
+                {
+                    availability.complete_bytes = availability.complete_bytes.saturating_add(size);
+                    availability.complete_blobs += 1;
+                }
+            }
+            Ok(availability)
         })
     }
 
-    /// Start to sync this document with a list of peers.
-    pub fn start_sync(&self, peers: Vec<Arc<NodeAddr>>) -> Result<(), IrohError> {
+    /// Read the content of multiple entries of this document, in the order of `entries`.
+    ///
+    /// To avoid unbounded allocations, this fails without reading anything if the combined
+    /// content length of the entries exceeds [`CONTENT_BYTES_MANY_MAX_SIZE`] (16 MiB). Fails
+    /// if the content of any of the entries is not available on this node.
+    pub fn content_bytes_many(&self, entries: Vec<Arc<Entry>>) -> Result<Vec<Vec<u8>>, IrohError> {
+        // the lengths come from the entries, which peers control, so they may overflow
+        let total = entries
+            .iter()
+            .try_fold(0u64, |total, e| total.checked_add(e.content_len()))
+            .ok_or_else(|| anyhow::anyhow!("combined content length overflows"))?;
+        if total > CONTENT_BYTES_MANY_MAX_SIZE {
+            return Err(anyhow::anyhow!(
+                "combined content length of {total} bytes exceeds the maximum of {CONTENT_BYTES_MANY_MAX_SIZE} bytes"
+            )
+            .into());
+        }
+        self.state.ensure_running()?;
+        block_on(&self.rt, async {
+            let mut contents = Vec::with_capacity(entries.len());
+            for entry in entries {
+                let hash = entry.0.content_hash();
+                let content = entry.0.content_bytes(&self.inner).await;
+                let content = check_available(&self.client, hash, content).await;
+                let content = content.map_err(|err| {
+                    err.context(format!(
+                        "content {hash} of entry {} is not available locally",
+                        String::from_utf8_lossy(entry.0.key())
+                    ))
+                })?;
+                contents.push(content.to_vec());
+            }
+            Ok(contents)
+        })
+    }
+
+    /// Get the latest entry for a key, regardless of its author.
+    ///
+    /// Shortcut for [`Self::get_one`] with [`Query::single_latest_per_key_exact`].
+    pub fn get_latest(&self, key: Vec<u8>) -> Result<Option<Arc<Entry>>, IrohError> {
+        self.get_one(Arc::new(Query::single_latest_per_key_exact(key)))
+    }
+
+    /// Share this document with peers over a ticket.
+    pub fn share(
+        &self,
+        mode: ShareMode,
+        addr_options: AddrInfoOptions,
+    ) -> Result<String, IrohError> {
+        self.state.ensure_running()?;
+        block_on(&self.rt, async {
+            let res = self
+                .inner
+                .share(mode.into(), addr_options.into())
+                .await
+                .map(|ticket| ticket.to_string())?;
+            Ok(res)
+        })
+    }
+
+    /// Share this document with peers over a ticket that is as small as possible.
+    ///
+    /// The ticket only contains the node id and relay url of this node, leaving out the
+    /// direct addresses. Use [`ticket_qr_payload`](crate::ticket_qr_payload) to encode it
+    /// for a QR code.
+    pub fn share_compact(&self, mode: ShareMode) -> Result<String, IrohError> {
+        self.share(mode, AddrInfoOptions::Relay)
+    }
+
+    /// Start to sync this document with a list of peers.
+    ///
+    /// `path_preference` picks which of the peers' addresses are used, see [`PathPreference`].
+    pub fn start_sync(
+        &self,
+        peers: Vec<Arc<NodeAddr>>,
+        path_preference: Option<PathPreference>,
+    ) -> Result<(), IrohError> {
+        let path_preference = path_preference.unwrap_or(PathPreference::Any);
+        self.state.ensure_running()?;
         block_on(&self.rt, async {
             self.inner
                 .start_sync(
                     peers
                         .into_iter()
-                        .map(|p| (*p).clone().try_into())
+                        .map(|p| Ok(path_preference.apply((*p).clone().try_into()?)))
                         .collect::<Result<Vec<_>, IrohError>>()?,
                 )
                 .await?;
@@ -334,8 +1243,133 @@ impl Doc {
         })
     }
 
+    /// Start to sync this document with a list of peers, retrying failed syncs.
+    ///
+    /// Like [`Self::start_sync`], this returns once the sync was started. Whenever a sync with
+    /// one of `peers` fails, it is started again for that peer after a backoff, until the
+    /// sync succeeds or `policy.max_attempts` syncs with the peer failed.
+    ///
+    /// `cb` receives a `LiveEvent::SyncRetry` event before each retry, and the
+    /// `LiveEvent::SyncFinished` event of the last sync with each peer: either the first
+    /// successful one, or the failure that exhausted the attempts. Other events of the
+    /// document are not passed to `cb`, use [`Self::subscribe`] for those. Returning an error
+    /// from the callback stops further retries.
+    pub fn start_sync_with_retry(
+        &self,
+        peers: Vec<Arc<NodeAddr>>,
+        policy: SyncRetryPolicy,
+        cb: Arc<dyn SubscribeCallback>,
+    ) -> Result<(), IrohError> {
+        let peers = peers
+            .into_iter()
+            .map(|p| (*p).clone().try_into())
+            .collect::<Result<Vec<iroh::net::endpoint::NodeAddr>, IrohError>>()?;
+        self.state.ensure_running()?;
+        let mut events = block_on(&self.rt, async {
+            let events = self.inner.subscribe().await?;
+            self.inner.start_sync(peers.clone()).await?;
+            anyhow::Ok(events)
+        })?;
+
+        let doc = self.inner.clone();
+        let rt = self.rt.clone();
+        self.rt.spawn(async move {
+            let mut pending: HashMap<_, _> = peers
+                .into_iter()
+                .map(|addr| (addr.node_id, (addr, 1u32)))
+                .collect();
+            while !pending.is_empty() {
+                let Some(event) = events.next().await else {
+                    break;
+                };
+                let sync = match event {
+                    Ok(iroh::client::docs::LiveEvent::SyncFinished(sync)) => sync,
+                    Ok(_) => continue,
+                    Err(err) => {
+                        tracing::error!("rpc error: {:?}", err);
+                        break;
+                    }
+                };
+                let Some((addr, attempt)) = pending.get_mut(&sync.peer) else {
+                    continue;
+                };
+                let event = match &sync.result {
+                    Err(reason) if *attempt < policy.max_attempts => {
+                        let delay = policy.backoff(*attempt);
+                        let event = LiveEvent::SyncRetry(SyncRetryEvent {
+                            peer: Arc::new(sync.peer.into()),
+                            attempt: *attempt,
+                            delay_millis: delay.as_millis() as u64,
+                            reason: reason.clone(),
+                        });
+                        *attempt += 1;
+                        let doc = doc.clone();
+                        let addr = addr.clone();
+                        rt.spawn(async move {
+                            tokio::time::sleep(delay).await;
+                            if let Err(err) = doc.start_sync(vec![addr]).await {
+                                tracing::warn!("failed to restart sync: {:?}", err);
+                            }
+                        });
+                        event
+                    }
+                    _ => {
+                        pending.remove(&sync.peer);
+                        LiveEvent::SyncFinished(sync.into())
+                    }
+                };
+                if let Err(err) = cb.event(Arc::new(event)) {
+                    if err != CallbackError::Cancelled {
+                        tracing::warn!("callback failed, ending subscription: {:?}", err);
+                    }
+                    break;
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Wait for the first successful sync of this document with a peer.
+    ///
+    /// For docs returned by `doc_join` and `doc_join_and_subscribe`, syncs are recorded from the
+    /// moment the doc was imported, so this also returns a sync that finished before it was
+    /// called. For other docs, only syncs finishing after the call are seen.
+    ///
+    /// Errors if no sync finished successfully within `timeout_millis`.
+    pub fn await_initial_sync(&self, timeout_millis: u64) -> Result<SyncEvent, IrohError> {
+        self.state.ensure_running()?;
+        block_on(&self.rt, async {
+            if let Some(synced) = &self.initial_sync {
+                return wait_initial_sync(synced.clone(), timeout_millis).await;
+            }
+
+            let mut stream = self.inner.subscribe().await?;
+            let wait = async {
+                while let Some(event) = stream.next().await {
+                    match event? {
+                        iroh::client::docs::LiveEvent::SyncFinished(event)
+                            if event.result.is_ok() =>
+                        {
+                            return Ok(event.into());
+                        }
+                        _ => {}
+                    }
+                }
+                Err(anyhow::anyhow!(
+                    "event stream closed before the initial sync finished"
+                ))
+            };
+            let event = tokio::time::timeout(Duration::from_millis(timeout_millis), wait)
+                .await
+                .map_err(|_| initial_sync_timeout(timeout_millis))??;
+            Ok(event)
+        })
+    }
+
     /// Stop the live sync for this document.
     pub fn leave(&self) -> Result<(), IrohError> {
+        self.state.ensure_running()?;
         block_on(&self.rt, async {
             self.inner.leave().await?;
             Ok(())
@@ -343,29 +1377,174 @@ impl Doc {
     }
 
     /// Subscribe to events for this document.
-    pub fn subscribe(&self, cb: Arc<dyn SubscribeCallback>) -> Result<(), IrohError> {
+    ///
+    /// Events are queued in a bounded buffer until the callback handles them, see
+    /// [`SubscribeBufferOptions`]. By default up to [`DEFAULT_SUBSCRIBE_BUFFER_CAPACITY`]
+    /// events are buffered and no events are dropped: if the callback falls behind, reading
+    /// events waits for it. Dropping the oldest events instead is opt-in, with
+    /// [`OverflowPolicy::DropOldest`].
+    pub fn subscribe(
+        &self,
+        cb: Arc<dyn SubscribeCallback>,
+        buffer: Option<SubscribeBufferOptions>,
+    ) -> Result<(), IrohError> {
+        self.subscribe_inner(None, cb, buffer.unwrap_or_default())
+    }
+
+    /// Subscribe to events of the given types for this document.
+    ///
+    /// Only events whose type is in `types` are passed to the callback, which avoids calling
+    /// into the callback for events the app ignores anyway. Filtered out events do not take
+    /// up space in the buffer, see [`Self::subscribe`].
+    pub fn subscribe_filtered(
+        &self,
+        types: Vec<LiveEventType>,
+        cb: Arc<dyn SubscribeCallback>,
+        buffer: Option<SubscribeBufferOptions>,
+    ) -> Result<(), IrohError> {
+        self.subscribe_inner(Some(types), cb, buffer.unwrap_or_default())
+    }
+
+    /// Deliver the entries changed since `since_micros`, then follow live changes.
+    ///
+    /// First `cb.entry` is called for each entry with a timestamp after `since_micros`, in
+    /// ascending timestamp order. Deletions are reported in that order as well, with
+    /// `cb.deleted` and the empty entry that marks them: its key is the deleted key or prefix.
+    /// Then `cb.caught_up` is called once to mark the boundary, and afterwards `cb.event` is
+    /// called for each live event, like with [`Self::subscribe`].
+    ///
+    /// iroh has no index by timestamp, so all entries of the document are read to find the
+    /// changed ones. Only those are kept in memory until they are delivered.
+    ///
+    /// The live subscription starts before the entries are read, so no change is missed in
+    /// between. A change made while the entries are read may be delivered both as an entry and
+    /// as a live event. If the callback returns an error, following ends, see `CallbackError`.
+    pub fn follow_since(
+        &self,
+        since_micros: u64,
+        cb: Arc<dyn FollowCallback>,
+    ) -> Result<(), IrohError> {
+        self.state.ensure_running()?;
+        let mut sub = block_on(&self.rt, self.inner.subscribe())?;
+        let doc = self.inner.clone();
+        self.rt.spawn(async move {
+            let entries = async {
+                let mut entries = doc
+                    .get_many(iroh::docs::store::Query::all().include_empty().build())
+                    .await?
+                    .try_filter(|entry| futures::future::ready(entry.timestamp() > since_micros))
+                    .try_collect::<Vec<_>>()
+                    .await?;
+                entries.sort_by_key(|entry| entry.timestamp());
+                anyhow::Ok(entries)
+            };
+            let entries = match entries.await {
+                Ok(entries) => entries,
+                Err(err) => {
+                    tracing::error!("rpc error: {:?}", err);
+                    return;
+                }
+            };
+
+            let end = |err: CallbackError| {
+                if err != CallbackError::Cancelled {
+                    tracing::warn!("callback failed, ending subscription: {:?}", err);
+                }
+            };
+            for entry in entries {
+                // an empty entry marks a deletion
+                let res = if entry.content_len() == 0 {
+                    cb.deleted(Arc::new(entry.into()))
+                } else {
+                    cb.entry(Arc::new(entry.into()))
+                };
+                if let Err(err) = res {
+                    return end(err);
+                }
+            }
+            if let Err(err) = cb.caught_up() {
+                return end(err);
+            }
+            let mut pending = PendingContent::new(doc).await;
+            while let Some(event) = sub.next().await {
+                match event {
+                    Ok(event) => {
+                        for event in pending.on_event(event).await {
+                            if let Err(err) = cb.event(Arc::new(event)) {
+                                return end(err);
+                            }
+                        }
+                    }
+                    Err(err) => {
+                        tracing::error!("rpc error: {:?}", err);
+                    }
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    fn subscribe_inner(
+        &self,
+        types: Option<Vec<LiveEventType>>,
+        cb: Arc<dyn SubscribeCallback>,
+        buffer: SubscribeBufferOptions,
+    ) -> Result<(), IrohError> {
+        if buffer.capacity == 0 {
+            return Err(anyhow::anyhow!("subscribe buffer capacity must be at least 1").into());
+        }
+        if buffer.capacity > MAX_SUBSCRIBE_BUFFER_CAPACITY {
+            return Err(anyhow::anyhow!(
+                "subscribe buffer capacity must be at most {MAX_SUBSCRIBE_BUFFER_CAPACITY}"
+            )
+            .into());
+        }
+        self.state.ensure_running()?;
+        let mut sub = block_on(&self.rt, self.inner.subscribe())?;
+        let (sender, mut receiver) = EventBuffer::new(buffer);
         let client = self.inner.clone();
+        // read the events into the buffer, until the stream ends or the callback side is gone
         self.rt.spawn(async move {
-            let mut sub = client.subscribe().await.unwrap();
+            let mut pending = PendingContent::new(client).await;
             while let Some(event) = sub.next().await {
                 match event {
                     Ok(event) => {
-                        if let Err(err) = cb.event(Arc::new(event.into())) {
-                            println!("cb error: {:?}", err);
+                        for event in pending.on_event(event).await {
+                            if let Some(ref types) = types {
+                                if !types.contains(&event.r#type()) {
+                                    continue;
+                                }
+                            }
+                            if !sender.send(Arc::new(event)).await {
+                                return;
+                            }
                         }
                     }
                     Err(err) => {
-                        println!("rpc error: {:?}", err);
+                        tracing::error!("rpc error: {:?}", err);
                     }
                 }
             }
         });
+        // pass the buffered events to the callback
+        self.rt.spawn(async move {
+            while let Some(event) = receiver.recv().await {
+                if let Err(err) = cb.event(event) {
+                    if err != CallbackError::Cancelled {
+                        tracing::warn!("callback failed, ending subscription: {:?}", err);
+                    }
+                    return;
+                }
+            }
+        });
 
         Ok(())
     }
 
     /// Get status info for this document
     pub fn status(&self) -> Result<OpenState, IrohError> {
+        self.state.ensure_running()?;
         block_on(&self.rt, async {
             let res = self.inner.status().await.map(|o| o.into())?;
             Ok(res)
@@ -374,6 +1553,7 @@ impl Doc {
 
     /// Set the download policy for this document
     pub fn set_download_policy(&self, policy: Arc<DownloadPolicy>) -> Result<(), IrohError> {
+        self.state.ensure_running()?;
         block_on(&self.rt, async {
             self.inner
                 .set_download_policy((*policy).clone().into())
@@ -384,6 +1564,7 @@ impl Doc {
 
     /// Get the download policy for this document
     pub fn get_download_policy(&self) -> Result<Arc<DownloadPolicy>, IrohError> {
+        self.state.ensure_running()?;
         block_on(&self.rt, async {
             let res = self
                 .inner
@@ -393,6 +1574,162 @@ impl Doc {
             Ok(res)
         })
     }
+
+    /// Download the content of all current entries of this document from its sync peers.
+    ///
+    /// This is meant to make a document available offline. Only the content the document's
+    /// download policy asks for is downloaded, unless `ignore_download_policy` is `true`.
+    /// Content that is already complete locally is skipped. The blobs are downloaded one after
+    /// the other.
+    ///
+    /// `cb.blob_progress` receives the progress events of each download as they come, their
+    /// sizes and offsets refer to the blob currently downloaded. `cb.progress` receives the
+    /// aggregate progress across all wanted content: once at the start, after each progress
+    /// event and after each blob. The totals are the content lengths of the entries, content
+    /// already complete locally counts as done from the start.
+    ///
+    /// Returning `CallbackError::Cancelled` from the callback stops the remaining downloads
+    /// and returns successfully, any other error fails the call with that error.
+    ///
+    /// The downloaded content is protected from garbage collection by the document entries
+    /// referencing it, no tags are left behind.
+    pub fn download_all(
+        &self,
+        ignore_download_policy: bool,
+        cb: Arc<dyn DocDownloadAllCallback>,
+    ) -> Result<(), IrohError> {
+        self.state.ensure_running()?;
+        block_on(&self.rt, async {
+            let peers = self.inner.get_sync_peers().await?.unwrap_or_default();
+            if peers.is_empty() {
+                return Err(anyhow::anyhow!("no sync peers known for this document").into());
+            }
+            let nodes = peers
+                .iter()
+                .map(|peer| {
+                    let node_id = iroh::net::key::PublicKey::from_bytes(peer)?;
+                    Ok(iroh::net::endpoint::NodeAddr::new(node_id))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            let policy = if ignore_download_policy {
+                None
+            } else {
+                Some(self.inner.get_download_policy().await?)
+            };
+
+            let query = iroh::docs::store::Query::single_latest_per_key().build();
+            let mut blobs = self
+                .inner
+                .get_many(query)
+                .await?
+                .try_filter(|entry| {
+                    let wanted = policy
+                        .as_ref()
+                        .map_or(true, |policy| policy_wants(policy, entry.key()));
+                    futures::future::ready(wanted)
+                })
+                .map_ok(|entry| (entry.content_hash(), entry.content_len()))
+                .try_collect::<Vec<_>>()
+                .await?;
+            blobs.sort();
+            blobs.dedup_by_key(|(hash, _)| *hash);
+
+            let mut pending = Vec::new();
+            let mut progress = DocDownloadProgress {
+                blobs_total: blobs.len() as u64,
+                ..Default::default()
+            };
+            for (hash, size) in blobs {
+                // sizes are announced by peers, so add them up without overflowing
+                progress.bytes_total = progress.bytes_total.saturating_add(size);
+                if matches!(
+                    self.client.blobs().status(hash).await?,
+                    iroh::client::blobs::BlobStatus::Complete { .. }
+                ) {
+                    progress.blobs_done += 1;
+                    progress.bytes_done = progress.bytes_done.saturating_add(size);
+                } else {
+                    pending.push((hash, size));
+                }
+            }
+            if !callback_continue(cb.progress(progress.clone()))? {
+                return Ok(());
+            }
+
+            for (hash, size) in pending {
+                // iroh needs a tag for a download, the doc entry protects the content after it
+                let tag = iroh::blobs::Tag(Bytes::from(format!("{DOWNLOAD_ALL_TAG_PREFIX}{hash}")));
+                let opts = iroh::client::blobs::DownloadOptions {
+                    format: iroh::blobs::BlobFormat::Raw,
+                    nodes: nodes.clone(),
+                    tag: iroh::blobs::util::SetTagOption::Named(tag.clone()),
+                    mode: iroh::client::blobs::DownloadMode::Queued,
+                };
+                let res = self
+                    .download_one(hash, size, opts, &mut progress, &cb)
+                    .await;
+                if let Err(err) = self.client.tags().delete(tag).await {
+                    tracing::warn!("failed to remove download tag of {hash}: {err:?}");
+                }
+                if !res? {
+                    break;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Download a single blob of `size` bytes for [`Self::download_all`], returning `false` if
+    /// `cb` cancelled.
+    ///
+    /// `progress` holds the aggregate progress before this blob and is advanced past it.
+    async fn download_one(
+        &self,
+        hash: iroh::blobs::Hash,
+        size: u64,
+        opts: iroh::client::blobs::DownloadOptions,
+        progress: &mut DocDownloadProgress,
+        cb: &Arc<dyn DocDownloadAllCallback>,
+    ) -> Result<bool, IrohError> {
+        let mut stream = self.client.blobs().download_with_opts(hash, opts).await?;
+        while let Some(event) = stream.next().await {
+            let event = event?;
+            let offset = match event {
+                iroh::blobs::get::db::DownloadProgress::Progress { offset, .. } => Some(offset),
+                _ => None,
+            };
+            if !callback_continue(cb.blob_progress(Arc::new(event.into())))? {
+                return Ok(false);
+            }
+            if let Some(offset) = offset {
+                let current = DocDownloadProgress {
+                    bytes_done: progress.bytes_done.saturating_add(offset.min(size)),
+                    ..progress.clone()
+                };
+                if !callback_continue(cb.progress(current))? {
+                    return Ok(false);
+                }
+            }
+        }
+        progress.blobs_done += 1;
+        progress.bytes_done = progress.bytes_done.saturating_add(size);
+        callback_continue(cb.progress(progress.clone()))
+    }
+}
+
+/// Prefix of the temporary tags of the downloads of [`Doc::download_all`].
+const DOWNLOAD_ALL_TAG_PREFIX: &str = "iroh-ffi/download-all/";
+
+/// Whether `policy` asks to download the content of `key`.
+fn policy_wants(policy: &iroh::docs::store::DownloadPolicy, key: &[u8]) -> bool {
+    match policy {
+        iroh::docs::store::DownloadPolicy::NothingExcept(filters) => {
+            filters.iter().any(|f| f.matches(key))
+        }
+        iroh::docs::store::DownloadPolicy::EverythingExcept(filters) => {
+            !filters.iter().any(|f| f.matches(key))
+        }
+    }
 }
 
 /// Download policy to decide which content blobs shall be downloaded.
@@ -564,6 +1901,46 @@ impl TryFrom<NodeAddr> for iroh::net::endpoint::NodeAddr {
     }
 }
 
+/// Which path to prefer when connecting to a peer.
+///
+/// This is a hint, not a guarantee: it only selects which of the addresses passed to a call
+/// are used to dial the peer. Addresses the node already knows from earlier connections or
+/// discovery may still be used, and an established connection may change paths.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathPreference {
+    /// Dial the peer's direct addresses, keeping its relay url as a fallback.
+    ///
+    /// The endpoint moves a connection to a direct path as soon as one works, so this passes
+    /// all addresses on. Stale direct addresses then do not leave the peer unreachable.
+    PreferDirect,
+    /// Dial the peer's direct addresses only, dropping its relay url if a direct address is
+    /// known.
+    DirectOnly,
+    /// Dial the peer through its relay, and only use direct addresses if no relay url is known.
+    PreferRelay,
+    /// Use all known addresses, letting the endpoint pick the path.
+    Any,
+}
+
+impl PathPreference {
+    /// Drop the addresses of `addr` that this preference does not want used.
+    pub(crate) fn apply(
+        self,
+        mut addr: iroh::net::endpoint::NodeAddr,
+    ) -> iroh::net::endpoint::NodeAddr {
+        match self {
+            PathPreference::DirectOnly if !addr.info.direct_addresses.is_empty() => {
+                addr.info.relay_url = None;
+            }
+            PathPreference::PreferRelay if addr.info.relay_url.is_some() => {
+                addr.info.direct_addresses.clear();
+            }
+            _ => {}
+        }
+        addr
+    }
+}
+
 impl From<iroh::net::endpoint::NodeAddr> for NodeAddr {
     fn from(value: iroh::net::endpoint::NodeAddr) -> Self {
         NodeAddr {
@@ -580,7 +1957,7 @@ impl From<iroh::net::endpoint::NodeAddr> for NodeAddr {
 }
 
 /// Intended capability for document share tickets
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum ShareMode {
     /// Read-only access
     Read,
@@ -632,6 +2009,16 @@ impl Entry {
         self.0.id().key().to_vec()
     }
 
+    /// Get the key of this entry as a string.
+    ///
+    /// Errors if the key is not valid UTF-8, use [`Self::key`] for binary keys.
+    pub fn key_string(&self) -> Result<String, IrohError> {
+        let key = std::str::from_utf8(self.0.id().key()).map_err(|err| {
+            anyhow::anyhow!("entry key is not valid UTF-8: {err}, use `key` for binary keys")
+        })?;
+        Ok(key.to_string())
+    }
+
     /// Get the namespace id of this entry.
     pub fn namespace(&self) -> String {
         self.0.id().namespace().to_string()
@@ -648,10 +2035,81 @@ impl Entry {
     /// before calling [`Self::content_bytes`].
     pub fn content_bytes(&self, doc: Arc<Doc>) -> Result<Vec<u8>, IrohError> {
         block_on(&doc.rt, async {
-            let res = self.0.content_bytes(&doc.inner).await.map(|c| c.to_vec())?;
-            Ok(res)
+            let res = self.0.content_bytes(&doc.inner).await;
+            let res = check_available(&doc.client, self.0.content_hash(), res).await?;
+            Ok(res.to_vec())
+        })
+    }
+
+    /// Convert this entry into a record that can be stored and turned back into an entry with
+    /// [`Self::from_record`].
+    pub fn to_record(&self) -> Result<EntryData, IrohError> {
+        let signed: iroh::docs::SignedEntry = self.0.clone().into();
+        let signature = signed.signature();
+        let mut bytes = Vec::with_capacity(ENTRY_SIGNATURE_LEN);
+        bytes.extend_from_slice(&signature.namespace_signature().to_bytes());
+        bytes.extend_from_slice(&signature.author_signature().to_bytes());
+        Ok(EntryData {
+            namespace: self.namespace(),
+            author: self.author(),
+            key: self.key(),
+            len: self.content_len(),
+            hash: self.content_hash(),
+            timestamp: self.timestamp(),
+            signature: bytes,
         })
     }
+
+    /// Rebuild an entry from a record created by [`Self::to_record`].
+    ///
+    /// Fails if the signature does not match the other fields of the record, so a record that
+    /// was altered after it was created is rejected.
+    pub fn from_record(data: EntryData) -> Result<Self, IrohError> {
+        let namespace = iroh::docs::NamespaceId::from_str(&data.namespace)?;
+        let id = iroh::docs::RecordIdentifier::new(namespace, data.author.0, &data.key);
+        let record = iroh::docs::Record::new(data.hash.0, data.len, data.timestamp);
+        let entry = iroh::docs::Entry::new(id, record);
+        if data.signature.len() != ENTRY_SIGNATURE_LEN {
+            return Err(anyhow::anyhow!(
+                "invalid entry record: signature must be {ENTRY_SIGNATURE_LEN} bytes, got {}",
+                data.signature.len()
+            )
+            .into());
+        }
+        let (namespace_sig, author_sig) = data.signature.split_at(ENTRY_SIGNATURE_LEN / 2);
+        let signature = iroh::docs::EntrySignature::from_parts(
+            namespace_sig.try_into().expect("checked length"),
+            author_sig.try_into().expect("checked length"),
+        );
+        let signed = iroh::docs::SignedEntry::new(signature, entry);
+        signed
+            .verify(&())
+            .map_err(|err| anyhow::anyhow!("invalid entry record: {err}"))?;
+        Ok(Entry(signed.into()))
+    }
+}
+
+/// Length of [`EntryData::signature`]: the ed25519 signatures of the namespace and the author.
+const ENTRY_SIGNATURE_LEN: usize = 128;
+
+/// The fields of an [`Entry`], see [`Entry::to_record`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryData {
+    /// The namespace id of the entry's doc.
+    pub namespace: String,
+    /// The author of the entry.
+    pub author: Arc<AuthorId>,
+    /// The key of the entry.
+    pub key: Vec<u8>,
+    /// The length of the entry's content.
+    pub len: u64,
+    /// The hash of the entry's content.
+    pub hash: Arc<Hash>,
+    /// The timestamp of the entry, in microseconds since the unix epoch.
+    pub timestamp: u64,
+    /// The signatures of the namespace and the author over the entry: the 64 byte ed25519
+    /// signature of the namespace followed by the one of the author.
+    pub signature: Vec<u8>,
 }
 
 ///d Fields by which the query can be sorted
@@ -916,6 +2374,21 @@ impl Query {
     pub fn offset(&self) -> u64 {
         self.0.offset()
     }
+
+    /// Serialize this query, including its filters, sorting and pagination, to restore it
+    /// later with [`Self::from_bytes`].
+    ///
+    /// The encoding is opaque and only meant to be read back by [`Self::from_bytes`].
+    pub fn to_bytes(&self) -> Result<Vec<u8>, IrohError> {
+        let bytes = serde_json::to_vec(&self.0).map_err(anyhow::Error::from)?;
+        Ok(bytes)
+    }
+
+    /// Restore a query serialized with [`Self::to_bytes`].
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, IrohError> {
+        let query = serde_json::from_slice(&bytes).map_err(anyhow::Error::from)?;
+        Ok(Query(query))
+    }
 }
 
 /// The `progress` method will be called for each `SubscribeProgress` event that is
@@ -925,6 +2398,126 @@ pub trait SubscribeCallback: Send + Sync + 'static {
     fn event(&self, event: Arc<LiveEvent>) -> Result<(), CallbackError>;
 }
 
+/// Callback for [`Doc::follow_since`].
+///
+/// `entry` is called for each entry changed since the requested timestamp and `deleted` for
+/// each deletion since then, then `caught_up` once, then `event` for each live event.
+pub trait FollowCallback: Send + Sync + 'static {
+    fn entry(&self, entry: Arc<Entry>) -> Result<(), CallbackError>;
+    fn deleted(&self, entry: Arc<Entry>) -> Result<(), CallbackError>;
+    fn caught_up(&self) -> Result<(), CallbackError>;
+    fn event(&self, event: Arc<LiveEvent>) -> Result<(), CallbackError>;
+}
+
+/// Callback for [`Doc::download_all`].
+///
+/// `blob_progress` is called with the progress events of each blob download, `progress` with
+/// the aggregate progress across all blobs.
+pub trait DocDownloadAllCallback: Send + Sync + 'static {
+    fn blob_progress(&self, progress: Arc<DownloadProgress>) -> Result<(), CallbackError>;
+    fn progress(&self, progress: DocDownloadProgress) -> Result<(), CallbackError>;
+}
+
+/// The number of events buffered for a subscription by default, see [`SubscribeBufferOptions`].
+pub const DEFAULT_SUBSCRIBE_BUFFER_CAPACITY: u32 = 1024;
+
+/// The largest buffer capacity a subscription accepts, see [`SubscribeBufferOptions`].
+///
+/// The buffer is allocated up front, so the capacity is capped to keep a subscription from
+/// reserving unbounded memory.
+pub const MAX_SUBSCRIBE_BUFFER_CAPACITY: u32 = 65536;
+
+/// What to do with new events when the buffer of a subscription is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Drop the oldest buffered events to make room for new ones.
+    ///
+    /// Before the next event, the callback receives a [`LiveEvent::EventsDropped`] event with
+    /// the number of events that were dropped.
+    DropOldest,
+    /// Stop reading events until the callback made room in the buffer.
+    ///
+    /// No events are dropped, but a slow callback holds up the event stream of the node.
+    Block,
+}
+
+/// Options for the buffer between the events of a document and a subscription callback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubscribeBufferOptions {
+    /// How many events are buffered at most, must be at least 1 and at most
+    /// [`MAX_SUBSCRIBE_BUFFER_CAPACITY`].
+    pub capacity: u32,
+    /// What happens to new events once `capacity` events are buffered.
+    pub policy: OverflowPolicy,
+}
+
+impl Default for SubscribeBufferOptions {
+    fn default() -> Self {
+        SubscribeBufferOptions {
+            capacity: DEFAULT_SUBSCRIBE_BUFFER_CAPACITY,
+            policy: OverflowPolicy::Block,
+        }
+    }
+}
+
+/// The bounded buffer of a subscription, see [`SubscribeBufferOptions`].
+enum EventBuffer {
+    DropOldest(tokio::sync::broadcast::Sender<Arc<LiveEvent>>),
+    Block(tokio::sync::mpsc::Sender<Arc<LiveEvent>>),
+}
+
+/// The receiving side of an [`EventBuffer`].
+enum EventBufferReceiver {
+    DropOldest(tokio::sync::broadcast::Receiver<Arc<LiveEvent>>),
+    Block(tokio::sync::mpsc::Receiver<Arc<LiveEvent>>),
+}
+
+impl EventBuffer {
+    fn new(options: SubscribeBufferOptions) -> (Self, EventBufferReceiver) {
+        let capacity = options.capacity as usize;
+        match options.policy {
+            OverflowPolicy::DropOldest => {
+                let (sender, receiver) = tokio::sync::broadcast::channel(capacity);
+                (
+                    EventBuffer::DropOldest(sender),
+                    EventBufferReceiver::DropOldest(receiver),
+                )
+            }
+            OverflowPolicy::Block => {
+                let (sender, receiver) = tokio::sync::mpsc::channel(capacity);
+                (
+                    EventBuffer::Block(sender),
+                    EventBufferReceiver::Block(receiver),
+                )
+            }
+        }
+    }
+
+    /// Buffer an event, returns false if the receiver is gone.
+    async fn send(&self, event: Arc<LiveEvent>) -> bool {
+        match self {
+            EventBuffer::DropOldest(sender) => sender.send(event).is_ok(),
+            EventBuffer::Block(sender) => sender.send(event).await.is_ok(),
+        }
+    }
+}
+
+impl EventBufferReceiver {
+    /// The next event, or a [`LiveEvent::EventsDropped`] event if events were dropped since.
+    async fn recv(&mut self) -> Option<Arc<LiveEvent>> {
+        match self {
+            EventBufferReceiver::DropOldest(receiver) => match receiver.recv().await {
+                Ok(event) => Some(event),
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(count)) => {
+                    Some(Arc::new(LiveEvent::EventsDropped(count)))
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => None,
+            },
+            EventBufferReceiver::Block(receiver) => receiver.recv().await,
+        }
+    }
+}
+
 /// Events informing about actions of the live sync progress
 #[derive(Debug, Serialize, Deserialize)]
 #[allow(clippy::large_enum_variant)]
@@ -964,9 +2557,26 @@ pub enum LiveEvent {
     /// Receiving this event does not guarantee that all content in the document is available. If
     /// blobs failed to download, this event will still be emitted after all operations completed.
     PendingContentReady,
+    /// A failed sync is going to be retried.
+    ///
+    /// Only emitted to the callback of [`Doc::start_sync_with_retry`].
+    SyncRetry(SyncRetryEvent),
+    /// The content of a remote entry was not downloaded in the last sync run.
+    ///
+    /// Emitted right before [`Self::PendingContentReady`] for each entry received with missing
+    /// content that did not become ready, and whose key the download policy, as set when the
+    /// sync finished, asks to download. The content may still arrive later, e.g. from another
+    /// peer.
+    ContentDownloadFailed(ContentDownloadFailedEvent),
+    /// The callback fell behind and this many events were dropped.
+    ///
+    /// Only emitted to subscriptions with [`OverflowPolicy::DropOldest`], regardless of the
+    /// event types the subscription is filtered to.
+    EventsDropped(u64),
 }
 
 /// The type of events that can be emitted during the live sync progress
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum LiveEventType {
     /// A local insertion.
     InsertLocal,
@@ -990,6 +2600,12 @@ pub enum LiveEventType {
     /// Receiving this event does not guarantee that all content in the document is available. If
     /// blobs failed to download, this event will still be emitted after all operations completed.
     PendingContentReady,
+    /// A failed sync is going to be retried.
+    SyncRetry,
+    /// The content of a remote entry was not downloaded in the last sync run.
+    ContentDownloadFailed,
+    /// The callback fell behind and events were dropped.
+    EventsDropped,
 }
 
 impl LiveEvent {
@@ -1003,6 +2619,9 @@ impl LiveEvent {
             Self::NeighborDown(_) => LiveEventType::NeighborDown,
             Self::SyncFinished(_) => LiveEventType::SyncFinished,
             Self::PendingContentReady => LiveEventType::PendingContentReady,
+            Self::SyncRetry(_) => LiveEventType::SyncRetry,
+            Self::ContentDownloadFailed(_) => LiveEventType::ContentDownloadFailed,
+            Self::EventsDropped(_) => LiveEventType::EventsDropped,
         }
     }
 
@@ -1068,6 +2687,33 @@ impl LiveEvent {
             panic!("not an sync event event");
         }
     }
+
+    /// For `LiveEventType::SyncRetry`, returns a SyncRetryEvent
+    pub fn as_sync_retry(&self) -> SyncRetryEvent {
+        if let Self::SyncRetry(event) = self {
+            event.clone()
+        } else {
+            panic!("not a sync retry event");
+        }
+    }
+
+    /// For `LiveEventType::ContentDownloadFailed`, returns a ContentDownloadFailedEvent
+    pub fn as_content_download_failed(&self) -> ContentDownloadFailedEvent {
+        if let Self::ContentDownloadFailed(event) = self {
+            event.clone()
+        } else {
+            panic!("not a content download failed event");
+        }
+    }
+
+    /// For `LiveEventType::EventsDropped`, returns the number of dropped events
+    pub fn as_events_dropped(&self) -> u64 {
+        if let Self::EventsDropped(count) = self {
+            *count
+        } else {
+            panic!("not an events dropped event");
+        }
+    }
 }
 
 impl From<iroh::client::docs::LiveEvent> for LiveEvent {
@@ -1096,9 +2742,147 @@ impl From<iroh::client::docs::LiveEvent> for LiveEvent {
     }
 }
 
-/// Outcome of a sync operation
+/// Content of a remote entry that was not downloaded, see [`LiveEvent::ContentDownloadFailed`].
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SyncEvent {
+pub struct ContentDownloadFailedEvent {
+    /// The content hash of the entry
+    pub hash: Arc<Hash>,
+    /// Why the content is not available
+    pub error: String,
+}
+
+/// Tracks the content of remote entries that is still missing during a subscription, to emit
+/// [`LiveEvent::ContentDownloadFailed`] for the content that is still missing once iroh
+/// reports all queued downloads as done.
+///
+/// The download policy of the document is read again after every sync, so that content a
+/// changed policy no longer asks for is not reported.
+struct PendingContent {
+    doc: MemDoc,
+    policy: Option<iroh::docs::store::DownloadPolicy>,
+    /// The keys of the entries whose content is missing, by content hash.
+    missing: HashMap<iroh::blobs::Hash, HashSet<Vec<u8>>>,
+}
+
+impl PendingContent {
+    async fn new(doc: MemDoc) -> Self {
+        let mut pending = PendingContent {
+            doc,
+            policy: None,
+            missing: HashMap::new(),
+        };
+        pending.refresh_policy().await;
+        pending
+    }
+
+    /// Read the download policy of the document again. Keeps the previous policy if it can't
+    /// be read.
+    async fn refresh_policy(&mut self) {
+        match self.doc.get_download_policy().await {
+            Ok(policy) => self.policy = Some(policy),
+            Err(err) => tracing::warn!("failed to read the download policy: {:?}", err),
+        }
+    }
+
+    /// Whether the download policy asks to download the content of `key`.
+    fn wants(&self, key: &[u8]) -> bool {
+        self.policy
+            .as_ref()
+            .map_or(true, |policy| policy_wants(policy, key))
+    }
+
+    /// Track `event` and convert it, preceded by the failures it reveals.
+    async fn on_event(&mut self, event: iroh::client::docs::LiveEvent) -> Vec<LiveEvent> {
+        let mut events = Vec::new();
+        match &event {
+            iroh::client::docs::LiveEvent::InsertRemote {
+                entry,
+                content_status,
+                ..
+            } => {
+                if !matches!(content_status, iroh::docs::ContentStatus::Complete)
+                    && self.wants(entry.key())
+                {
+                    self.missing
+                        .entry(entry.content_hash())
+                        .or_default()
+                        .insert(entry.key().to_vec());
+                }
+            }
+            iroh::client::docs::LiveEvent::ContentReady { hash } => {
+                self.missing.remove(hash);
+            }
+            iroh::client::docs::LiveEvent::SyncFinished(_) => {
+                self.refresh_policy().await;
+            }
+            iroh::client::docs::LiveEvent::PendingContentReady => {
+                let missing = std::mem::take(&mut self.missing);
+                events.extend(
+                    missing
+                        .into_iter()
+                        .filter(|(_, keys)| keys.iter().any(|key| self.wants(key)))
+                        .map(|(hash, _)| {
+                            LiveEvent::ContentDownloadFailed(ContentDownloadFailedEvent {
+                                hash: Arc::new(hash.into()),
+                                error: "content was not downloaded from any peer".to_string(),
+                            })
+                        }),
+                );
+            }
+            _ => {}
+        }
+        events.push(event.into());
+        events
+    }
+}
+
+/// Policy for retrying failed syncs, see [`Doc::start_sync_with_retry`].
+#[derive(Debug, Clone)]
+pub struct SyncRetryPolicy {
+    /// Maximum number of syncs attempted with each peer, including the first one.
+    pub max_attempts: u32,
+    /// Delay before the first retry, in milliseconds. Doubles with every further retry.
+    pub base_backoff_millis: u64,
+    /// Maximum random delay added to each backoff, in milliseconds.
+    pub jitter_millis: u64,
+}
+
+impl SyncRetryPolicy {
+    /// The delay before retrying after `attempt` failed syncs.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let backoff = self
+            .base_backoff_millis
+            .saturating_mul(1u64 << attempt.saturating_sub(1).min(16));
+        let jitter = match self.jitter_millis {
+            0 => 0,
+            jitter => {
+                let nanos = SystemTime::now()
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .subsec_nanos();
+                u64::from(nanos) % (jitter + 1)
+            }
+        };
+        Duration::from_millis(backoff.saturating_add(jitter))
+    }
+}
+
+/// A failed sync that is going to be retried, see [`Doc::start_sync_with_retry`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncRetryEvent {
+    /// Peer the sync failed with
+    pub peer: Arc<PublicKey>,
+    /// The number of the failed attempt, starting at 1
+    pub attempt: u32,
+    /// Delay until the sync is retried, in milliseconds
+    pub delay_millis: u64,
+    /// Why the sync failed
+    pub reason: String,
+}
+
+/// Outcome of a sync operation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncEvent {
     /// Peer we synced with
     pub peer: Arc<PublicKey>,
     /// Origin of the sync exchange
@@ -1180,12 +2964,31 @@ impl From<iroh::docs::ContentStatus> for ContentStatus {
 }
 
 /// The `progress` method will be called for each `DocImportProgress` event that is
-/// emitted during a `doc.import_file()` call. Use the `DocImportProgress.type()`
+/// emitted during a `doc.import_file()` or `doc.import_blob()` call. Use the `DocImportProgress.type()`
 /// method to check the `DocImportProgressType`
 pub trait DocImportFileCallback: Send + Sync + 'static {
     fn progress(&self, progress: Arc<DocImportProgress>) -> Result<(), CallbackError>;
 }
 
+impl DocImportFileCallback for ProgressSender<DocImportProgress> {
+    fn progress(&self, progress: Arc<DocImportProgress>) -> Result<(), CallbackError> {
+        self.send(progress)
+    }
+}
+
+/// The progress events of an import started with [`Doc::import_file_stream`].
+pub struct DocImportProgressStream(ProgressReceiver<DocImportProgress>);
+
+impl DocImportProgressStream {
+    /// Wait for the next event, `None` once the import is done.
+    ///
+    /// This is async, so waiting does not block the calling thread. Fails with the error of
+    /// the import if it failed.
+    pub async fn next(&self) -> Result<Option<Arc<DocImportProgress>>, IrohError> {
+        self.0.next().await
+    }
+}
+
 /// The type of `DocImportProgress` event
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum DocImportProgressType {
@@ -1500,7 +3303,7 @@ mod tests {
         let node = IrohNode::new(path.path().to_string_lossy().into_owned()).unwrap();
         let node_id = node.node_id();
         println!("id: {}", node_id);
-        let doc = node.doc_create().unwrap();
+        let doc = node.doc_create(None).unwrap();
         let doc_id = doc.id();
         println!("doc_id: {}", doc_id);
 
@@ -1508,7 +3311,116 @@ mod tests {
             .share(crate::doc::ShareMode::Write, AddrInfoOptions::Id)
             .unwrap();
         println!("doc_ticket: {}", doc_ticket);
-        node.doc_join(doc_ticket).unwrap();
+        node.doc_join(doc_ticket, None, None).unwrap();
+    }
+
+    #[test]
+    fn test_doc_share_compact() {
+        let path = tempfile::tempdir().unwrap();
+        let node = IrohNode::new(path.path().to_string_lossy().into_owned()).unwrap();
+        let doc = node.doc_create(None).unwrap();
+
+        let full = doc
+            .share(ShareMode::Read, AddrInfoOptions::RelayAndAddresses)
+            .unwrap();
+        let compact = doc.share_compact(ShareMode::Read).unwrap();
+        assert!(compact.len() <= full.len());
+
+        // the qr payload can be joined like the regular ticket
+        let payload = crate::ticket_qr_payload(compact);
+        assert_eq!(payload, payload.to_ascii_uppercase());
+        let joined = node.doc_join(payload, None, None).unwrap();
+        assert_eq!(doc.id(), joined.id());
+    }
+
+    #[test]
+    fn test_doc_merge() {
+        let dir = tempfile::tempdir().unwrap();
+        let node = IrohNode::new(dir.path().to_string_lossy().into_owned()).unwrap();
+        let author = node.author_create().unwrap();
+        let target = node.doc_create(None).unwrap();
+        let source = node.doc_create(None).unwrap();
+
+        // the target keeps entries that are newer than the source's
+        source
+            .set_bytes(&author, b"older".to_vec(), b"source".to_vec())
+            .unwrap();
+        target
+            .set_bytes(&author, b"older".to_vec(), b"target".to_vec())
+            .unwrap();
+        target
+            .set_bytes(&author, b"newer".to_vec(), b"target".to_vec())
+            .unwrap();
+        source
+            .set_bytes(&author, b"newer".to_vec(), b"source".to_vec())
+            .unwrap();
+        source
+            .set_bytes(&author, b"only".to_vec(), b"source".to_vec())
+            .unwrap();
+        // a key deleted in the target after the source write stays deleted
+        source
+            .set_bytes(&author, b"deleted".to_vec(), b"source".to_vec())
+            .unwrap();
+        target
+            .set_bytes(&author, b"deleted".to_vec(), b"target".to_vec())
+            .unwrap();
+        target.del(author.clone(), b"deleted".to_vec()).unwrap();
+
+        let merger = node.author_create().unwrap();
+        let merged = node
+            .doc_merge(target.clone(), source.clone(), merger.clone())
+            .unwrap();
+        assert_eq!(merged, 2);
+
+        let content = |key: &[u8]| {
+            let query = Query::single_latest_per_key_exact(key.to_vec());
+            let entry = target.get_one(Arc::new(query)).unwrap().unwrap();
+            (
+                entry.author(),
+                node.blobs_read_to_bytes(entry.content_hash()).unwrap(),
+            )
+        };
+        assert_eq!(content(b"older").1, b"target".to_vec());
+        let (written_by, newer) = content(b"newer");
+        assert_eq!(newer, b"source".to_vec());
+        assert!(written_by.equal(&merger));
+        assert_eq!(content(b"only").1, b"source".to_vec());
+        let query = Query::single_latest_per_key_exact(b"deleted".to_vec());
+        assert!(target.get_one(Arc::new(query)).unwrap().is_none());
+
+        // merging again copies nothing, the copies are newer than the source entries
+        assert_eq!(node.doc_merge(target, source, merger).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_doc_create_and_join_with_download_policy() {
+        let iroh_dir_0 = tempfile::tempdir().unwrap();
+        let node_0 = IrohNode::new(iroh_dir_0.path().to_string_lossy().into_owned()).unwrap();
+        let iroh_dir_1 = tempfile::tempdir().unwrap();
+        let node_1 = IrohNode::new(iroh_dir_1.path().to_string_lossy().into_owned()).unwrap();
+
+        // the policy is in place as soon as the doc is created
+        let nothing = Arc::new(DownloadPolicy::nothing());
+        let doc_0 = node_0.doc_create(Some(nothing.clone())).unwrap();
+        assert_eq!(*nothing, *doc_0.get_download_policy().unwrap());
+
+        // without a policy the default applies
+        let doc = node_0.doc_create(None).unwrap();
+        assert_eq!(
+            DownloadPolicy::everything(),
+            *doc.get_download_policy().unwrap()
+        );
+
+        // the policy is set on join, before sync starts
+        let ticket = doc_0
+            .share(ShareMode::Read, AddrInfoOptions::RelayAndAddresses)
+            .unwrap();
+        let photos = Arc::new(DownloadPolicy::nothing_except(vec![Arc::new(
+            FilterKind::prefix(b"photos/".to_vec()),
+        )]));
+        let doc_1 = node_1.doc_join(ticket, None, Some(photos.clone())).unwrap();
+        assert_eq!(doc_0.id(), doc_1.id());
+        assert_eq!(*photos, *doc_1.get_download_policy().unwrap());
     }
 
     #[test]
@@ -1522,7 +3434,7 @@ mod tests {
         let node_1 = IrohNode::new(iroh_dir_1.path().to_string_lossy().into_owned()).unwrap();
 
         // create doc on node_0
-        let doc_0 = node_0.doc_create().unwrap();
+        let doc_0 = node_0.doc_create(None).unwrap();
         let ticket = doc_0
             .share(ShareMode::Write, AddrInfoOptions::RelayAndAddresses)
             .unwrap();
@@ -1543,10 +3455,10 @@ mod tests {
             }
         }
         let cb = Callback { found_s };
-        doc_0.subscribe(Arc::new(cb)).unwrap();
+        doc_0.subscribe(Arc::new(cb), None).unwrap();
 
         // join the same doc from node_1
-        let doc_1 = node_1.doc_join(ticket).unwrap();
+        let doc_1 = node_1.doc_join(ticket, None, None).unwrap();
 
         // create author on node_1
         let author = node_1.author_create().unwrap();
@@ -1558,6 +3470,134 @@ mod tests {
         assert_eq!(b"world".to_vec(), val);
     }
 
+    #[test]
+    fn test_doc_content_unavailable() {
+        let iroh_dir = tempfile::tempdir().unwrap();
+        let node = IrohNode::new(iroh_dir.path().to_string_lossy().into_owned()).unwrap();
+        let doc = node.doc_create(None).unwrap();
+        let author = node.author_create().unwrap();
+
+        let hash = doc
+            .set_bytes(&author, b"key".to_vec(), b"value".to_vec())
+            .unwrap();
+        let entry = doc.get_latest(b"key".to_vec()).unwrap().unwrap();
+        block_on(&node.rt(), node.sync_client.blobs().delete_blob(hash.0)).unwrap();
+
+        let err = entry.content_bytes(doc.clone()).unwrap_err();
+        assert!(err.is_content_unavailable());
+        assert_eq!(Some(hash.clone()), err.content_unavailable_hash());
+        let err = doc.content_bytes_many(vec![entry]).unwrap_err();
+        assert_eq!(Some(hash), err.content_unavailable_hash());
+    }
+
+    #[test]
+    fn test_doc_namespace_keys() {
+        let iroh_dir = tempfile::tempdir().unwrap();
+        let node = IrohNode::new(iroh_dir.path().to_string_lossy().into_owned()).unwrap();
+
+        let doc = node.doc_create(None).unwrap();
+        assert_eq!(doc.id(), doc.namespace_public_key().unwrap().to_string());
+        let secret = doc.namespace_secret().unwrap();
+        assert_eq!(32, secret.len());
+
+        // a read-only copy of the doc does not know the secret
+        let iroh_dir_1 = tempfile::tempdir().unwrap();
+        let node_1 = IrohNode::new(iroh_dir_1.path().to_string_lossy().into_owned()).unwrap();
+        let ticket = doc.share(ShareMode::Read, AddrInfoOptions::Id).unwrap();
+        let read_doc = node_1.doc_join(ticket, None, None).unwrap();
+        assert_eq!(
+            doc.id(),
+            read_doc.namespace_public_key().unwrap().to_string()
+        );
+        assert!(read_doc
+            .namespace_secret()
+            .unwrap_err()
+            .is_permission_denied());
+
+        // writing to the read-only copy fails up front
+        let author = node_1.author_create().unwrap();
+        let err = read_doc
+            .set_bytes(&author, b"key".to_vec(), b"value".to_vec())
+            .unwrap_err();
+        assert!(err.is_permission_denied());
+        assert!(err.message().contains("read-only"));
+        let err = read_doc.del(author, b"key".to_vec()).unwrap_err();
+        assert!(err.is_permission_denied());
+
+        let docs = node.doc_list().unwrap();
+        assert_eq!(1, docs.len());
+        assert!(docs[0].has_secret);
+        let docs = node_1.doc_list().unwrap();
+        assert_eq!(1, docs.len());
+        assert!(!docs[0].has_secret);
+    }
+
+    #[test]
+    fn test_await_initial_sync() {
+        let iroh_dir_0 = tempfile::tempdir().unwrap();
+        let node_0 = IrohNode::new(iroh_dir_0.path().to_string_lossy().into_owned()).unwrap();
+        let iroh_dir_1 = tempfile::tempdir().unwrap();
+        let node_1 = IrohNode::new(iroh_dir_1.path().to_string_lossy().into_owned()).unwrap();
+
+        let doc_0 = node_0.doc_create(None).unwrap();
+        let ticket = doc_0
+            .share(ShareMode::Write, AddrInfoOptions::RelayAndAddresses)
+            .unwrap();
+
+        let doc_1 = node_1.doc_join(ticket, None, None).unwrap();
+        let event = doc_1.await_initial_sync(10_000).unwrap();
+        assert!(event.result.is_none());
+        assert_eq!(event.peer.to_string(), node_0.node_id());
+
+        // the sync already happened, so waiting again returns the same event right away
+        let again = doc_1.await_initial_sync(1).unwrap();
+        assert_eq!(event.finished, again.finished);
+
+        // a created doc has no peers to sync with
+        assert!(node_1
+            .doc_create(None)
+            .unwrap()
+            .await_initial_sync(100)
+            .is_err());
+    }
+
+    #[test]
+    fn test_path_preference() {
+        let node_id =
+            PublicKey::from_string("ki6htfv2252cj2lhq3hxu4qfcfjtpjnukzonevigudzjpmmruxva".into())
+                .unwrap();
+        let both: iroh::net::endpoint::NodeAddr = NodeAddr::new(
+            &node_id,
+            Some("https://relay.example".into()),
+            vec!["127.0.0.1:3000".into()],
+        )
+        .try_into()
+        .unwrap();
+
+        // preferring direct paths keeps the relay as a fallback
+        assert_eq!(both, PathPreference::PreferDirect.apply(both.clone()));
+
+        let direct = PathPreference::DirectOnly.apply(both.clone());
+        assert!(direct.info.relay_url.is_none());
+        assert_eq!(1, direct.info.direct_addresses.len());
+
+        let relay = PathPreference::PreferRelay.apply(both.clone());
+        assert!(relay.info.relay_url.is_some());
+        assert!(relay.info.direct_addresses.is_empty());
+
+        assert_eq!(both, PathPreference::Any.apply(both.clone()));
+
+        // without a relay url, preferring the relay falls back to the direct addresses
+        let direct_only: iroh::net::endpoint::NodeAddr =
+            NodeAddr::new(&node_id, None, vec!["127.0.0.1:3000".into()])
+                .try_into()
+                .unwrap();
+        assert_eq!(
+            direct_only,
+            PathPreference::PreferRelay.apply(direct_only.clone())
+        );
+    }
+
     #[test]
     fn test_node_addr() {
         //
@@ -1663,7 +3703,7 @@ mod tests {
         let node = crate::IrohNode::new(path.path().to_string_lossy().into_owned()).unwrap();
 
         // create doc  and author
-        let doc = node.doc_create().unwrap();
+        let doc = node.doc_create(None).unwrap();
         let author = node.author_create().unwrap();
 
         // add entry
@@ -1681,6 +3721,631 @@ mod tests {
         assert_eq!(val, got_val);
         assert_eq!(val.len() as u64, entry.content_len());
     }
+
+    #[test]
+    fn test_doc_availability() {
+        let path = tempfile::tempdir().unwrap();
+        let node = crate::IrohNode::new(path.path().to_string_lossy().into_owned()).unwrap();
+        let doc = node.doc_create(None).unwrap();
+        let author = node.author_create().unwrap();
+
+        doc.set_bytes(&author, b"a".to_vec(), b"hello".to_vec())
+            .unwrap();
+        // same content again, counted once
+        doc.set_bytes(&author, b"b".to_vec(), b"hello".to_vec())
+            .unwrap();
+        // content that is not on this node
+        let missing = Arc::new(Hash::new(b"missing".to_vec()));
+        doc.set_hash(author.clone(), b"c".to_vec(), missing, 7)
+            .unwrap();
+
+        assert_eq!(
+            DocAvailability {
+                total_bytes: 12,
+                complete_bytes: 5,
+                total_blobs: 2,
+                complete_blobs: 1,
+            },
+            doc.availability().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_doc_create_and_share() {
+        let path = tempfile::tempdir().unwrap();
+        let node = crate::IrohNode::new(path.path().to_string_lossy().into_owned()).unwrap();
+        let author = node.author_create().unwrap();
+
+        let initial = vec![
+            KeyValue {
+                key: b"a".to_vec(),
+                value: b"1".to_vec(),
+            },
+            KeyValue {
+                key: b"b".to_vec(),
+                value: b"2".to_vec(),
+            },
+        ];
+        let DocAndTicket { doc, ticket } = node
+            .doc_create_and_share(
+                author.clone(),
+                initial,
+                ShareMode::Write,
+                AddrInfoOptions::Id,
+            )
+            .unwrap();
+        let entry = doc
+            .get_exact(author.clone(), b"b".to_vec(), false)
+            .unwrap()
+            .unwrap();
+        assert_eq!(b"2".to_vec(), entry.content_bytes(doc.clone()).unwrap());
+        let ticket = iroh::docs::DocTicket::from_str(&ticket).unwrap();
+        assert_eq!(doc.id(), ticket.capability.id().to_string());
+    }
+
+    #[test]
+    fn test_entry_record_roundtrip() {
+        let path = tempfile::tempdir().unwrap();
+        let node = crate::IrohNode::new(path.path().to_string_lossy().into_owned()).unwrap();
+        let doc = node.doc_create(None).unwrap();
+        let author = node.author_create().unwrap();
+        doc.set_bytes(&author, b"key".to_vec(), b"value".to_vec())
+            .unwrap();
+        let entry = doc
+            .get_exact(author.clone(), b"key".to_vec(), false)
+            .unwrap()
+            .unwrap();
+
+        let record = entry.to_record().unwrap();
+        assert_eq!(author, record.author);
+        assert_eq!(5, record.len);
+        assert_eq!(ENTRY_SIGNATURE_LEN, record.signature.len());
+        let restored = Entry::from_record(record.clone()).unwrap();
+        assert_eq!(entry.key(), restored.key());
+        assert_eq!(entry.content_hash(), restored.content_hash());
+        assert_eq!(entry.timestamp(), restored.timestamp());
+        assert_eq!(record, restored.to_record().unwrap());
+
+        // an altered record does not match its signature
+        let altered = EntryData {
+            timestamp: record.timestamp + 1,
+            ..record.clone()
+        };
+        assert!(Entry::from_record(altered).is_err());
+        let truncated = EntryData {
+            signature: record.signature[..64].to_vec(),
+            ..record
+        };
+        assert!(Entry::from_record(truncated).is_err());
+    }
+
+    #[test]
+    fn test_doc_max_value_size() {
+        let path = tempfile::tempdir().unwrap();
+        let opts = crate::NodeOptions {
+            max_doc_value_size: Some(5),
+            ..Default::default()
+        };
+        let node = crate::IrohNode::with_options(path.path().to_string_lossy().into_owned(), opts)
+            .unwrap();
+        let doc = node.doc_create(None).unwrap();
+        let author = node.author_create().unwrap();
+
+        doc.set_bytes(&author, b"small".to_vec(), b"hello".to_vec())
+            .unwrap();
+        let err = doc
+            .set_bytes(&author, b"large".to_vec(), b"hello world".to_vec())
+            .unwrap_err();
+        assert!(err.message().contains("maximum doc value size"));
+        assert!(doc
+            .set_bytes_if(&author, b"large".to_vec(), b"hello world".to_vec(), None)
+            .is_err());
+        let hash = Arc::new(Hash::new(b"hello world".to_vec()));
+        assert!(doc
+            .set_hash(author.clone(), b"large".to_vec(), hash, 11)
+            .is_err());
+
+        let file = path.path().join("large.txt");
+        std::fs::write(&file, b"hello world").unwrap();
+        assert!(doc
+            .import_file(
+                author.clone(),
+                b"large".to_vec(),
+                file.display().to_string(),
+                false,
+                None,
+                None
+            )
+            .is_err());
+        assert!(doc
+            .get_exact(author, b"large".to_vec(), false)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_doc_string_keys() {
+        let path = tempfile::tempdir().unwrap();
+        let node = crate::IrohNode::new(path.path().to_string_lossy().into_owned()).unwrap();
+        let doc = node.doc_create(None).unwrap();
+        let author = node.author_create().unwrap();
+
+        let hash = doc
+            .set_bytes_str(&author, "grüße".to_string(), b"hi".to_vec())
+            .unwrap();
+        let entry = doc
+            .get_exact(author.clone(), "grüße".as_bytes().to_vec(), false)
+            .unwrap()
+            .unwrap();
+        assert!(hash.equal(&entry.content_hash()));
+        assert_eq!("grüße", entry.key_string().unwrap());
+        let entry = doc
+            .get_exact_str(author.clone(), "grüße".to_string(), false)
+            .unwrap()
+            .unwrap();
+        assert!(hash.equal(&entry.content_hash()));
+
+        doc.set_bytes(&author, vec![0xff, 0xfe], b"binary".to_vec())
+            .unwrap();
+        let entry = doc
+            .get_exact(author, vec![0xff, 0xfe], false)
+            .unwrap()
+            .unwrap();
+        assert!(entry.key_string().is_err());
+    }
+
+    #[test]
+    fn test_sync_retry_backoff() {
+        let policy = SyncRetryPolicy {
+            max_attempts: 5,
+            base_backoff_millis: 100,
+            jitter_millis: 0,
+        };
+        assert_eq!(Duration::from_millis(100), policy.backoff(1));
+        assert_eq!(Duration::from_millis(200), policy.backoff(2));
+        assert_eq!(Duration::from_millis(400), policy.backoff(3));
+
+        let policy = SyncRetryPolicy {
+            jitter_millis: 50,
+            ..policy
+        };
+        let backoff = policy.backoff(2);
+        assert!(backoff >= Duration::from_millis(200));
+        assert!(backoff <= Duration::from_millis(250));
+    }
+
+    #[test]
+    fn test_query_bytes_roundtrip() {
+        let author = AuthorId::from_string(
+            "mqtlzayyv4pb4xvnqnw5wxb2meivzq5ze6jihpa7fv5lfwdoya4q".to_string(),
+        )
+        .unwrap();
+        let opts = QueryOptions {
+            sort_by: SortBy::KeyAuthor,
+            direction: SortDirection::Desc,
+            offset: 10,
+            limit: 100,
+        };
+        let query = Query::author_key_prefix(&author, b"prefix".to_vec(), Some(opts));
+        let restored = Query::from_bytes(query.to_bytes().unwrap()).unwrap();
+        assert_eq!(format!("{:?}", query.0), format!("{:?}", restored.0));
+        assert_eq!(10, restored.offset());
+        assert_eq!(Some(100), restored.limit());
+    }
+
+    #[test]
+    fn test_doc_subscribe_filtered() {
+        let path = tempfile::tempdir().unwrap();
+        let node = crate::IrohNode::new(path.path().to_string_lossy().into_owned()).unwrap();
+        let doc = node.doc_create(None).unwrap();
+        let author = node.author_create().unwrap();
+
+        struct Callback {
+            types: std::sync::mpsc::Sender<LiveEventType>,
+        }
+        impl SubscribeCallback for Callback {
+            fn event(&self, event: Arc<LiveEvent>) -> Result<(), CallbackError> {
+                self.types.send(event.r#type()).unwrap();
+                Ok(())
+            }
+        }
+
+        let (types_s, types_r) = std::sync::mpsc::channel();
+        doc.subscribe_filtered(
+            vec![LiveEventType::InsertLocal],
+            Arc::new(Callback { types: types_s }),
+            None,
+        )
+        .unwrap();
+        // give the subscription time to start
+        std::thread::sleep(Duration::from_millis(100));
+
+        doc.set_bytes(&author, b"key".to_vec(), b"value".to_vec())
+            .unwrap();
+        let typ = types_r.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(LiveEventType::InsertLocal, typ);
+        // no other events are passed to the callback
+        while let Ok(typ) = types_r.recv_timeout(Duration::from_millis(200)) {
+            assert_eq!(LiveEventType::InsertLocal, typ);
+        }
+    }
+
+    #[test]
+    fn test_doc_subscribe_drop_oldest() {
+        let path = tempfile::tempdir().unwrap();
+        let node = crate::IrohNode::new(path.path().to_string_lossy().into_owned()).unwrap();
+        let doc = node.doc_create(None).unwrap();
+        let author = node.author_create().unwrap();
+
+        struct Callback {
+            events: std::sync::mpsc::Sender<Arc<LiveEvent>>,
+        }
+        impl SubscribeCallback for Callback {
+            fn event(&self, event: Arc<LiveEvent>) -> Result<(), CallbackError> {
+                // a slow callback
+                std::thread::sleep(Duration::from_millis(200));
+                self.events.send(event).unwrap();
+                Ok(())
+            }
+        }
+
+        let (events_s, events_r) = std::sync::mpsc::channel();
+        let buffer = SubscribeBufferOptions {
+            capacity: 1,
+            policy: OverflowPolicy::DropOldest,
+        };
+        doc.subscribe_filtered(
+            vec![LiveEventType::InsertLocal],
+            Arc::new(Callback { events: events_s }),
+            Some(buffer),
+        )
+        .unwrap();
+        // give the subscription time to start
+        std::thread::sleep(Duration::from_millis(100));
+
+        for i in 0..5u8 {
+            doc.set_bytes(&author, vec![i], b"value".to_vec()).unwrap();
+        }
+        let mut dropped = 0;
+        let mut received = 0;
+        while let Ok(event) = events_r.recv_timeout(Duration::from_secs(2)) {
+            match event.r#type() {
+                LiveEventType::EventsDropped => dropped += event.as_events_dropped(),
+                LiveEventType::InsertLocal => received += 1,
+                typ => panic!("unexpected event {typ:?}"),
+            }
+        }
+        assert!(dropped > 0);
+        assert_eq!(5, dropped + received);
+
+        let buffer = SubscribeBufferOptions {
+            capacity: 0,
+            policy: OverflowPolicy::Block,
+        };
+        let (events_s, _events_r) = std::sync::mpsc::channel();
+        assert!(doc
+            .subscribe(
+                Arc::new(Callback {
+                    events: events_s.clone()
+                }),
+                Some(buffer)
+            )
+            .is_err());
+        let buffer = SubscribeBufferOptions {
+            capacity: MAX_SUBSCRIBE_BUFFER_CAPACITY + 1,
+            policy: OverflowPolicy::DropOldest,
+        };
+        assert!(doc
+            .subscribe(Arc::new(Callback { events: events_s }), Some(buffer))
+            .is_err());
+    }
+
+    #[test]
+    fn test_doc_create_deterministic() {
+        let path = tempfile::tempdir().unwrap();
+        let node = crate::IrohNode::new(path.path().to_string_lossy().into_owned()).unwrap();
+        let path_1 = tempfile::tempdir().unwrap();
+        let node_1 = crate::IrohNode::new(path_1.path().to_string_lossy().into_owned()).unwrap();
+
+        let doc = node
+            .doc_create_deterministic(b"family-photos-2024".to_vec())
+            .unwrap();
+        // the same seed gives the same writable doc, on this and other nodes
+        let again = node
+            .doc_create_deterministic(b"family-photos-2024".to_vec())
+            .unwrap();
+        let other_node = node_1
+            .doc_create_deterministic(b"family-photos-2024".to_vec())
+            .unwrap();
+        assert_eq!(doc.id(), again.id());
+        assert_eq!(doc.id(), other_node.id());
+        assert_eq!(
+            doc.namespace_secret().unwrap(),
+            other_node.namespace_secret().unwrap()
+        );
+
+        let different = node.doc_create_deterministic(b"other".to_vec()).unwrap();
+        assert_ne!(doc.id(), different.id());
+    }
+
+    #[test]
+    fn test_pending_content_follows_download_policy() {
+        let path = tempfile::tempdir().unwrap();
+        let node = crate::IrohNode::new(path.path().to_string_lossy().into_owned()).unwrap();
+        let doc = node.doc_create(None).unwrap();
+
+        let mut pending = block_on(&node.rt(), PendingContent::new(doc.inner.clone()));
+        assert!(pending.wants(b"photos/a"));
+        assert!(pending.wants(b"notes/a"));
+
+        // a changed policy is only picked up once it is read again
+        let photos = vec![Arc::new(FilterKind::prefix(b"photos/".to_vec()))];
+        doc.set_download_policy(Arc::new(DownloadPolicy::nothing_except(photos.clone())))
+            .unwrap();
+        assert!(pending.wants(b"notes/a"));
+        block_on(&node.rt(), pending.refresh_policy());
+        assert!(pending.wants(b"photos/a"));
+        assert!(!pending.wants(b"notes/a"));
+
+        doc.set_download_policy(Arc::new(DownloadPolicy::everything_except(photos)))
+            .unwrap();
+        block_on(&node.rt(), pending.refresh_policy());
+        assert!(!pending.wants(b"photos/a"));
+        assert!(pending.wants(b"notes/a"));
+
+        // missing content is only reported if the current policy still wants it
+        let photo = iroh::blobs::Hash::new(b"photo");
+        let note = iroh::blobs::Hash::new(b"note");
+        pending
+            .missing
+            .insert(photo, HashSet::from([b"photos/a".to_vec()]));
+        pending
+            .missing
+            .insert(note, HashSet::from([b"notes/a".to_vec()]));
+        let events = block_on(
+            &node.rt(),
+            pending.on_event(iroh::client::docs::LiveEvent::PendingContentReady),
+        );
+        let failed: Vec<_> = events
+            .iter()
+            .filter(|event| event.r#type() == LiveEventType::ContentDownloadFailed)
+            .map(|event| event.as_content_download_failed().hash)
+            .collect();
+        assert_eq!(failed, vec![Arc::new(Hash::from(note))]);
+        assert!(pending.missing.is_empty());
+    }
+
+    #[test]
+    fn test_doc_follow_since() {
+        let path = tempfile::tempdir().unwrap();
+        let node = crate::IrohNode::new(path.path().to_string_lossy().into_owned()).unwrap();
+        let doc = node.doc_create(None).unwrap();
+        let author = node.author_create().unwrap();
+
+        doc.set_bytes(&author, b"a".to_vec(), b"1".to_vec())
+            .unwrap();
+        let since = doc
+            .get_exact(author.clone(), b"a".to_vec(), false)
+            .unwrap()
+            .unwrap()
+            .timestamp();
+        doc.set_bytes(&author, b"b".to_vec(), b"2".to_vec())
+            .unwrap();
+        doc.set_bytes(&author, b"c".to_vec(), b"3".to_vec())
+            .unwrap();
+        doc.del(author.clone(), b"b".to_vec()).unwrap();
+
+        #[derive(Debug, PartialEq)]
+        enum Followed {
+            Entry(Vec<u8>),
+            Deleted(Vec<u8>),
+            CaughtUp,
+            Event(LiveEventType),
+        }
+        struct Callback {
+            followed: std::sync::mpsc::Sender<Followed>,
+        }
+        impl FollowCallback for Callback {
+            fn entry(&self, entry: Arc<Entry>) -> Result<(), CallbackError> {
+                self.followed.send(Followed::Entry(entry.key())).unwrap();
+                Ok(())
+            }
+            fn deleted(&self, entry: Arc<Entry>) -> Result<(), CallbackError> {
+                self.followed.send(Followed::Deleted(entry.key())).unwrap();
+                Ok(())
+            }
+            fn caught_up(&self) -> Result<(), CallbackError> {
+                self.followed.send(Followed::CaughtUp).unwrap();
+                Ok(())
+            }
+            fn event(&self, event: Arc<LiveEvent>) -> Result<(), CallbackError> {
+                self.followed.send(Followed::Event(event.r#type())).unwrap();
+                Ok(())
+            }
+        }
+
+        let (followed_s, followed_r) = std::sync::mpsc::channel();
+        doc.follow_since(
+            since,
+            Arc::new(Callback {
+                followed: followed_s,
+            }),
+        )
+        .unwrap();
+        let next = || followed_r.recv_timeout(Duration::from_secs(5)).unwrap();
+        // the entry of b was replaced by the empty entry that marks its deletion
+        assert_eq!(Followed::Entry(b"c".to_vec()), next());
+        assert_eq!(Followed::Deleted(b"b".to_vec()), next());
+        assert_eq!(Followed::CaughtUp, next());
+
+        doc.set_bytes(&author, b"d".to_vec(), b"4".to_vec())
+            .unwrap();
+        assert_eq!(Followed::Event(LiveEventType::InsertLocal), next());
+    }
+
+    #[test]
+    fn test_doc_set_bytes_if() {
+        let path = tempfile::tempdir().unwrap();
+        let node = crate::IrohNode::new(path.path().to_string_lossy().into_owned()).unwrap();
+        let doc = node.doc_create(None).unwrap();
+        let author = node.author_create().unwrap();
+        let key = b"key".to_vec();
+
+        // must not exist
+        let first = doc
+            .set_bytes_if(&author, key.clone(), b"first".to_vec(), None)
+            .unwrap()
+            .unwrap();
+        assert!(doc
+            .set_bytes_if(&author, key.clone(), b"again".to_vec(), None)
+            .unwrap()
+            .is_none());
+
+        // must match the current content
+        let stale = Arc::new(Hash::new(b"stale".to_vec()));
+        assert!(doc
+            .set_bytes_if(&author, key.clone(), b"second".to_vec(), Some(stale))
+            .unwrap()
+            .is_none());
+        let second = doc
+            .set_bytes_if(&author, key.clone(), b"second".to_vec(), Some(first))
+            .unwrap()
+            .unwrap();
+
+        let entry = doc.get_latest(key).unwrap().unwrap();
+        assert!(second.equal(&entry.content_hash()));
+
+        // concurrent calls for the same key are serialized, so only one of them writes
+        let written = (0..8u8)
+            .map(|i| {
+                let doc = doc.clone();
+                let author = author.clone();
+                std::thread::spawn(move || {
+                    doc.set_bytes_if(&author, b"race".to_vec(), vec![i], None)
+                        .unwrap()
+                        .is_some()
+                })
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .filter(|written| *written)
+            .count();
+        assert_eq!(1, written);
+    }
+
+    #[test]
+    fn test_doc_get_latest() {
+        let path = tempfile::tempdir().unwrap();
+        let node = crate::IrohNode::new(path.path().to_string_lossy().into_owned()).unwrap();
+
+        let doc = node.doc_create(None).unwrap();
+        let author_0 = node.author_create().unwrap();
+        let author_1 = node.author_create().unwrap();
+
+        assert!(doc.get_latest(b"key".to_vec()).unwrap().is_none());
+
+        doc.set_bytes(&author_0, b"key".to_vec(), b"first".to_vec())
+            .unwrap();
+        doc.set_bytes(&author_1, b"key".to_vec(), b"second".to_vec())
+            .unwrap();
+
+        let entry = doc.get_latest(b"key".to_vec()).unwrap().unwrap();
+        assert!(entry.author().equal(&author_1));
+        assert_eq!(b"second".to_vec(), entry.content_bytes(doc).unwrap());
+    }
+
+    #[test]
+    fn test_doc_content_bytes_many() {
+        let path = tempfile::tempdir().unwrap();
+        let node = crate::IrohNode::new(path.path().to_string_lossy().into_owned()).unwrap();
+
+        let doc = node.doc_create(None).unwrap();
+        let author = node.author_create().unwrap();
+
+        let mut entries = vec![];
+        for i in 0..3u8 {
+            let key = vec![i];
+            doc.set_bytes(&author, key.clone(), vec![i; 10]).unwrap();
+            entries.push(doc.get_exact(author.clone(), key, false).unwrap().unwrap());
+        }
+        entries.reverse();
+
+        let contents = doc.content_bytes_many(entries).unwrap();
+        assert_eq!(vec![vec![2u8; 10], vec![1u8; 10], vec![0u8; 10]], contents);
+
+        // lengths that overflow when added up are rejected, not wrapped
+        let hash = Arc::new(Hash::new(b"huge".to_vec()));
+        let mut huge = vec![];
+        for key in [b"huge-0", b"huge-1"] {
+            doc.set_hash(author.clone(), key.to_vec(), hash.clone(), u64::MAX / 2 + 1)
+                .unwrap();
+            huge.push(
+                doc.get_exact(author.clone(), key.to_vec(), false)
+                    .unwrap()
+                    .unwrap(),
+            );
+        }
+        assert!(doc.content_bytes_many(huge).is_err());
+    }
+
+    #[test]
+    fn test_doc_del_exact() {
+        let path = tempfile::tempdir().unwrap();
+        let node = crate::IrohNode::new(path.path().to_string_lossy().into_owned()).unwrap();
+
+        let doc = node.doc_create(None).unwrap();
+        let author = node.author_create().unwrap();
+
+        // nothing to delete
+        assert!(!doc.del_exact(author.clone(), b"user".to_vec()).unwrap());
+
+        doc.set_bytes(&author, b"user".to_vec(), b"a".to_vec())
+            .unwrap();
+        doc.set_bytes(&author, b"user_settings".to_vec(), b"b".to_vec())
+            .unwrap();
+
+        // refuses to clear the sibling sharing the prefix
+        assert!(doc.del_exact(author.clone(), b"user".to_vec()).is_err());
+        assert!(doc
+            .get_exact(author.clone(), b"user".to_vec(), false)
+            .unwrap()
+            .is_some());
+
+        // deletes only the exact key
+        assert!(doc
+            .del_exact(author.clone(), b"user_settings".to_vec())
+            .unwrap());
+        assert!(doc
+            .get_exact(author.clone(), b"user_settings".to_vec(), false)
+            .unwrap()
+            .is_none());
+        assert!(doc
+            .get_exact(author.clone(), b"user".to_vec(), false)
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    fn test_doc_authors() {
+        let path = tempfile::tempdir().unwrap();
+        let node = crate::IrohNode::new(path.path().to_string_lossy().into_owned()).unwrap();
+
+        let doc = node.doc_create(None).unwrap();
+        assert!(doc.authors().unwrap().is_empty());
+
+        let alice = node.author_create().unwrap();
+        let bob = node.author_create().unwrap();
+        doc.set_bytes(&alice, b"a".to_vec(), b"1".to_vec()).unwrap();
+        doc.set_bytes(&alice, b"b".to_vec(), b"2".to_vec()).unwrap();
+        doc.set_bytes(&bob, b"c".to_vec(), b"3".to_vec()).unwrap();
+        doc.del(bob.clone(), b"c".to_vec()).unwrap();
+
+        let mut expected = vec![alice, bob];
+        expected.sort_by_key(|author| author.0);
+        assert_eq!(expected, doc.authors().unwrap());
+    }
+
     #[test]
     fn test_doc_import_export() {
         // create temp file
@@ -1703,14 +4368,14 @@ mod tests {
         let node = crate::IrohNode::new(iroh_dir.path().to_string_lossy().into_owned()).unwrap();
 
         // create doc & author
-        let doc = node.doc_create().unwrap();
+        let doc = node.doc_create(None).unwrap();
         let author = node.author_create().unwrap();
 
         // import file
         let path_str = path.to_string_lossy().into_owned();
         let in_root_str = in_root.to_string_lossy().into_owned();
         let key = crate::path_to_key(path_str.clone(), None, Some(in_root_str)).unwrap();
-        doc.import_file(author.clone(), key.clone(), path_str, true, None)
+        doc.import_file(author.clone(), key.clone(), path_str, true, None, None)
             .unwrap();
 
         // export file
@@ -1725,5 +4390,21 @@ mod tests {
 
         let got_bytes = std::fs::read(path).unwrap();
         assert_eq!(buf, got_bytes);
+
+        // import and get the entry in one call
+        let key = b"imported".to_vec();
+        let entry = doc
+            .import_blob(
+                author.clone(),
+                key.clone(),
+                in_root.join("test").to_string_lossy().into_owned(),
+                false,
+                None,
+                None,
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(key, entry.key());
+        assert_eq!(size as u64, entry.content_len());
     }
 }
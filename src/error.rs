@@ -1,3 +1,7 @@
+use std::sync::Arc;
+
+use crate::Hash;
+
 /// An Error.
 #[derive(Debug, thiserror::Error)]
 #[error("{e:?}")]
@@ -9,6 +13,83 @@ impl IrohError {
     pub fn message(&self) -> String {
         self.to_string()
     }
+
+    /// Whether the call failed because the node was shut down.
+    pub fn is_node_stopped(&self) -> bool {
+        self.e.downcast_ref::<NodeStopped>().is_some()
+    }
+
+    /// Whether creating a node failed because its data directory is used by another node.
+    ///
+    /// Data directories are only locked on unix and windows, elsewhere this is never true.
+    pub fn is_data_dir_locked(&self) -> bool {
+        self.e.downcast_ref::<DataDirLocked>().is_some()
+    }
+
+    /// Whether a read failed because the blob is missing or incomplete on this node.
+    ///
+    /// The content can be fetched from a peer, e.g. with `IrohNode::blobs_download`.
+    pub fn is_content_unavailable(&self) -> bool {
+        self.e.downcast_ref::<ContentUnavailable>().is_some()
+    }
+
+    /// Whether a write failed because this node only has read access to the document.
+    ///
+    /// This is the case for documents joined with a read ticket.
+    pub fn is_permission_denied(&self) -> bool {
+        self.e.downcast_ref::<PermissionDenied>().is_some()
+    }
+
+    /// The hash of the blob that is not available locally, if [`Self::is_content_unavailable`].
+    pub fn content_unavailable_hash(&self) -> Option<Arc<Hash>> {
+        let err = self.e.downcast_ref::<ContentUnavailable>()?;
+        Some(Arc::new(err.hash.into()))
+    }
+}
+
+/// The node a call was made on has been shut down, see [`IrohError::is_node_stopped`].
+#[derive(Debug, thiserror::Error)]
+#[error("the node has been shut down")]
+pub(crate) struct NodeStopped;
+
+/// The data directory of a node is locked by another node, see [`IrohError::is_data_dir_locked`].
+#[derive(Debug, thiserror::Error)]
+#[error(
+    "data directory {} is locked by another process{}",
+    path.display(),
+    pid.map(|pid| format!(" (pid {pid})")).unwrap_or_default()
+)]
+pub(crate) struct DataDirLocked {
+    pub(crate) path: std::path::PathBuf,
+    /// The process holding the lock, if it could be read from the lock file.
+    pub(crate) pid: Option<u32>,
+}
+
+/// A blob could not be read because it is not complete on this node, see
+/// [`IrohError::is_content_unavailable`].
+#[derive(Debug, thiserror::Error)]
+#[error(
+    "content {hash} is {} available locally",
+    if *partial { "only partially" } else { "not" }
+)]
+pub(crate) struct ContentUnavailable {
+    pub(crate) hash: iroh::blobs::Hash,
+    /// Whether some of the content is present.
+    pub(crate) partial: bool,
+}
+
+/// A document could not be written because it is read-only on this node, see
+/// [`IrohError::is_permission_denied`].
+#[derive(Debug, thiserror::Error)]
+#[error("document {namespace} is read-only on this node, it cannot be written to")]
+pub(crate) struct PermissionDenied {
+    pub(crate) namespace: iroh::docs::NamespaceId,
+}
+
+impl From<NodeStopped> for IrohError {
+    fn from(e: NodeStopped) -> Self {
+        IrohError { e: e.into() }
+    }
 }
 
 impl From<anyhow::Error> for IrohError {
@@ -17,10 +98,32 @@ impl From<anyhow::Error> for IrohError {
     }
 }
 
+/// An error returned by a foreign callback.
+///
+/// Operations that report to a callback until they return stop at the first error: with
+/// [`CallbackError::Cancelled`] they return successfully with what was done so far, with
+/// [`CallbackError::Error`] they fail. Subscriptions, which have no caller to fail, end on
+/// either error.
 #[derive(Debug, thiserror::Error, PartialEq, Eq)]
 pub enum CallbackError {
+    /// The callback failed, the operation fails as well.
     #[error("Callback failed")]
     Error,
+    /// The callback asks to stop the operation without an error.
+    #[error("Callback cancelled the operation")]
+    Cancelled,
+}
+
+/// Whether an operation goes on after calling a callback, following the policy described on
+/// [`CallbackError`].
+///
+/// Returns `Ok(false)` if the callback cancelled the operation.
+pub(crate) fn callback_continue(res: Result<(), CallbackError>) -> Result<bool, IrohError> {
+    match res {
+        Ok(()) => Ok(true),
+        Err(CallbackError::Cancelled) => Ok(false),
+        Err(err) => Err(err.into()),
+    }
 }
 
 impl From<CallbackError> for IrohError {
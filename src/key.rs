@@ -1,4 +1,4 @@
-use std::str::FromStr;
+use std::{str::FromStr, sync::Arc};
 
 use serde::{Deserialize, Serialize};
 
@@ -39,7 +39,16 @@ impl PublicKey {
 
     /// Make a PublicKey from base32 string
     pub fn from_string(s: String) -> Result<Self, IrohError> {
-        let key = iroh::net::key::PublicKey::from_str(&s).map_err(anyhow::Error::from)?;
+        let key = iroh::net::key::PublicKey::from_str(&s).map_err(|err| {
+            anyhow::anyhow!("invalid PublicKey {s:?}: {err}, expected {KEY_ENCODINGS}")
+        })?;
+        Ok(key.into())
+    }
+
+    /// Make a PublicKey from a hex string, as printed by some other tools.
+    pub fn from_hex(s: String) -> Result<Self, IrohError> {
+        let bytes = decode_hex_key(&s, "PublicKey")?;
+        let key = iroh::net::key::PublicKey::from_bytes(&bytes).map_err(anyhow::Error::from)?;
         Ok(key.into())
     }
 
@@ -60,6 +69,66 @@ impl PublicKey {
     }
 }
 
+/// The secret key of a node, which determines its [`PublicKey`], the node id.
+///
+/// Create one with [`generate_secret_key`] to know a node's id before the node exists, store
+/// the bytes somewhere secure and pass the key to [`IrohNode::persistent_with_secret_key`]
+/// when creating the node. Anyone holding the key can act as the node, so treat the bytes
+/// like a password.
+///
+/// [`IrohNode::persistent_with_secret_key`]: crate::IrohNode::persistent_with_secret_key
+#[derive(Clone)]
+pub struct SecretKey(pub(crate) iroh::net::key::SecretKey);
+
+impl SecretKey {
+    /// Make a SecretKey from the 32 bytes returned by [`Self::to_bytes`].
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, IrohError> {
+        let bytes: [u8; 32] = bytes
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("the SecretKey must be 32 bytes in length"))?;
+        Ok(SecretKey(iroh::net::key::SecretKey::from_bytes(&bytes)))
+    }
+
+    /// Express the SecretKey as a byte array.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0.to_bytes().to_vec()
+    }
+
+    /// The public key of a node using this secret key, i.e. its node id.
+    pub fn public(&self) -> Arc<PublicKey> {
+        Arc::new(self.0.public().into())
+    }
+}
+
+impl std::fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        // never print the secret itself
+        write!(f, "SecretKey({})", self.0.public().fmt_short())
+    }
+}
+
+/// Generate a new random secret key for a node, see [`SecretKey`].
+pub fn generate_secret_key() -> Arc<SecretKey> {
+    Arc::new(SecretKey(iroh::net::key::SecretKey::generate()))
+}
+
+/// The encodings keys are accepted in, for parse errors.
+pub(crate) const KEY_ENCODINGS: &str =
+    "a base32 string (`from_string`) or a hex string of 64 characters (`from_hex`)";
+
+/// Decode a 32 byte key from hex, ignoring case and surrounding whitespace.
+pub(crate) fn decode_hex_key(s: &str, kind: &str) -> anyhow::Result<[u8; 32]> {
+    let bytes = data_encoding::HEXLOWER_PERMISSIVE
+        .decode(s.trim().as_bytes())
+        .map_err(|err| anyhow::anyhow!("invalid {kind} {s:?}: {err}, expected {KEY_ENCODINGS}"))?;
+    bytes.try_into().map_err(|bytes: Vec<u8>| {
+        anyhow::anyhow!(
+            "invalid {kind} {s:?}: decoded to {} bytes instead of 32, expected {KEY_ENCODINGS}",
+            bytes.len()
+        )
+    })
+}
+
 impl PartialEq for PublicKey {
     fn eq(&self, other: &PublicKey) -> bool {
         self.key == other.key
@@ -101,5 +170,30 @@ mod tests {
         // test that the eq function works
         assert!(key.equal(&key_0));
         assert!(key_0.equal(&key));
+        //
+        // create key from hex, in any case
+        let hex = data_encoding::HEXUPPER.encode(bytes);
+        let key_1 = PublicKey::from_hex(hex.clone()).unwrap();
+        assert!(key.equal(&key_1));
+        //
+        // parse errors name the accepted encodings
+        let err = PublicKey::from_string(hex).unwrap_err();
+        assert!(err.message().contains("from_hex"));
+        assert!(PublicKey::from_hex(key_str).is_err());
+    }
+
+    #[test]
+    fn test_secret_key() {
+        let key = generate_secret_key();
+        let bytes = key.to_bytes();
+        assert_eq!(32, bytes.len());
+
+        let key_0 = SecretKey::from_bytes(bytes.clone()).unwrap();
+        assert_eq!(bytes, key_0.to_bytes());
+        assert!(key.public().equal(&key_0.public()));
+        assert!(!key.public().equal(&generate_secret_key().public()));
+        assert!(!format!("{key_0:?}").contains(&data_encoding::HEXLOWER.encode(&bytes)));
+
+        assert!(SecretKey::from_bytes(vec![0; 31]).is_err());
     }
 }
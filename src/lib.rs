@@ -16,6 +16,8 @@ pub use self::node::*;
 pub use self::tag::*;
 pub use self::ticket::*;
 
+use std::sync::Arc;
+
 use futures::Future;
 use iroh::metrics::try_init_metrics_collection;
 
@@ -73,6 +75,66 @@ fn block_on<F: Future<Output = T>, T>(rt: &tokio::runtime::Handle, fut: F) -> T
     })
 }
 
+/// Number of events a progress stream buffers before the operation waits for them to be pulled.
+const PROGRESS_STREAM_CAPACITY: usize = 64;
+
+/// Receiving end of the events of an operation running in the background.
+///
+/// Backs the pull-based progress streams, e.g. [`AddProgressStream`]. The operation is cancelled
+/// once the receiver is dropped.
+pub(crate) struct ProgressReceiver<T> {
+    rx: tokio::sync::Mutex<tokio::sync::mpsc::Receiver<Result<Arc<T>, IrohError>>>,
+}
+
+/// Sending end of a [`ProgressReceiver`], passed to the operation as its callback.
+pub(crate) struct ProgressSender<T> {
+    tx: tokio::sync::mpsc::Sender<Result<Arc<T>, IrohError>>,
+}
+
+impl<T: Send + Sync + 'static> ProgressReceiver<T> {
+    /// Run `op` on a blocking thread of `rt`, forwarding the events it sends.
+    ///
+    /// If `op` fails, its error is the last item of the stream.
+    pub(crate) fn spawn<F>(rt: &tokio::runtime::Handle, op: F) -> Self
+    where
+        F: FnOnce(Arc<ProgressSender<T>>) -> Result<(), IrohError> + Send + 'static,
+    {
+        let (tx, rx) = tokio::sync::mpsc::channel(PROGRESS_STREAM_CAPACITY);
+        let sender = Arc::new(ProgressSender { tx: tx.clone() });
+        rt.spawn_blocking(move || {
+            if let Err(err) = op(sender) {
+                tx.blocking_send(Err(err)).ok();
+            }
+        });
+        ProgressReceiver {
+            rx: tokio::sync::Mutex::new(rx),
+        }
+    }
+
+    /// Wait for the next event, `None` once the operation has finished.
+    ///
+    /// Does not block the calling thread, and does not need to be polled on a tokio runtime.
+    pub(crate) async fn next(&self) -> Result<Option<Arc<T>>, IrohError> {
+        let mut rx = self.rx.lock().await;
+        match rx.recv().await {
+            Some(Ok(event)) => Ok(Some(event)),
+            Some(Err(err)) => Err(err),
+            None => Ok(None),
+        }
+    }
+}
+
+impl<T> ProgressSender<T> {
+    /// Pass `event` on, waiting while the stream is full.
+    ///
+    /// Fails with [`CallbackError::Cancelled`] once the stream was dropped.
+    pub(crate) fn send(&self, event: Arc<T>) -> Result<(), CallbackError> {
+        // operations call this from within their own `block_on`, where tokio's
+        // `blocking_send` would panic
+        futures::executor::block_on(self.tx.send(Ok(event))).map_err(|_| CallbackError::Cancelled)
+    }
+}
+
 /// Helper function that translates a key that was derived from the [`path_to_key`] function back
 /// into a path.
 ///
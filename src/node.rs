@@ -1,4 +1,14 @@
-use std::{collections::HashMap, path::PathBuf, sync::Arc, time::Duration};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{Read, Seek, Write},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 
 use futures::stream::TryStreamExt;
 use iroh::{
@@ -6,7 +16,17 @@ use iroh::{
     node::{Builder, FsNode},
 };
 
-use crate::{block_on, IrohError, NodeAddr, PublicKey};
+use crate::{
+    blob::{
+        sweep_expired_pins, sweep_hard_links, sweep_store_size, BlobAccessLog, BlobMetadataStore,
+        DownloadRegistry, DownloadSourceStore, HardLinkStore,
+    },
+    block_on,
+    doc::KeyLocks,
+    error::{DataDirLocked, NodeStopped},
+    ticket::AddrInfoOptions,
+    CallbackError, IrohError, NodeAddr, PublicKey, SecretKey,
+};
 
 /// Stats counter
 /// Counter stats
@@ -111,7 +131,7 @@ pub enum ConnType {
 }
 
 /// The type of connection we have to the node
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 pub enum ConnectionType {
     /// Direct UDP connection
     Direct(String),
@@ -165,6 +185,121 @@ impl ConnectionType {
     }
 }
 
+/// How often [`IrohNode::watch_peer`] checks the connection to the peer.
+const PEER_WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The `changed` method is called by [`IrohNode::watch_peer`] with the type of the connection
+/// to the watched peer, first with the current type and then whenever it changes.
+pub trait ConnectionChangeCallback: Send + Sync + 'static {
+    fn changed(&self, conn_type: Arc<ConnectionType>) -> Result<(), CallbackError>;
+}
+
+/// A running [`IrohNode::watch_peer`], ends when cancelled or dropped.
+#[derive(Debug, Default)]
+pub struct PeerWatch {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl PeerWatch {
+    /// Stop watching. The callback is not called again, except for a change that is being
+    /// delivered right now.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+}
+
+impl Drop for PeerWatch {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+/// The metrics counters of the bytes the endpoint sends, over UDP and through relays.
+const SENT_BYTES_COUNTERS: [&str; 3] = ["send_ipv4", "send_ipv6", "send_relay"];
+
+/// The metrics counters of the bytes the endpoint receives, over UDP and through relays.
+const RECEIVED_BYTES_COUNTERS: [&str; 3] = ["recv_data_ipv4", "recv_data_ipv6", "recv_data_relay"];
+
+/// The `throughput` method is called by [`IrohNode::subscribe_throughput`] once per interval.
+pub trait ThroughputCallback: Send + Sync + 'static {
+    fn throughput(&self, throughput: Throughput) -> Result<(), CallbackError>;
+}
+
+/// The aggregate transfer rates of a node, see [`IrohNode::subscribe_throughput`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Throughput {
+    /// Bytes sent per second, averaged over the interval.
+    pub upload_bytes_per_sec: u64,
+    /// Bytes received per second, averaged over the interval.
+    pub download_bytes_per_sec: u64,
+}
+
+/// A running [`IrohNode::subscribe_throughput`], ends when cancelled or dropped.
+#[derive(Debug, Default)]
+pub struct ThroughputSubscription {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl ThroughputSubscription {
+    /// Stop sampling. The callback is not called again, except for a sample that is being
+    /// delivered right now.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+}
+
+impl Drop for ThroughputSubscription {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+/// How often [`FailureLog`] logs the failures of a polling task at most.
+const FAILURE_LOG_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Logs the repeated failures of a polling task, at most once per [`FAILURE_LOG_INTERVAL`].
+///
+/// A task polling every few milliseconds would otherwise flood the log while the node is
+/// unreachable.
+#[derive(Debug, Default)]
+pub(crate) struct FailureLog {
+    last: Option<Instant>,
+    suppressed: u64,
+}
+
+impl FailureLog {
+    pub(crate) fn warn(&mut self, what: &str, err: &impl std::fmt::Debug) {
+        let now = Instant::now();
+        if self
+            .last
+            .is_some_and(|last| now.duration_since(last) < FAILURE_LOG_INTERVAL)
+        {
+            self.suppressed += 1;
+            return;
+        }
+        tracing::warn!(
+            "{what} failed, {} failures since the last warning: {:?}",
+            self.suppressed,
+            err
+        );
+        self.last = Some(now);
+        self.suppressed = 0;
+    }
+}
+
+/// The bytes sent and received by the endpoints of this process so far.
+async fn transferred_bytes(client: &MemIroh) -> anyhow::Result<(u64, u64)> {
+    let stats = client.stats().await?;
+    let sum = |counters: &[&str]| {
+        counters
+            .iter()
+            .filter_map(|name| stats.get(*name))
+            .map(|counter| counter.value)
+            .sum::<u64>()
+    };
+    Ok((sum(&SENT_BYTES_COUNTERS), sum(&RECEIVED_BYTES_COUNTERS)))
+}
+
 /// The socket address and url of the mixed connection
 pub struct ConnectionTypeMixed {
     /// Address of the node
@@ -189,11 +324,59 @@ impl From<iroh::net::endpoint::ConnectionType> for ConnectionType {
         }
     }
 }
+/// Worker threads of the node's runtime if [`NodeOptions::runtime_worker_threads`] is not set.
+const DEFAULT_RUNTIME_WORKER_THREADS: usize = 2;
+
 /// Options passed to [`IrohNode.new`]. Controls the behaviour of an iroh node.
 pub struct NodeOptions {
     /// How frequently the blob store should clean up unreferenced blobs, in milliseconds.
     /// Set to 0 to disable gc
     pub gc_interval_millis: Option<u64>,
+    /// Maximum size in bytes of a single blob downloaded with `blobs_download`.
+    ///
+    /// A download started through `blobs_download`, `blobs_download_stream` or
+    /// `ResumableDownload::resume` is aborted as soon as a peer announces a blob larger than
+    /// this. `None` means no limit.
+    ///
+    /// This is not a general limit on incoming data: content fetched by document sync or by
+    /// `Doc::download_all` is downloaded by iroh directly and is not checked, since iroh 0.19
+    /// offers no hook into its downloader.
+    pub max_download_blob_size: Option<u64>,
+    /// Maximum content size in bytes of a document entry written on this node.
+    ///
+    /// Bounds the size of the content an entry references, not the size of the entry itself.
+    /// Applies to local writes: `Doc::set_bytes`, `Doc::set_bytes_if`, `Doc::set_hash` and
+    /// file imports into a doc fail with an error past the limit. Entries received from peers
+    /// are not checked, iroh does not offer a way to reject them. `None` means no limit.
+    pub max_doc_value_size: Option<u64>,
+    /// Number of worker threads of the tokio runtime the node creates for itself.
+    ///
+    /// Must be at least 1. Defaults to 2 if `None`, which is enough for most apps; raise it only
+    /// for nodes serving many peers, since idle threads still cost battery on mobile.
+    pub runtime_worker_threads: Option<u32>,
+    /// Human readable label of the node, e.g. to tell nodes apart in a fleet.
+    ///
+    /// Purely informational and reported by [`IrohNode::status`]. The label stays local: the
+    /// discovery services of iroh only publish the relay url and direct addresses of a node.
+    pub node_label: Option<String>,
+    /// Maximum total size in bytes of the complete blobs in the store.
+    ///
+    /// Once the store grows past this, the least recently used blobs are evicted until it fits
+    /// again, turning the store into a bounded cache. Blobs are used when they are added, read
+    /// or exported through this node; serving them to peers does not count. Only blobs that are
+    /// untagged or only have automatically generated tags are evicted: blobs with a named tag,
+    /// including pins from [`IrohNode::blobs_pin_until`], children of a tagged collection and
+    /// the content of document entries are never evicted, so the store can stay above the
+    /// limit. Evicting a blob deletes its automatic tags, the ones named `auto-...`; that
+    /// prefix can't be used for named tags. No events are emitted for evicted blobs. The size
+    /// is checked when the node starts and then once a minute. `None` means no limit.
+    pub max_store_bytes: Option<u64>,
+    /// The secret key of the node, which determines its node id.
+    ///
+    /// Takes precedence over the key stored in the data directory, which is left untouched: a
+    /// later start without a key uses the stored key again. `None` uses the stored key, or
+    /// generates and stores a new one on the first start.
+    pub secret_key: Option<Arc<SecretKey>>,
 }
 
 impl From<NodeOptions> for iroh::node::Builder<iroh::blobs::store::mem::Store> {
@@ -217,16 +400,180 @@ impl Default for NodeOptions {
     fn default() -> Self {
         NodeOptions {
             gc_interval_millis: Some(0),
+            max_download_blob_size: None,
+            max_doc_value_size: None,
+            runtime_worker_threads: None,
+            node_label: None,
+            max_store_bytes: None,
+            secret_key: None,
         }
     }
 }
 
 /// An Iroh node. Allows you to sync, store, and transfer data.
+///
+/// The docs and blob batches opened on a node are handles to it, they do not keep it running.
+/// Once the node is shut down with [`IrohNode::shutdown`], calls on the node and on all of its
+/// handles fail with an error for which [`IrohError::is_node_stopped`] is true.
 pub struct IrohNode {
     pub(crate) node: FsNode,
     pub(crate) sync_client: MemIroh,
     #[allow(dead_code)]
     pub(crate) tokio_rt: Option<tokio::runtime::Runtime>,
+    /// When this node was spawned, used to report the uptime.
+    pub(crate) started_at: Instant,
+    /// See [`NodeOptions::max_download_blob_size`].
+    pub(crate) max_download_blob_size: Option<u64>,
+    /// See [`NodeOptions::max_doc_value_size`].
+    pub(crate) max_doc_value_size: Option<u64>,
+    /// See [`NodeOptions::node_label`].
+    pub(crate) node_label: Option<String>,
+    /// Local metadata attached to blobs, see [`IrohNode::blobs_set_metadata`].
+    pub(crate) blob_metadata: Arc<BlobMetadataStore>,
+    /// When blobs were last used, see [`NodeOptions::max_store_bytes`].
+    pub(crate) blob_access: Arc<BlobAccessLog>,
+    /// Imports done with [`ImportStrategy::HardLink`](crate::ImportStrategy::HardLink).
+    pub(crate) hard_links: Arc<HardLinkStore>,
+    /// Keys locked by [`Doc::set_bytes_if`](crate::Doc::set_bytes_if), shared with the docs.
+    pub(crate) key_locks: Arc<KeyLocks>,
+    /// Downloads currently running through [`IrohNode::blobs_download`].
+    pub(crate) downloads: DownloadRegistry,
+    /// Where unfinished downloads get their data from, see
+    /// [`IrohNode::blobs_resumable_downloads`].
+    pub(crate) download_sources: DownloadSourceStore,
+    /// Whether the node is running, shared with the handles opened on it.
+    pub(crate) state: NodeState,
+    /// Exclusive lock on the data directory, held as long as the node exists.
+    #[allow(dead_code)]
+    pub(crate) lock: DataDirLock,
+}
+
+/// How long sharing helpers such as [`IrohNode::blobs_add_and_share`] wait for the node's
+/// addresses.
+const SHARE_ONLINE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often sharing helpers check the node's addresses.
+const SHARE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Name of the lock file in the data directory of a node.
+const LOCK_FILE_NAME: &str = "iroh-ffi.lock";
+
+/// An exclusive lock on the data directory of a node, held until dropped.
+///
+/// Two nodes using the same directory would corrupt each other's stores, so a node fails to
+/// start with [`DataDirLocked`] while another node, in this or another process, holds the lock.
+/// The lock file contains the pid of the process holding the lock.
+///
+/// On unix the lock is an advisory `flock` on the lock file. On windows the lock file is
+/// opened without sharing, so the pid of the holder cannot be read there. On other platforms
+/// the directory is not locked.
+#[derive(Debug)]
+pub(crate) struct DataDirLock {
+    _file: File,
+}
+
+impl DataDirLock {
+    pub(crate) fn acquire(dir: &std::path::Path) -> anyhow::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let mut opts = std::fs::OpenOptions::new();
+        opts.read(true).write(true).create(true).truncate(false);
+        #[cfg(windows)]
+        {
+            use std::os::windows::fs::OpenOptionsExt;
+            // no other handle, in this or another process, may open the file while it is open
+            opts.share_mode(0);
+        }
+        let mut file = match opts.open(dir.join(LOCK_FILE_NAME)) {
+            Ok(file) => file,
+            Err(err) if is_sharing_violation(&err) => {
+                return Err(DataDirLocked {
+                    path: dir.to_path_buf(),
+                    pid: None,
+                }
+                .into());
+            }
+            Err(err) => return Err(err.into()),
+        };
+        if !try_lock(&file)? {
+            let mut content = String::new();
+            file.read_to_string(&mut content).ok();
+            return Err(DataDirLocked {
+                path: dir.to_path_buf(),
+                pid: content.trim().parse().ok(),
+            }
+            .into());
+        }
+        file.set_len(0)?;
+        file.rewind()?;
+        write!(file, "{}", std::process::id())?;
+        file.sync_all()?;
+        Ok(DataDirLock { _file: file })
+    }
+}
+
+/// Take an exclusive, advisory lock on `file`, returns false if it is locked already.
+#[cfg(unix)]
+fn try_lock(file: &File) -> std::io::Result<bool> {
+    use std::os::unix::io::AsRawFd;
+
+    // SAFETY: the file descriptor is valid for as long as `file` is borrowed.
+    let res = unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) };
+    if res == 0 {
+        return Ok(true);
+    }
+    let err = std::io::Error::last_os_error();
+    if err.kind() == std::io::ErrorKind::WouldBlock {
+        Ok(false)
+    } else {
+        Err(err)
+    }
+}
+
+/// On windows the file is locked by opening it without sharing, elsewhere locking is not
+/// supported, so this always succeeds.
+#[cfg(not(unix))]
+fn try_lock(_file: &File) -> std::io::Result<bool> {
+    Ok(true)
+}
+
+/// Whether opening a file failed because another handle opened it without sharing.
+#[cfg(windows)]
+fn is_sharing_violation(err: &std::io::Error) -> bool {
+    /// `ERROR_SHARING_VIOLATION` of the windows api.
+    const ERROR_SHARING_VIOLATION: i32 = 32;
+    err.raw_os_error() == Some(ERROR_SHARING_VIOLATION)
+}
+
+/// Files are only opened without sharing on windows.
+#[cfg(not(windows))]
+fn is_sharing_violation(_err: &std::io::Error) -> bool {
+    false
+}
+
+/// Whether a node is still running, shared between the node and the handles opened on it.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct NodeState {
+    stopped: Arc<AtomicBool>,
+}
+
+impl NodeState {
+    pub(crate) fn is_running(&self) -> bool {
+        !self.stopped.load(Ordering::SeqCst)
+    }
+
+    /// Mark the node as stopped, returns whether it was running before.
+    fn stop(&self) -> bool {
+        !self.stopped.swap(true, Ordering::SeqCst)
+    }
+
+    /// Fail with [`NodeStopped`] once the node was shut down.
+    pub(crate) fn ensure_running(&self) -> Result<(), IrohError> {
+        if self.is_running() {
+            Ok(())
+        } else {
+            Err(NodeStopped.into())
+        }
+    }
 }
 
 impl IrohNode {
@@ -239,16 +586,47 @@ impl IrohNode {
 
     /// Create a new iroh node. The `path` param should be a directory where we can store or load
     /// iroh data from a previous session.
+    ///
+    /// Only one node can use a directory at a time: while another node, in this or another
+    /// process, uses `path`, this fails with an error for which
+    /// [`IrohError::is_data_dir_locked`] is true. The directory is locked on unix and windows
+    /// only, on other platforms two nodes sharing a directory are not detected.
     pub fn new(path: String) -> Result<Self, IrohError> {
         let options = NodeOptions::default();
         Self::with_options(path, options)
     }
 
+    /// Create a new iroh node that uses `secret_key` as its identity.
+    ///
+    /// Shortcut for [`Self::with_options`] with [`NodeOptions::secret_key`] set, so the node
+    /// id is the public key of `secret_key`, see [`SecretKey`].
+    pub fn persistent_with_secret_key(
+        path: String,
+        secret_key: Arc<SecretKey>,
+    ) -> Result<Self, IrohError> {
+        let options = NodeOptions {
+            secret_key: Some(secret_key),
+            ..Default::default()
+        };
+        Self::with_options(path, options)
+    }
+
     /// Create a new iroh node with options.
+    ///
+    /// The node runs on a multi-threaded tokio runtime it creates for itself, with
+    /// [`NodeOptions::runtime_worker_threads`] worker threads. Calls into the node block the
+    /// calling thread until they complete on that runtime.
     pub fn with_options(path: String, options: NodeOptions) -> Result<Self, IrohError> {
+        let worker_threads = match options.runtime_worker_threads {
+            None => DEFAULT_RUNTIME_WORKER_THREADS,
+            Some(0) => {
+                return Err(anyhow::anyhow!("runtime_worker_threads must be at least 1").into())
+            }
+            Some(threads) => threads as usize,
+        };
         let tokio_rt = tokio::runtime::Builder::new_multi_thread()
             .thread_name("main-runtime")
-            .worker_threads(2)
+            .worker_threads(worker_threads)
             .enable_all()
             .build()
             .map_err(anyhow::Error::from)?;
@@ -267,14 +645,76 @@ impl IrohNode {
         options: NodeOptions,
         tokio_rt: Option<tokio::runtime::Runtime>,
     ) -> Result<Self, anyhow::Error> {
+        let lock = DataDirLock::acquire(&path)?;
+        let blob_metadata = Arc::new(BlobMetadataStore::load(&path)?);
+        let blob_access = Arc::new(BlobAccessLog::load(&path)?);
+        let download_sources = DownloadSourceStore::load(&path)?;
+        let max_store_bytes = options.max_store_bytes;
+        let secret_key = options.secret_key.clone();
+        let hard_links = Arc::new(HardLinkStore::load(&path)?);
+        let max_download_blob_size = options.max_download_blob_size;
+        let max_doc_value_size = options.max_doc_value_size;
+        let node_label = options.node_label.clone();
         let builder: Builder<iroh::blobs::store::mem::Store> = options.into();
-        let node = builder.persist(path).await?.spawn().await?;
+        let mut builder = builder.persist(path).await?;
+        if let Some(secret_key) = secret_key {
+            builder = builder.secret_key(secret_key.0.clone());
+        }
+        let node = builder.spawn().await?;
         let sync_client = node.clone().client().clone();
+        let state = NodeState::default();
+        tokio::spawn(sweep_expired_pins(sync_client.clone(), state.clone()));
+        tokio::spawn(sweep_hard_links(
+            sync_client.clone(),
+            hard_links.clone(),
+            state.clone(),
+        ));
+        if let Some(max_bytes) = max_store_bytes {
+            tokio::spawn(sweep_store_size(
+                sync_client.clone(),
+                blob_access.clone(),
+                blob_metadata.clone(),
+                max_bytes,
+                state.clone(),
+            ));
+        }
 
         Ok(IrohNode {
             node,
             sync_client,
             tokio_rt,
+            started_at: Instant::now(),
+            max_download_blob_size,
+            max_doc_value_size,
+            node_label,
+            blob_metadata,
+            blob_access,
+            hard_links,
+            key_locks: Arc::default(),
+            downloads: DownloadRegistry::default(),
+            download_sources,
+            state,
+            lock,
+        })
+    }
+
+    /// Whether this node is running, i.e. has not been shut down.
+    pub fn is_running(&self) -> bool {
+        self.state.is_running()
+    }
+
+    /// Shut down this node.
+    ///
+    /// Afterwards, all calls on this node and on the docs and batches opened on it fail with an
+    /// error for which [`IrohError::is_node_stopped`] is true. Shutting down a node that was
+    /// already shut down does nothing.
+    pub fn shutdown(&self) -> Result<(), IrohError> {
+        if !self.state.stop() {
+            return Ok(());
+        }
+        block_on(&self.rt(), async {
+            self.node.clone().shutdown().await?;
+            Ok(())
         })
     }
 
@@ -285,6 +725,7 @@ impl IrohNode {
 
     /// Get statistics of the running node.
     pub fn stats(&self) -> Result<HashMap<String, CounterStats>, IrohError> {
+        self.state.ensure_running()?;
         block_on(&self.rt(), async {
             let stats = self.sync_client.stats().await?;
             Ok(stats
@@ -304,6 +745,7 @@ impl IrohNode {
 
     /// Return `ConnectionInfo`s for each connection we have to another iroh node.
     pub fn connections(&self) -> Result<Vec<ConnectionInfo>, IrohError> {
+        self.state.ensure_running()?;
         block_on(&self.rt(), async {
             let infos = self
                 .sync_client
@@ -321,6 +763,7 @@ impl IrohNode {
         &self,
         node_id: &PublicKey,
     ) -> Result<Option<ConnectionInfo>, IrohError> {
+        self.state.ensure_running()?;
         block_on(&self.rt(), async {
             let info = self
                 .sync_client
@@ -331,38 +774,349 @@ impl IrohNode {
         })
     }
 
+    /// The home relay url of a peer, if this node knows it.
+    ///
+    /// The relay is taken from the address information the endpoint has for the peer, learned
+    /// from connections, discovery or [`Self::add_node_addr`]. Comparing it with the relay of
+    /// this node, see [`NodeStatus::node_addr`], shows if traffic relayed between both nodes
+    /// goes through two different relays.
+    pub fn remote_relay(&self, node_id: Arc<PublicKey>) -> Result<Option<String>, IrohError> {
+        Ok(self
+            .connection_info(&node_id)?
+            .and_then(|info| info.relay_url))
+    }
+
+    /// Watch the connection to a single peer.
+    ///
+    /// `cb.changed` is called with the current connection type to `node_id` right away, and
+    /// again whenever the connection type changes. A type of `ConnType::None` means that
+    /// there is no connection to the peer, also if the peer is not known to this node.
+    ///
+    /// The connection is checked every 500 milliseconds, so short-lived changes in between
+    /// may not be reported. Watching ends when the returned watch is cancelled or dropped, when
+    /// the callback returns an error, see `CallbackError`, or when the node is shut down.
+    pub fn watch_peer(
+        &self,
+        node_id: Arc<PublicKey>,
+        cb: Arc<dyn ConnectionChangeCallback>,
+    ) -> Result<Arc<PeerWatch>, IrohError> {
+        self.state.ensure_running()?;
+        let client = self.sync_client.clone();
+        let state = self.state.clone();
+        let watch = Arc::new(PeerWatch::default());
+        let cancelled = watch.cancelled.clone();
+        let node_id: iroh::net::key::PublicKey = (&*node_id).into();
+        self.rt().spawn(async move {
+            let mut last = None;
+            let mut failures = FailureLog::default();
+            while state.is_running() && !cancelled.load(Ordering::SeqCst) {
+                match client.connection_info(node_id).await {
+                    Ok(info) => {
+                        let current = info
+                            .map(|info| info.conn_type.into())
+                            .unwrap_or(ConnectionType::None);
+                        if last.as_deref() != Some(&current) {
+                            let current = Arc::new(current);
+                            if let Err(err) = cb.changed(current.clone()) {
+                                if err != CallbackError::Cancelled {
+                                    tracing::warn!("callback failed, ending peer watch: {:?}", err);
+                                }
+                                return;
+                            }
+                            last = Some(current);
+                        }
+                    }
+                    Err(err) => failures.warn("peer watch rpc", &err),
+                }
+                tokio::time::sleep(PEER_WATCH_INTERVAL).await;
+            }
+        });
+        Ok(watch)
+    }
+
+    /// Report the aggregate upload and download rate of the node every `interval_millis`.
+    ///
+    /// `cb.throughput` is called once per interval with the bytes sent and received per
+    /// second, averaged over that interval: the sampling window is the interval itself, a
+    /// short interval gives spiky rates, a long one smooth but delayed rates. The rates count
+    /// all traffic of the endpoint, blobs as well as document sync and gossip, including
+    /// protocol overhead.
+    ///
+    /// The rates are computed from the global metrics, so [`start_metrics_collection`] must be
+    /// called first, otherwise this fails. As the metrics are process wide, the rates are the
+    /// sum over all nodes in the process.
+    ///
+    /// Sampling ends when the returned subscription is cancelled or dropped, when the callback
+    /// returns an error, see `CallbackError`, or when the node is shut down.
+    ///
+    /// [`start_metrics_collection`]: crate::start_metrics_collection
+    pub fn subscribe_throughput(
+        &self,
+        interval_millis: u64,
+        cb: Arc<dyn ThroughputCallback>,
+    ) -> Result<Arc<ThroughputSubscription>, IrohError> {
+        if interval_millis == 0 {
+            return Err(
+                anyhow::anyhow!("throughput interval must be at least 1 millisecond").into(),
+            );
+        }
+        self.state.ensure_running()?;
+        let client = self.sync_client.clone();
+        let mut last = block_on(&self.rt(), transferred_bytes(&client))?;
+        let subscription = Arc::new(ThroughputSubscription::default());
+        let cancelled = subscription.cancelled.clone();
+        let state = self.state.clone();
+        let interval = Duration::from_millis(interval_millis);
+        self.rt().spawn(async move {
+            let mut last_at = Instant::now();
+            let mut failures = FailureLog::default();
+            loop {
+                tokio::time::sleep(interval).await;
+                if !state.is_running() || cancelled.load(Ordering::SeqCst) {
+                    return;
+                }
+                let current = match transferred_bytes(&client).await {
+                    Ok(current) => current,
+                    Err(err) => {
+                        failures.warn("throughput sampling", &err);
+                        continue;
+                    }
+                };
+                let now = Instant::now();
+                let secs = now.duration_since(last_at).as_secs_f64();
+                let rate =
+                    |current: u64, last: u64| (current.saturating_sub(last) as f64 / secs) as u64;
+                let throughput = Throughput {
+                    upload_bytes_per_sec: rate(current.0, last.0),
+                    download_bytes_per_sec: rate(current.1, last.1),
+                };
+                (last, last_at) = (current, now);
+                if let Err(err) = cb.throughput(throughput) {
+                    if err != CallbackError::Cancelled {
+                        tracing::warn!(
+                            "callback failed, ending throughput subscription: {:?}",
+                            err
+                        );
+                    }
+                    return;
+                }
+            }
+        });
+        Ok(subscription)
+    }
+
+    /// Add addressing information for a node to the endpoint's address book.
+    ///
+    /// Subsequent connections and syncs to this node id can use the given relay url and direct
+    /// addresses without relying on discovery. Information for a node id that is already known
+    /// is merged with the existing information, not replaced.
+    pub fn add_node_addr(&self, addr: Arc<NodeAddr>) -> Result<(), IrohError> {
+        let addr: iroh::net::endpoint::NodeAddr = (*addr).clone().try_into()?;
+        self.state.ensure_running()?;
+        block_on(&self.rt(), async {
+            self.sync_client.add_node_addr(addr).await?;
+            Ok(())
+        })
+    }
+
     /// Get status information about a node
     pub fn status(&self) -> Result<Arc<NodeStatus>, IrohError> {
+        self.state.ensure_running()?;
         block_on(&self.rt(), async {
-            let res = self
-                .sync_client
-                .status()
-                .await
-                .map(|n| Arc::new(n.into()))?;
-            Ok(res)
+            let status = self.sync_client.status().await?;
+            Ok(Arc::new(NodeStatus {
+                status,
+                uptime: self.started_at.elapsed(),
+                label: self.node_label.clone(),
+            }))
+        })
+    }
+
+    /// Wait until this node knows the addresses a ticket with `options` includes.
+    ///
+    /// Errors if they are still missing after [`SHARE_ONLINE_TIMEOUT`].
+    pub(crate) async fn wait_for_ticket_addrs(
+        &self,
+        options: AddrInfoOptions,
+    ) -> anyhow::Result<()> {
+        let online = async {
+            loop {
+                let info = self.sync_client.status().await?.addr.info;
+                let ready = match options {
+                    AddrInfoOptions::Id => true,
+                    AddrInfoOptions::Relay => info.relay_url.is_some(),
+                    AddrInfoOptions::Addresses => !info.direct_addresses.is_empty(),
+                    AddrInfoOptions::RelayAndAddresses => {
+                        info.relay_url.is_some() || !info.direct_addresses.is_empty()
+                    }
+                };
+                if ready {
+                    return anyhow::Ok(());
+                }
+                tokio::time::sleep(SHARE_POLL_INTERVAL).await;
+            }
+        };
+        tokio::time::timeout(SHARE_ONLINE_TIMEOUT, online)
+            .await
+            .map_err(|_| {
+                anyhow::anyhow!(
+                    "node has no addresses for a {options:?} ticket after {:?}",
+                    SHARE_ONLINE_TIMEOUT
+                )
+            })?
+    }
+
+    /// The ALPNs of the protocols this node accepts connections for.
+    ///
+    /// These are the built-in blobs, gossip and docs protocols; registering custom protocols is
+    /// not supported by these bindings.
+    pub fn supported_protocols(&self) -> Vec<Vec<u8>> {
+        [
+            iroh::blobs::protocol::ALPN,
+            iroh::gossip::net::GOSSIP_ALPN,
+            iroh::docs::net::DOCS_ALPN,
+        ]
+        .iter()
+        .map(|alpn| alpn.to_vec())
+        .collect()
+    }
+
+    /// Whether other nodes can currently connect to this node, and how.
+    ///
+    /// This is derived from the addresses this node currently advertises: if one of its
+    /// direct addresses is a public IP address, discovered either from a local interface or
+    /// through the relay servers' STUN, the node is considered [`Reachability::DirectlyReachable`].
+    /// Behind NATs that do not keep the mapped port stable for all peers, this may be too
+    /// optimistic, in which case connections fall back to the relay. With only a relay url, the
+    /// node is [`Reachability::RelayOnly`], and without either [`Reachability::Unknown`].
+    ///
+    /// The result changes as the network of the node changes and address discovery runs, so
+    /// query it again instead of caching it.
+    pub fn reachability(&self) -> Result<Reachability, IrohError> {
+        self.state.ensure_running()?;
+        block_on(&self.rt(), async {
+            let status = self.sync_client.status().await?;
+            Ok(reachability_of(&status.addr.info))
+        })
+    }
+
+    /// Inspect what a ticket created with `options` would contain right now.
+    ///
+    /// Reports whether the ticket would include a relay url, how many public and private
+    /// direct addresses it would include, and the [`Reachability`] those addresses give
+    /// recipients, like [`Self::reachability`] does for all of the node's addresses. With
+    /// [`AddrInfoOptions::Id`] the ticket contains no addresses and recipients depend on
+    /// discovery, so the reachability is [`Reachability::Unknown`].
+    pub fn evaluate_share(&self, options: AddrInfoOptions) -> Result<ShareAssessment, IrohError> {
+        self.state.ensure_running()?;
+        block_on(&self.rt(), async {
+            let status = self.sync_client.status().await?;
+            let mut info = status.addr.info;
+            match options {
+                AddrInfoOptions::Id => {
+                    info.relay_url = None;
+                    info.direct_addresses.clear();
+                }
+                AddrInfoOptions::Relay => info.direct_addresses.clear(),
+                AddrInfoOptions::Addresses => info.relay_url = None,
+                AddrInfoOptions::RelayAndAddresses => {}
+            }
+            let public_addresses = info
+                .direct_addresses
+                .iter()
+                .filter(|addr| is_public(addr.ip()))
+                .count() as u32;
+            Ok(ShareAssessment {
+                includes_relay: info.relay_url.is_some(),
+                public_addresses,
+                private_addresses: info.direct_addresses.len() as u32 - public_addresses,
+                reachability: reachability_of(&info),
+            })
         })
     }
 }
 
-/// The response to a status request
-#[derive(Debug)]
-pub struct NodeStatus(iroh::client::NodeStatus);
+/// The [`Reachability`] given by the addresses in `info`.
+fn reachability_of(info: &iroh::base::node_addr::AddrInfo) -> Reachability {
+    if info
+        .direct_addresses
+        .iter()
+        .any(|addr| is_public(addr.ip()))
+    {
+        Reachability::DirectlyReachable
+    } else if info.relay_url.is_some() {
+        Reachability::RelayOnly
+    } else {
+        Reachability::Unknown
+    }
+}
+
+/// What a ticket would contain if it was shared now, see [`IrohNode::evaluate_share`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShareAssessment {
+    /// Whether the ticket would include the relay url of this node
+    pub includes_relay: bool,
+    /// The number of public direct addresses the ticket would include
+    pub public_addresses: u32,
+    /// The number of private direct addresses, e.g. LAN addresses, the ticket would include
+    pub private_addresses: u32,
+    /// How recipients of the ticket could reach this node
+    pub reachability: Reachability,
+}
 
-impl From<iroh::client::NodeStatus> for NodeStatus {
-    fn from(n: iroh::client::NodeStatus) -> Self {
-        NodeStatus(n)
+/// Whether other nodes can connect to this node, see [`IrohNode::reachability`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reachability {
+    /// Other nodes can connect to one of this node's public addresses directly.
+    DirectlyReachable,
+    /// Other nodes can only reach this node through its relay server.
+    RelayOnly,
+    /// Neither a public address nor a relay server is known for this node (yet).
+    Unknown,
+}
+
+/// Whether `ip` is routable on the public internet.
+fn is_public(ip: std::net::IpAddr) -> bool {
+    match ip {
+        std::net::IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            // 100.64.0.0/10 is shared address space used by carrier grade NATs
+            let shared = a == 100 && (b & 0b1100_0000) == 64;
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || shared)
+        }
+        std::net::IpAddr::V6(ip) => {
+            let first = ip.segments()[0];
+            // fc00::/7 unique local and fe80::/10 link local addresses
+            let unique_local = (first & 0xfe00) == 0xfc00;
+            let link_local = (first & 0xffc0) == 0xfe80;
+            !(ip.is_loopback() || ip.is_unspecified() || unique_local || link_local)
+        }
     }
 }
 
+/// The response to a status request
+#[derive(Debug)]
+pub struct NodeStatus {
+    status: iroh::client::NodeStatus,
+    uptime: Duration,
+    label: Option<String>,
+}
+
 impl NodeStatus {
     /// The node id and socket addresses of this node.
     pub fn node_addr(&self) -> Arc<NodeAddr> {
-        Arc::new(self.0.addr.clone().into())
+        Arc::new(self.status.addr.clone().into())
     }
 
     /// The bound listening addresses of the node
     pub fn listen_addrs(&self) -> Vec<String> {
-        self.0
+        self.status
             .listen_addrs
             .iter()
             .map(|addr| addr.to_string())
@@ -371,6 +1125,273 @@ impl NodeStatus {
 
     /// The version of the node
     pub fn version(&self) -> String {
-        self.0.version.clone()
+        self.status.version.clone()
+    }
+
+    /// How long the node has been running, in seconds
+    pub fn uptime_seconds(&self) -> u64 {
+        self.uptime.as_secs()
+    }
+
+    /// The label set with [`NodeOptions::node_label`], if any.
+    pub fn node_label(&self) -> Option<String> {
+        self.label.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_public() {
+        for ip in ["1.1.1.1", "2a01:4f8::1"] {
+            assert!(is_public(ip.parse().unwrap()), "{ip}");
+        }
+        for ip in [
+            "127.0.0.1",
+            "10.0.0.1",
+            "192.168.1.10",
+            "172.16.0.1",
+            "169.254.0.1",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+        ] {
+            assert!(!is_public(ip.parse().unwrap()), "{ip}");
+        }
+    }
+
+    #[test]
+    fn test_subscribe_throughput() {
+        // fails if another test started the collection already
+        let _ = crate::start_metrics_collection();
+        let dir_0 = tempfile::tempdir().unwrap();
+        let node_0 = IrohNode::new(dir_0.path().display().to_string()).unwrap();
+        let dir_1 = tempfile::tempdir().unwrap();
+        let node_1 = IrohNode::new(dir_1.path().display().to_string()).unwrap();
+
+        struct Callback(std::sync::mpsc::Sender<Throughput>);
+        impl ThroughputCallback for Callback {
+            fn throughput(&self, throughput: Throughput) -> Result<(), CallbackError> {
+                self.0.send(throughput).map_err(|_| CallbackError::Error)
+            }
+        }
+        let cb = |s| Arc::new(Callback(s));
+        let (s, r) = std::sync::mpsc::channel();
+        assert!(node_1.subscribe_throughput(0, cb(s.clone())).is_err());
+        let subscription = node_1.subscribe_throughput(50, cb(s)).unwrap();
+
+        let hash = node_0.blobs_add_bytes(vec![1; 1024 * 1024]).unwrap().hash;
+        let addr = node_0.status().unwrap().node_addr();
+        let opts = crate::BlobDownloadOptions::with_nodes(
+            crate::BlobFormat::Raw,
+            vec![addr],
+            Arc::new(crate::SetTagOption::Auto),
+            None,
+        )
+        .unwrap();
+        struct Progress;
+        impl crate::DownloadCallback for Progress {
+            fn progress(
+                &self,
+                _progress: Arc<crate::DownloadProgress>,
+            ) -> Result<(), CallbackError> {
+                Ok(())
+            }
+        }
+        node_1
+            .blobs_download(hash, Arc::new(opts), Arc::new(Progress))
+            .unwrap();
+
+        let sample = r
+            .iter()
+            .find(|sample| sample.download_bytes_per_sec > 0)
+            .unwrap();
+        assert!(sample.upload_bytes_per_sec > 0);
+
+        // no samples after cancelling, the sender is dropped with the sampling task
+        subscription.cancel();
+        while r.recv_timeout(Duration::from_secs(1)).is_ok() {}
+        assert!(r.recv().is_err());
+    }
+
+    #[test]
+    fn test_shutdown() {
+        let dir = tempfile::tempdir().unwrap();
+        let node = IrohNode::new(dir.path().display().to_string()).unwrap();
+        let doc = node.doc_create(None).unwrap();
+        assert!(node.is_running());
+
+        node.shutdown().unwrap();
+        assert!(!node.is_running());
+        // shutting down again is a no-op
+        node.shutdown().unwrap();
+
+        assert!(node.blobs_list().unwrap_err().is_node_stopped());
+        let author = node.author_create();
+        assert!(author.unwrap_err().is_node_stopped());
+        assert!(doc.status().unwrap_err().is_node_stopped());
+    }
+
+    #[test]
+    fn test_data_dir_locked() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().display().to_string();
+        let node = IrohNode::new(path.clone()).unwrap();
+
+        let err = IrohNode::new(path.clone()).err().unwrap();
+        assert!(err.is_data_dir_locked());
+        assert!(err.message().contains(&path));
+        assert!(err
+            .message()
+            .contains(&format!("pid {}", std::process::id())));
+
+        // a different directory is not affected
+        let other = tempfile::tempdir().unwrap();
+        IrohNode::new(other.path().display().to_string()).unwrap();
+        drop(node);
+    }
+
+    #[test]
+    fn test_persistent_with_secret_key() {
+        let key = crate::generate_secret_key();
+        let stored = SecretKey::from_bytes(key.to_bytes()).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().display().to_string();
+        let node = IrohNode::persistent_with_secret_key(path.clone(), Arc::new(stored)).unwrap();
+        assert_eq!(key.public().to_string(), node.node_id());
+        node.shutdown().unwrap();
+        drop(node);
+
+        // without a key, the node falls back to the one in the data directory
+        let node = IrohNode::new(path).unwrap();
+        assert_ne!(key.public().to_string(), node.node_id());
+    }
+
+    #[test]
+    fn test_runtime_worker_threads() {
+        let dir = tempfile::tempdir().unwrap();
+        let opts = NodeOptions {
+            runtime_worker_threads: Some(0),
+            ..Default::default()
+        };
+        assert!(IrohNode::with_options(dir.path().display().to_string(), opts).is_err());
+
+        let opts = NodeOptions {
+            runtime_worker_threads: Some(1),
+            ..Default::default()
+        };
+        let node = IrohNode::with_options(dir.path().display().to_string(), opts).unwrap();
+        assert!(node.is_running());
+    }
+
+    #[test]
+    fn test_node_label() {
+        let dir = tempfile::tempdir().unwrap();
+        let node = IrohNode::new(dir.path().display().to_string()).unwrap();
+        assert_eq!(None, node.status().unwrap().node_label());
+
+        let dir = tempfile::tempdir().unwrap();
+        let opts = NodeOptions {
+            node_label: Some("edge-1".to_string()),
+            ..Default::default()
+        };
+        let node = IrohNode::with_options(dir.path().display().to_string(), opts).unwrap();
+        assert_eq!(
+            Some("edge-1".to_string()),
+            node.status().unwrap().node_label()
+        );
+    }
+
+    #[test]
+    fn test_evaluate_share() {
+        let dir = tempfile::tempdir().unwrap();
+        let node = IrohNode::new(dir.path().display().to_string()).unwrap();
+
+        let id_only = node.evaluate_share(AddrInfoOptions::Id).unwrap();
+        assert_eq!(
+            ShareAssessment {
+                includes_relay: false,
+                public_addresses: 0,
+                private_addresses: 0,
+                reachability: Reachability::Unknown,
+            },
+            id_only
+        );
+
+        let addresses = node.evaluate_share(AddrInfoOptions::Addresses).unwrap();
+        assert!(!addresses.includes_relay);
+        let all = node
+            .evaluate_share(AddrInfoOptions::RelayAndAddresses)
+            .unwrap();
+        assert_eq!(addresses.public_addresses, all.public_addresses);
+        assert_eq!(addresses.private_addresses, all.private_addresses);
+    }
+
+    #[test]
+    fn test_supported_protocols() {
+        let dir = tempfile::tempdir().unwrap();
+        let node = IrohNode::new(dir.path().display().to_string()).unwrap();
+        let alpns = node.supported_protocols();
+        assert_eq!(3, alpns.len());
+        assert!(alpns.contains(&iroh::docs::net::DOCS_ALPN.to_vec()));
+    }
+
+    #[test]
+    fn test_remote_relay() {
+        let dir = tempfile::tempdir().unwrap();
+        let node = IrohNode::new(dir.path().display().to_string()).unwrap();
+
+        let peer = Arc::new(
+            PublicKey::from_string(
+                "ki6htfv2252cj2lhq3hxu4qfcfjtpjnukzonevigudzjpmmruxva".to_string(),
+            )
+            .unwrap(),
+        );
+        assert_eq!(None, node.remote_relay(peer.clone()).unwrap());
+
+        let relay = "https://relay.example.com/".to_string();
+        let addr = NodeAddr::new(&peer, Some(relay.clone()), vec![]);
+        node.add_node_addr(Arc::new(addr)).unwrap();
+        assert_eq!(Some(relay), node.remote_relay(peer).unwrap());
+    }
+
+    #[test]
+    fn test_watch_peer() {
+        let dir = tempfile::tempdir().unwrap();
+        let node = IrohNode::new(dir.path().display().to_string()).unwrap();
+
+        struct Callback {
+            changes: std::sync::mpsc::Sender<ConnType>,
+        }
+        impl ConnectionChangeCallback for Callback {
+            fn changed(&self, conn_type: Arc<ConnectionType>) -> Result<(), CallbackError> {
+                self.changes.send(conn_type.r#type()).ok();
+                Ok(())
+            }
+        }
+
+        // an unknown peer is reported as not connected
+        let peer = PublicKey::from_string(
+            "ki6htfv2252cj2lhq3hxu4qfcfjtpjnukzonevigudzjpmmruxva".to_string(),
+        )
+        .unwrap();
+        let (changes_s, changes_r) = std::sync::mpsc::channel();
+        let watch = node
+            .watch_peer(Arc::new(peer), Arc::new(Callback { changes: changes_s }))
+            .unwrap();
+        let change = changes_r.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(matches!(change, ConnType::None));
+
+        // the watch task ends once cancelled, dropping the callback
+        watch.cancel();
+        assert!(matches!(
+            changes_r.recv_timeout(Duration::from_secs(5)),
+            Err(std::sync::mpsc::RecvTimeoutError::Disconnected)
+        ));
     }
 }
@@ -1,8 +1,9 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use crate::{block_on, BlobFormat, Hash, IrohError, IrohNode};
 use bytes::Bytes;
 use futures::TryStreamExt;
+use iroh::client::MemIroh;
 
 /// A response to a list collections request
 pub struct TagInfo {
@@ -30,6 +31,7 @@ impl IrohNode {
     /// Note: this allocates for each `ListTagsResponse`, if you have many `Tags`s this may be a prohibitively large list.
     /// Please file an [issue](https://github.com/n0-computer/iroh-ffi/issues/new) if you run into this issue
     pub fn tags_list(&self) -> Result<Vec<TagInfo>, IrohError> {
+        self.state.ensure_running()?;
         block_on(&self.rt(), async {
             let tags = self
                 .sync_client
@@ -43,12 +45,238 @@ impl IrohNode {
         })
     }
 
+    /// Get the tag with the given name, if it exists.
+    pub fn tags_get(&self, name: Vec<u8>) -> Result<Option<TagInfo>, IrohError> {
+        self.state.ensure_running()?;
+        block_on(&self.rt(), async {
+            let mut tags = self.sync_client.tags().list().await?;
+            while let Some(tag) = tags.try_next().await? {
+                if tag.name.0 == name {
+                    return Ok(Some(tag.into()));
+                }
+            }
+            Ok(None)
+        })
+    }
+
     /// Delete a tag
     pub fn tags_delete(&self, name: Vec<u8>) -> Result<(), IrohError> {
         let tag = iroh::blobs::Tag(Bytes::from(name));
+        self.state.ensure_running()?;
         block_on(&self.rt(), async {
             self.sync_client.tags().delete(tag).await?;
             Ok(())
         })
     }
+
+    /// Export all tags as a manifest, e.g. to back up or relocate the set of pinned content.
+    ///
+    /// The manifest only holds the tag to hash mapping, the blob data is not included. Restore
+    /// it with [`Self::tags_import_manifest`] on a node that has the blobs.
+    pub fn tags_export_manifest(&self) -> Result<Vec<TagInfo>, IrohError> {
+        self.tags_list()
+    }
+
+    /// Restore the tags of a manifest created by [`Self::tags_export_manifest`].
+    ///
+    /// Existing tags with the same name are overwritten. Tags whose blob is not complete on
+    /// this node are skipped with a warning, as are hash sequences that are not collections;
+    /// an existing tag of the same name is kept in that case. Returns the skipped tags.
+    ///
+    /// Tags named `auto-...` were generated automatically on the exporting node and are
+    /// restored as automatic tags, which eviction may delete.
+    ///
+    /// iroh 0.19 cannot point a tag at an existing hash, so each tag is set by adding its
+    /// local data again, which reads and hashes the whole blob. Tags that already point at the
+    /// right content are left alone.
+    pub fn tags_import_manifest(&self, tags: Vec<TagInfo>) -> Result<Vec<TagInfo>, IrohError> {
+        self.state.ensure_running()?;
+        block_on(&self.rt(), async {
+            let existing = self
+                .sync_client
+                .tags()
+                .list()
+                .await?
+                .map_ok(|tag| (tag.name, (tag.hash, tag.format)))
+                .try_collect::<HashMap<_, _>>()
+                .await?;
+            let mut skipped = Vec::new();
+            for tag in tags {
+                let name = iroh::blobs::Tag(Bytes::from(tag.name.clone()));
+                let previous = existing.get(&name).copied();
+                if let Err(err) = self.import_tag(name, &tag, previous).await {
+                    tracing::warn!(
+                        "skipping tag {:?} for {}: {:?}",
+                        Bytes::from(tag.name.clone()),
+                        tag.hash,
+                        err
+                    );
+                    skipped.push(tag);
+                }
+            }
+            Ok(skipped)
+        })
+    }
+
+    /// Point the tag `name` at `tag.hash`, which must be complete on this node.
+    ///
+    /// `previous` is what the tag pointed at before, it is restored if setting the tag fails.
+    async fn import_tag(
+        &self,
+        name: iroh::blobs::Tag,
+        tag: &TagInfo,
+        previous: Option<(iroh::blobs::Hash, iroh::blobs::BlobFormat)>,
+    ) -> anyhow::Result<()> {
+        let format: iroh::blobs::BlobFormat = tag.format.clone().into();
+        if previous == Some((tag.hash.0, format)) {
+            return Ok(());
+        }
+        let res = set_tag(&self.sync_client, name.clone(), tag.hash.0, &tag.format).await;
+        if let (Err(_), Some((hash, format))) = (&res, previous) {
+            // set_tag only touches the tag once the content checks out, so it is usually
+            // still in place
+            let current = self
+                .sync_client
+                .tags()
+                .list()
+                .await?
+                .try_filter(|info| futures::future::ready(info.name == name))
+                .try_next()
+                .await?;
+            if current.map(|tag| (tag.hash, tag.format)) != Some((hash, format)) {
+                set_tag(&self.sync_client, name, hash, &format.into())
+                    .await
+                    .map_err(|err| anyhow::anyhow!("failed to restore the previous tag: {err}"))?;
+            }
+        }
+        res
+    }
+}
+
+/// Point the tag `name` at `hash` in `format`, overwriting an existing tag of that name.
+///
+/// The blob must be complete on this node, and a hash sequence must be a collection. The
+/// tag is written by adding the local data again under its name: a raw blob is read and
+/// hashed in full, a collection is rebuilt from its metadata. A hash sequence is checked
+/// before the tag is touched; a raw blob read back from the store always hashes to `hash`,
+/// but if it does not, the tag is deleted.
+pub(crate) async fn set_tag(
+    client: &MemIroh,
+    name: iroh::blobs::Tag,
+    hash: iroh::blobs::Hash,
+    format: &BlobFormat,
+) -> anyhow::Result<()> {
+    let blobs = client.blobs();
+    let status = blobs.status(hash).await?;
+    if !matches!(status, iroh::client::blobs::BlobStatus::Complete { .. }) {
+        anyhow::bail!("blob is not complete on this node");
+    }
+    // there is no way to set a tag directly, so re-add the local data under the tag
+    match format {
+        BlobFormat::Raw => {
+            let reader = blobs.read(hash).await?;
+            let opt = iroh::blobs::util::SetTagOption::Named(name.clone());
+            let created = blobs.add_stream(reader, opt).await?.finish().await?.hash;
+            if created != hash {
+                client.tags().delete(name).await?;
+                anyhow::bail!("re-created content has hash {created}");
+            }
+        }
+        BlobFormat::HashSeq => {
+            // collections are small, so build one under an automatic tag first to check that
+            // it has the expected hash
+            let collection = blobs.get_collection(hash).await?;
+            let (created, check_tag) = blobs
+                .create_collection(
+                    collection.clone(),
+                    iroh::blobs::util::SetTagOption::Auto,
+                    vec![],
+                )
+                .await?;
+            if created != hash {
+                client.tags().delete(check_tag).await?;
+                anyhow::bail!("hash sequence is not a collection, re-created as {created}");
+            }
+            let opt = iroh::blobs::util::SetTagOption::Named(name);
+            blobs
+                .create_collection(collection, opt, vec![check_tag])
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tags_get_list_delete() {
+        let dir = tempfile::tempdir().unwrap();
+        let node = IrohNode::new(dir.path().display().to_string()).unwrap();
+
+        let outcome = node.blobs_add_bytes(b"hello".to_vec()).unwrap();
+        assert!(node.tags_get(b"missing".to_vec()).unwrap().is_none());
+
+        let tags = node.tags_list().unwrap();
+        assert_eq!(1, tags.len());
+        assert_eq!(outcome.tag, tags[0].name);
+
+        let tag = node.tags_get(outcome.tag.clone()).unwrap().unwrap();
+        assert_eq!(outcome.hash, tag.hash);
+        assert_eq!(BlobFormat::Raw, tag.format);
+
+        node.tags_delete(outcome.tag.clone()).unwrap();
+        assert!(node.tags_get(outcome.tag).unwrap().is_none());
+        assert!(node.tags_list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_tags_export_import_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let node = IrohNode::new(dir.path().display().to_string()).unwrap();
+
+        let outcome = node.blobs_add_bytes(b"hello".to_vec()).unwrap();
+        let manifest = node.tags_export_manifest().unwrap();
+        assert_eq!(1, manifest.len());
+
+        node.tags_delete(outcome.tag.clone()).unwrap();
+        assert!(node.tags_list().unwrap().is_empty());
+
+        let missing = TagInfo {
+            name: b"missing".to_vec(),
+            format: BlobFormat::Raw,
+            hash: Arc::new(Hash::new(b"not on this node".to_vec())),
+        };
+        let mut manifest = manifest;
+        manifest.push(missing);
+        let skipped = node.tags_import_manifest(manifest).unwrap();
+        assert_eq!(1, skipped.len());
+        assert_eq!(b"missing".to_vec(), skipped[0].name);
+
+        let tag = node.tags_get(outcome.tag).unwrap().unwrap();
+        assert_eq!(outcome.hash, tag.hash);
+        assert_eq!(BlobFormat::Raw, tag.format);
+        assert!(node.tags_get(b"missing".to_vec()).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_tags_import_manifest_keeps_previous_tag() {
+        let dir = tempfile::tempdir().unwrap();
+        let node = IrohNode::new(dir.path().display().to_string()).unwrap();
+
+        let outcome = node.blobs_add_bytes(b"hello".to_vec()).unwrap();
+        // a raw blob is not a collection, so it cannot be tagged as one
+        let bad = TagInfo {
+            name: outcome.tag.clone(),
+            format: BlobFormat::HashSeq,
+            hash: outcome.hash.clone(),
+        };
+        let skipped = node.tags_import_manifest(vec![bad]).unwrap();
+        assert_eq!(1, skipped.len());
+
+        let tag = node.tags_get(outcome.tag).unwrap().unwrap();
+        assert_eq!(outcome.hash, tag.hash);
+        assert_eq!(BlobFormat::Raw, tag.format);
+    }
 }
@@ -1,6 +1,8 @@
 use std::str::FromStr;
 use std::sync::Arc;
 
+use iroh::base::ticket::Ticket;
+
 use crate::blob::{BlobDownloadOptions, BlobFormat, Hash};
 use crate::doc::NodeAddr;
 use crate::error::IrohError;
@@ -8,13 +10,35 @@ use crate::error::IrohError;
 /// A token containing everything to get a file from the provider.
 ///
 /// It is a single item which can be easily serialized and deserialized.
-pub struct BlobTicket(iroh::base::ticket::BlobTicket);
+pub struct BlobTicket(pub(crate) iroh::base::ticket::BlobTicket);
 impl BlobTicket {
     pub fn new(str: String) -> Result<Self, IrohError> {
-        let ticket = iroh::base::ticket::BlobTicket::from_str(&str).map_err(anyhow::Error::from)?;
+        // accept the uppercase form produced by `qr_payload`
+        let ticket = iroh::base::ticket::BlobTicket::from_str(&str.to_ascii_lowercase())
+            .map_err(anyhow::Error::from)?;
+        Ok(BlobTicket(ticket))
+    }
+
+    /// Decode a ticket from its binary encoding, see [`Self::to_bytes`].
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, IrohError> {
+        let ticket =
+            iroh::base::ticket::BlobTicket::from_bytes(&bytes).map_err(anyhow::Error::from)?;
         Ok(BlobTicket(ticket))
     }
 
+    /// The binary encoding of this ticket.
+    ///
+    /// This is the content of the string form without the prefix and base32 encoding, useful
+    /// for transports that can send bytes directly.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0.to_bytes()
+    }
+
+    /// The ticket encoded for use in a QR code, see [`ticket_qr_payload`].
+    pub fn qr_payload(&self) -> String {
+        ticket_qr_payload(self.0.to_string())
+    }
+
     /// The hash of the item this ticket can retrieve.
     pub fn hash(&self) -> Arc<Hash> {
         Arc::new(self.0.hash().into())
@@ -36,6 +60,19 @@ impl BlobTicket {
         self.0.format().is_hash_seq()
     }
 
+    /// The number of direct addresses of the provider in this ticket.
+    ///
+    /// Direct addresses go stale when the provider changes networks, see
+    /// [`IrohNode::blobs_refresh_ticket`](crate::IrohNode::blobs_refresh_ticket).
+    pub fn direct_address_count(&self) -> u32 {
+        direct_address_count([self.0.node_addr()])
+    }
+
+    /// Whether this ticket contains the relay url of the provider.
+    pub fn has_relay(&self) -> bool {
+        has_relay([self.0.node_addr()])
+    }
+
     /// Convert this ticket into input parameters for a call to blobs_download
     pub fn as_download_options(&self) -> Arc<BlobDownloadOptions> {
         let r: BlobDownloadOptions = iroh::client::blobs::DownloadOptions {
@@ -49,7 +86,256 @@ impl BlobTicket {
     }
 }
 
+impl std::fmt::Display for BlobTicket {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Contains both a key (either secret or public) to a document, and a list of peers to join.
+pub struct DocTicket(pub(crate) iroh::docs::DocTicket);
+
+impl DocTicket {
+    pub fn new(str: String) -> Result<Self, IrohError> {
+        // accept the uppercase form produced by `qr_payload`
+        let ticket = iroh::docs::DocTicket::from_str(&str.to_ascii_lowercase())
+            .map_err(anyhow::Error::from)?;
+        Ok(DocTicket(ticket))
+    }
+
+    /// Decode a ticket from its binary encoding, see [`Self::to_bytes`].
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, IrohError> {
+        let ticket = iroh::docs::DocTicket::from_bytes(&bytes).map_err(anyhow::Error::from)?;
+        Ok(DocTicket(ticket))
+    }
+
+    /// The binary encoding of this ticket.
+    ///
+    /// This is the content of the string form without the prefix and base32 encoding, useful
+    /// for transports that can send bytes directly.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0.to_bytes()
+    }
+
+    /// The ticket encoded for use in a QR code, see [`ticket_qr_payload`].
+    pub fn qr_payload(&self) -> String {
+        ticket_qr_payload(self.0.to_string())
+    }
+
+    /// The number of direct addresses in this ticket, summed over all its nodes.
+    ///
+    /// Direct addresses go stale when nodes change networks, see
+    /// [`IrohNode::doc_refresh_ticket`](crate::IrohNode::doc_refresh_ticket).
+    pub fn direct_address_count(&self) -> u32 {
+        direct_address_count(&self.0.nodes)
+    }
+
+    /// Whether this ticket contains a relay url for at least one of its nodes.
+    pub fn has_relay(&self) -> bool {
+        has_relay(&self.0.nodes)
+    }
+}
+
+impl std::fmt::Display for DocTicket {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The prefix of the string form of a [`WorkspaceTicket`].
+const WORKSPACE_TICKET_PREFIX: &str = "workspace";
+
+/// Version of the binary encoding of a [`WorkspaceTicket`].
+const WORKSPACE_TICKET_VERSION: u8 = 0;
+
+/// A bundle of [`DocTicket`]s to join several related documents at once.
+///
+/// Create one with [`IrohNode::doc_share_workspace`](crate::IrohNode::doc_share_workspace)
+/// and join all its documents with
+/// [`IrohNode::doc_join_workspace`](crate::IrohNode::doc_join_workspace).
+pub struct WorkspaceTicket(pub(crate) Vec<iroh::docs::DocTicket>);
+
+impl WorkspaceTicket {
+    pub fn new(str: String) -> Result<Self, IrohError> {
+        // accept the uppercase form produced by `qr_payload`
+        let str = str.to_ascii_lowercase();
+        let encoded = str.strip_prefix(WORKSPACE_TICKET_PREFIX).ok_or_else(|| {
+            anyhow::anyhow!("not a workspace ticket, expected prefix {WORKSPACE_TICKET_PREFIX:?}")
+        })?;
+        let bytes = data_encoding::BASE32_NOPAD
+            .decode(encoded.to_ascii_uppercase().as_bytes())
+            .map_err(|err| anyhow::anyhow!("invalid workspace ticket: {err}"))?;
+        Self::from_bytes(bytes)
+    }
+
+    /// Decode a ticket from its binary encoding, see [`Self::to_bytes`].
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, IrohError> {
+        let invalid = |reason: &str| anyhow::anyhow!("invalid workspace ticket: {reason}");
+        let (version, mut rest) = bytes.split_first().ok_or_else(|| invalid("empty"))?;
+        if *version != WORKSPACE_TICKET_VERSION {
+            return Err(invalid(&format!("unsupported version {version}")).into());
+        }
+        let mut tickets = Vec::new();
+        while !rest.is_empty() {
+            if rest.len() < 4 {
+                return Err(invalid("truncated length").into());
+            }
+            let (len, tail) = rest.split_at(4);
+            let len = u32::from_be_bytes(len.try_into().expect("checked above")) as usize;
+            if tail.len() < len {
+                return Err(invalid("truncated document ticket").into());
+            }
+            let (ticket, tail) = tail.split_at(len);
+            tickets.push(iroh::docs::DocTicket::from_bytes(ticket).map_err(anyhow::Error::from)?);
+            rest = tail;
+        }
+        if tickets.is_empty() {
+            return Err(invalid("no document tickets").into());
+        }
+        Ok(WorkspaceTicket(tickets))
+    }
+
+    /// The binary encoding of this ticket.
+    ///
+    /// This is the content of the string form without the prefix and base32 encoding, useful
+    /// for transports that can send bytes directly.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![WORKSPACE_TICKET_VERSION];
+        for ticket in &self.0 {
+            let ticket = ticket.to_bytes();
+            bytes.extend_from_slice(&(ticket.len() as u32).to_be_bytes());
+            bytes.extend_from_slice(&ticket);
+        }
+        bytes
+    }
+
+    /// The ticket encoded for use in a QR code, see [`ticket_qr_payload`].
+    pub fn qr_payload(&self) -> String {
+        ticket_qr_payload(self.to_string())
+    }
+
+    /// The tickets of the documents in this workspace, in the order they were shared.
+    pub fn doc_tickets(&self) -> Vec<Arc<DocTicket>> {
+        self.0
+            .iter()
+            .map(|ticket| Arc::new(DocTicket(ticket.clone())))
+            .collect()
+    }
+
+    /// The number of direct addresses in this ticket, summed over all its document tickets.
+    pub fn direct_address_count(&self) -> u32 {
+        direct_address_count(self.0.iter().flat_map(|ticket| &ticket.nodes))
+    }
+
+    /// Whether this ticket contains a relay url for at least one node.
+    pub fn has_relay(&self) -> bool {
+        has_relay(self.0.iter().flat_map(|ticket| &ticket.nodes))
+    }
+}
+
+impl From<Vec<iroh::docs::DocTicket>> for WorkspaceTicket {
+    fn from(value: Vec<iroh::docs::DocTicket>) -> Self {
+        WorkspaceTicket(value)
+    }
+}
+
+impl std::fmt::Display for WorkspaceTicket {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut encoded = data_encoding::BASE32_NOPAD.encode(&self.to_bytes());
+        encoded.make_ascii_lowercase();
+        write!(f, "{WORKSPACE_TICKET_PREFIX}{encoded}")
+    }
+}
+
+/// The number of direct addresses of `nodes`.
+fn direct_address_count<'a>(
+    nodes: impl IntoIterator<Item = &'a iroh::net::endpoint::NodeAddr>,
+) -> u32 {
+    let count: usize = nodes
+        .into_iter()
+        .map(|node| node.info.direct_addresses.len())
+        .sum();
+    count.try_into().unwrap_or(u32::MAX)
+}
+
+/// Whether one of `nodes` has a relay url.
+fn has_relay<'a>(nodes: impl IntoIterator<Item = &'a iroh::net::endpoint::NodeAddr>) -> bool {
+    nodes.into_iter().any(|node| node.info.relay_url.is_some())
+}
+
+/// The address of this node to put in place of `addr` when refreshing a ticket.
+///
+/// `current` is the current address of this node. The result has the same kinds of address
+/// info as `addr`: the relay url only if `addr` has one, the direct addresses only if `addr`
+/// has some, so a ticket that was kept small stays small. Errors if `addr` is not an address
+/// of this node.
+pub(crate) fn refresh_node_addr(
+    addr: &iroh::net::endpoint::NodeAddr,
+    current: &iroh::net::endpoint::NodeAddr,
+) -> Result<iroh::net::endpoint::NodeAddr, IrohError> {
+    if addr.node_id != current.node_id {
+        return Err(anyhow::anyhow!(
+            "the ticket refers to node {}, not to this node",
+            addr.node_id.fmt_short()
+        )
+        .into());
+    }
+    let mut refreshed = iroh::net::endpoint::NodeAddr::new(current.node_id);
+    if addr.info.relay_url.is_some() {
+        if let Some(url) = current.info.relay_url.clone() {
+            refreshed = refreshed.with_relay_url(url);
+        }
+    }
+    if !addr.info.direct_addresses.is_empty() {
+        refreshed = refreshed.with_direct_addresses(current.info.direct_addresses.iter().copied());
+    }
+    Ok(refreshed)
+}
+
+/// `ticket` with the address of this node replaced by its current one, `current`.
+///
+/// The other nodes of the ticket are kept as they are. Errors if this node is not one of the
+/// ticket's nodes.
+pub(crate) fn refresh_doc_ticket(
+    ticket: &iroh::docs::DocTicket,
+    current: &iroh::net::endpoint::NodeAddr,
+) -> Result<iroh::docs::DocTicket, IrohError> {
+    if !ticket
+        .nodes
+        .iter()
+        .any(|node| node.node_id == current.node_id)
+    {
+        return Err(anyhow::anyhow!("the ticket does not refer to this node").into());
+    }
+    let nodes = ticket
+        .nodes
+        .iter()
+        .map(|node| {
+            if node.node_id == current.node_id {
+                refresh_node_addr(node, current)
+            } else {
+                Ok(node.clone())
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(iroh::docs::DocTicket {
+        capability: ticket.capability.clone(),
+        nodes,
+    })
+}
+
+/// Encode a blob or document ticket for use in a QR code.
+///
+/// Tickets are base32 encoded, which in uppercase only uses characters of the QR code
+/// alphanumeric mode. Encoding the uppercase form in that mode results in a noticeably
+/// smaller QR code than encoding the ticket as bytes. The uppercase form is accepted
+/// everywhere a ticket is parsed.
+pub fn ticket_qr_payload(ticket: String) -> String {
+    ticket.to_ascii_uppercase()
+}
+
 /// Options when creating a ticket
+#[derive(Debug, Clone, Copy)]
 pub enum AddrInfoOptions {
     /// Only the Node ID is added.
     ///
@@ -75,3 +361,131 @@ impl From<AddrInfoOptions> for iroh::base::node_addr::AddrInfoOptions {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{IrohNode, ShareMode};
+
+    #[test]
+    fn test_refresh_ticket() {
+        let path = tempfile::tempdir().unwrap();
+        let node = IrohNode::new(path.path().to_string_lossy().into_owned()).unwrap();
+        let current: iroh::net::endpoint::NodeAddr = (*node.status().unwrap().node_addr())
+            .clone()
+            .try_into()
+            .unwrap();
+        let stale = iroh::net::endpoint::NodeAddr::new(current.node_id)
+            .with_direct_addresses(["127.0.0.1:1".parse().unwrap()]);
+
+        // a blob ticket gets the current addresses, and no relay as it had none
+        let hash = node.blobs_add_bytes(b"hello".to_vec()).unwrap().hash;
+        let ticket = BlobTicket(
+            iroh::base::ticket::BlobTicket::new(
+                stale.clone(),
+                hash.0,
+                iroh::blobs::BlobFormat::Raw,
+            )
+            .unwrap(),
+        );
+        assert_eq!(1, ticket.direct_address_count());
+        assert!(!ticket.has_relay());
+        let refreshed = node.blobs_refresh_ticket(Arc::new(ticket)).unwrap();
+        assert_eq!(hash, refreshed.hash());
+        assert_eq!(
+            current.info.direct_addresses,
+            refreshed.0.node_addr().info.direct_addresses
+        );
+        assert!(!refreshed.has_relay());
+
+        // a doc ticket keeps its capability, other nodes stay as they are
+        let doc = node.doc_create(None).unwrap();
+        let shared = doc.share(ShareMode::Read, AddrInfoOptions::Id).unwrap();
+        let capability = iroh::docs::DocTicket::from_str(&shared).unwrap().capability;
+        let other =
+            iroh::net::endpoint::NodeAddr::new(iroh::net::key::SecretKey::generate().public())
+                .with_direct_addresses(["127.0.0.1:2".parse().unwrap()]);
+        let ticket = DocTicket(iroh::docs::DocTicket {
+            capability,
+            nodes: vec![stale, other.clone()],
+        });
+        assert_eq!(2, ticket.direct_address_count());
+        let refreshed = node.doc_refresh_ticket(Arc::new(ticket)).unwrap();
+        assert_eq!(doc.id(), refreshed.0.capability.id().to_string());
+        assert_eq!(
+            current.info.direct_addresses,
+            refreshed.0.nodes[0].info.direct_addresses
+        );
+        assert_eq!(other, refreshed.0.nodes[1]);
+
+        // tickets of other nodes are rejected
+        let ticket = DocTicket(iroh::docs::DocTicket {
+            capability: refreshed.0.capability.clone(),
+            nodes: vec![other],
+        });
+        assert!(node.doc_refresh_ticket(Arc::new(ticket)).is_err());
+    }
+
+    #[test]
+    fn test_ticket_bytes_roundtrip() {
+        let path = tempfile::tempdir().unwrap();
+        let node = IrohNode::new(path.path().to_string_lossy().into_owned()).unwrap();
+
+        let doc = node.doc_create(None).unwrap();
+        let doc_ticket = doc
+            .share(ShareMode::Write, AddrInfoOptions::RelayAndAddresses)
+            .unwrap();
+        let ticket = DocTicket::new(doc_ticket.clone()).unwrap();
+        let bytes = ticket.to_bytes();
+        assert!(bytes.len() < doc_ticket.len());
+        let ticket = DocTicket::from_bytes(bytes).unwrap();
+        assert_eq!(doc_ticket, ticket.to_string());
+
+        let hash = node.blobs_add_bytes(b"hello".to_vec()).unwrap().hash;
+        let blob_ticket = node
+            .blobs_share(hash, BlobFormat::Raw, AddrInfoOptions::RelayAndAddresses)
+            .unwrap();
+        let ticket = BlobTicket::new(blob_ticket.clone()).unwrap();
+        let ticket = BlobTicket::from_bytes(ticket.to_bytes()).unwrap();
+        assert_eq!(blob_ticket, ticket.to_string());
+    }
+
+    #[test]
+    fn test_workspace_ticket() {
+        let path = tempfile::tempdir().unwrap();
+        let node = IrohNode::new(path.path().to_string_lossy().into_owned()).unwrap();
+        let path_1 = tempfile::tempdir().unwrap();
+        let node_1 = IrohNode::new(path_1.path().to_string_lossy().into_owned()).unwrap();
+
+        let docs = [
+            node.doc_create(None).unwrap(),
+            node.doc_create(None).unwrap(),
+        ];
+        let ids = docs.iter().map(|doc| doc.id()).collect::<Vec<_>>();
+        let ticket = node
+            .doc_share_workspace(ids.clone(), ShareMode::Read, AddrInfoOptions::Id)
+            .unwrap();
+        assert_eq!(2, ticket.doc_tickets().len());
+
+        // string, QR and byte forms roundtrip
+        let str = ticket.to_string();
+        assert!(str.starts_with("workspace"));
+        let parsed = WorkspaceTicket::new(ticket.qr_payload()).unwrap();
+        assert_eq!(str, parsed.to_string());
+        let parsed = WorkspaceTicket::from_bytes(ticket.to_bytes()).unwrap();
+        assert_eq!(str, parsed.to_string());
+        assert!(WorkspaceTicket::new("workspace".to_string()).is_err());
+        assert!(WorkspaceTicket::from_bytes(vec![0, 0, 0, 0, 9]).is_err());
+
+        let joined = node_1.doc_join_workspace(Arc::new(parsed)).unwrap();
+        let joined_ids = joined.iter().map(|doc| doc.id()).collect::<Vec<_>>();
+        assert_eq!(ids, joined_ids);
+
+        // sharing a document that does not exist fails
+        let missing = node_1.doc_create(None).unwrap().id();
+        node_1.doc_drop(missing.clone()).unwrap();
+        assert!(node_1
+            .doc_share_workspace(vec![missing], ShareMode::Read, AddrInfoOptions::Id)
+            .is_err());
+    }
+}